@@ -0,0 +1,43 @@
+// Order finding for a = 7, N = 15, compiled down to 7 qubits: three counting
+// qubits q[0..2] and the work register q[3..6]. Multiplying by 7 mod 15 is a
+// rotation of the work bits followed by NOTs, multiplying by 7^2 = 4 two swaps,
+// and 7^4 = 1 needs no gates at all. The counting register reads y / 8 with
+// y in {0, 2, 4, 6}, so r = 4 and gcd(7^2 ± 1, 15) = 3 and 5.
+OPENQASM 2.0;
+include "qelib1.inc";
+
+qreg q[7];
+creg c[3];
+
+// |1> in the work register, the counting register in superposition
+x q[3];
+h q[0];
+h q[1];
+h q[2];
+
+// controlled multiplication by 7 mod 15
+cswap q[0], q[3], q[4];
+cswap q[0], q[4], q[5];
+cswap q[0], q[5], q[6];
+cx q[0], q[3];
+cx q[0], q[4];
+cx q[0], q[5];
+cx q[0], q[6];
+
+// controlled multiplication by 4 mod 15
+cswap q[1], q[4], q[6];
+cswap q[1], q[3], q[5];
+
+// inverse QFT on the counting register
+swap q[0], q[2];
+h q[0];
+cu1(-pi/2) q[0], q[1];
+h q[1];
+cu1(-pi/4) q[0], q[2];
+cu1(-pi/2) q[1], q[2];
+h q[2];
+
+barrier q;
+measure q[0] -> c[0];
+measure q[1] -> c[1];
+measure q[2] -> c[2];
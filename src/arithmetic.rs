@@ -0,0 +1,100 @@
+// Reversible arithmetic as gate-level circuits, the pieces of the modular
+// exponentiation oracle that `ModMul` otherwise simulates as a black box.
+//
+// Registers are slices of qubit indices, least significant bit first.
+// Everything returns plain gate lists, so adding a control to every gate
+// gives the controlled version, and reversing them with `Gate::inverse` the inverse.
+
+mod beauregard;
+mod cuccaro;
+mod draper;
+
+pub use beauregard::Beauregard;
+pub use cuccaro::cuccaro_add;
+pub use draper::{draper_add, draper_add_constant, fourier_qubit, phi_add_constant};
+
+use crate::quantum::Gate;
+use std::error::Error;
+use std::fmt;
+
+// x -> a x mod n is only reversible, and only has a circuit, when gcd(a, n) = 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotInvertible {
+    pub a: u64,
+    pub n: u64,
+}
+
+impl fmt::Display for NotInvertible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not invertible mod {}, multiplying by it is not reversible", self.a, self.n)
+    }
+}
+
+impl Error for NotInvertible {}
+
+// The same gates, each with the extra controls
+pub fn controlled(gates: Vec<Gate>, controls: &[usize]) -> Vec<Gate> {
+    gates
+        .into_iter()
+        .map(|g| controls.iter().fold(g, |g, &c| g.controlled(c)))
+        .collect()
+}
+
+// The gates that undo `gates`
+pub fn inverse(gates: &[Gate]) -> Vec<Gate> {
+    gates.iter().rev().map(Gate::inverse).collect()
+}
+
+// Helpers for the tests of the arithmetic circuits, which map basis states to basis states
+#[cfg(test)]
+mod testing {
+    use crate::quantum::{Gate, Simulator, StateVector};
+    use num_complex::Complex64;
+
+    // The basis state |index> after `gates`, which must leave it a basis state
+    pub fn run_on_basis(num_qubits: usize, index: usize, gates: &[Gate]) -> usize {
+        let mut state = StateVector::basis(num_qubits, index).unwrap();
+        for gate in gates {
+            state.apply(gate);
+        }
+        state
+            .probabilities()
+            .iter()
+            .position(|&p| p > 1.0 - 1e-9)
+            .unwrap_or_else(|| panic!("|{}> did not end up in a basis state", index))
+    }
+
+    // Where `gates` take each of the basis states |indices[i]>, found in one run on a
+    // superposition that gives each of them a different weight
+    pub fn run_on_basis_states(num_qubits: usize, indices: &[usize], gates: &[Gate]) -> Vec<usize> {
+        let total = (indices.len() * (indices.len() + 1) / 2) as f64;
+        let weight = |i: usize| (i + 1) as f64 / total;
+        let mut amplitudes = vec![Complex64::new(0.0, 0.0); 1 << num_qubits];
+        for (i, &index) in indices.iter().enumerate() {
+            amplitudes[index] = Complex64::new(weight(i).sqrt(), 0.0);
+        }
+        let mut state = StateVector::from_amplitudes(amplitudes).unwrap();
+        for gate in gates {
+            state.apply(gate);
+        }
+        let probabilities = state.probabilities();
+        (0..indices.len())
+            .map(|i| {
+                probabilities
+                    .iter()
+                    .position(|&p| (p - weight(i)).abs() < 1e-9)
+                    .unwrap_or_else(|| panic!("|{}> did not end up in a basis state", indices[i]))
+            })
+            .collect()
+    }
+
+    // Basis index with `value` on `register` (bit k on register[k])
+    pub fn place(register: &[usize], value: u64) -> usize {
+        register.iter().enumerate().fold(0, |index, (k, &q)| index | ((value >> k & 1) as usize) << q)
+    }
+
+    // The value `register` holds in basis state `index`
+    pub fn read(register: &[usize], index: usize) -> u64 {
+        register.iter().enumerate().fold(0, |value, (k, &q)| value | ((index >> q & 1) as u64) << k)
+    }
+}
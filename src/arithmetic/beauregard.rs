@@ -0,0 +1,191 @@
+use super::{controlled, inverse, phi_add_constant, NotInvertible};
+use crate::circuit::{mod_inverse, Qft};
+use crate::quantum::Gate;
+
+// Beauregard's 2n+3 qubit construction of controlled modular exponentiation
+// (quant-ph/0205095), n = bits of the modulus:
+//
+//   x        n qubits, the value being multiplied
+//   b        n + 1 qubits, |0> outside the multiplier (the top bit catches overflow)
+//   ancilla  1 qubit, |0> outside the modular adder
+//
+// plus the control qubit(s), one in the semi-classical setting, for 2n+3 in all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beauregard {
+    pub x: Vec<usize>,
+    pub b: Vec<usize>,
+    pub ancilla: usize,
+    pub n: u64,
+    pub qft: Qft,
+}
+
+impl Beauregard {
+    // Registers for modulus n laid out from qubit `first` on: x, then b, then the ancilla
+    pub fn new(n: u64, first: usize, qft: Qft) -> Self {
+        let bits = (u64::BITS - n.leading_zeros()) as usize;
+        let x = (first..first + bits).collect();
+        let b = (first + bits..first + 2 * bits + 1).collect();
+        Beauregard { x, b, ancilla: first + 2 * bits + 1, n, qft }
+    }
+
+    // Qubits used besides the controls: 2n + 2
+    pub fn num_qubits(&self) -> usize {
+        self.x.len() + self.b.len() + 1
+    }
+
+    // φADD(a)MOD(N): b <- b + a mod N on the Fourier-space b, for b < N and a < N,
+    // when every control is |1>. The ancilla detects the overflow past N.
+    pub fn phi_add_mod(&self, a: u64, controls: &[usize]) -> Vec<Gate> {
+        let a = a % self.n;
+        let b = &self.b;
+        let msb = b[b.len() - 1];
+        let mut gates = Vec::new();
+
+        gates.extend(phi_add_constant(b, a, controls, &self.qft));
+        gates.extend(inverse(&phi_add_constant(b, self.n, &[], &self.qft)));
+        // b < 0 after subtracting N shows in the top bit, copy it to the ancilla
+        gates.extend(self.qft.inverse_gates(b));
+        gates.push(Gate::cnot(msb, self.ancilla));
+        gates.extend(self.qft.gates(b));
+        gates.extend(phi_add_constant(b, self.n, &[self.ancilla], &self.qft));
+        // Clear the ancilla again: b + a mod N >= a exactly when no N was added back
+        gates.extend(inverse(&phi_add_constant(b, a, controls, &self.qft)));
+        gates.extend(self.qft.inverse_gates(b));
+        gates.push(Gate::x(msb));
+        gates.push(Gate::cnot(msb, self.ancilla));
+        gates.push(Gate::x(msb));
+        gates.extend(self.qft.gates(b));
+        gates.extend(phi_add_constant(b, a, controls, &self.qft));
+        gates
+    }
+
+    // CMULT(a)MOD(N): b <- b + a x mod N when `control` is |1>
+    pub fn cmult_mod(&self, a: u64, control: usize) -> Vec<Gate> {
+        let mut gates = self.qft.gates(&self.b);
+        let mut term = a % self.n;
+        for &xi in &self.x {
+            gates.extend(self.phi_add_mod(term, &[control, xi]));
+            term = (term as u128 * 2 % self.n as u128) as u64;
+        }
+        gates.extend(self.qft.inverse_gates(&self.b));
+        gates
+    }
+
+    // Controlled U_a: x <- a x mod N in place, an error unless gcd(a, N) = 1.
+    // Multiply into b, swap b and x, then un-multiply by a^-1 to clear b.
+    pub fn controlled_mod_mul(&self, a: u64, control: usize) -> Result<Vec<Gate>, NotInvertible> {
+        let a_inverse = mod_inverse(a % self.n, self.n).ok_or(NotInvertible { a, n: self.n })?;
+        let mut gates = self.cmult_mod(a, control);
+        for (&xi, &bi) in self.x.iter().zip(&self.b) {
+            gates.extend(controlled(Gate::swap(xi, bi).to_vec(), &[control]));
+        }
+        gates.extend(inverse(&self.cmult_mod(a_inverse, control)));
+        Ok(gates)
+    }
+
+    // Controlled modular exponentiation: x <- a^c x mod N, where c is the value of
+    // `controls` (least significant first), as one controlled U_(a^(2^j)) per control qubit
+    pub fn controlled_mod_exp(&self, a: u64, controls: &[usize]) -> Result<Vec<Gate>, NotInvertible> {
+        let mut gates = Vec::new();
+        let mut multiplier = a % self.n;
+        for &c in controls {
+            gates.extend(self.controlled_mod_mul(multiplier, c).map_err(|_| NotInvertible { a, n: self.n })?);
+            multiplier = (multiplier as u128 * multiplier as u128 % self.n as u128) as u64;
+        }
+        Ok(gates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::testing::{place, read, run_on_basis_states};
+    use crate::math::gcd;
+    use num_bigint::BigUint;
+
+    fn coprime(a: u64, n: u64) -> bool {
+        gcd(&BigUint::from(a), &BigUint::from(n)) == BigUint::from(1u32)
+    }
+
+    #[test]
+    fn adds_mod_n() {
+        for n in [5, 7, 11] {
+            // Two controls on qubits 0 and 1, both set
+            let circuit = Beauregard::new(n, 2, Qft::exact());
+            let qubits = 2 + circuit.num_qubits();
+            for a in 0..n {
+                let mut gates = circuit.qft.gates(&circuit.b);
+                gates.extend(circuit.phi_add_mod(a, &[0, 1]));
+                gates.extend(circuit.qft.inverse_gates(&circuit.b));
+                let inputs: Vec<usize> = (0..n).map(|b| place(&circuit.b, b) | 0b11).collect();
+                for (b, out) in (0..n).zip(run_on_basis_states(qubits, &inputs, &gates)) {
+                    assert_eq!(read(&circuit.b, out), (a + b) % n, "{} + {} mod {}", a, b, n);
+                    assert_eq!(out >> circuit.ancilla & 1, 0, "the ancilla is not clean");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multiplies_and_adds_mod_n() {
+        let n = 7;
+        let circuit = Beauregard::new(n, 1, Qft::exact());
+        let qubits = 1 + circuit.num_qubits();
+        for a in 1..n {
+            let gates = circuit.cmult_mod(a, 0);
+            for b in [0, 3] {
+                for on in [0, 1] {
+                    let inputs: Vec<usize> = (0..n).map(|x| place(&circuit.x, x) | place(&circuit.b, b) | on).collect();
+                    for (x, out) in (0..n).zip(run_on_basis_states(qubits, &inputs, &gates)) {
+                        assert_eq!(read(&circuit.x, out), x);
+                        let expected = if on == 1 { (b + a * x) % n } else { b };
+                        assert_eq!(read(&circuit.b, out), expected, "{} + {} * {} mod {}", b, a, x, n);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multiplies_in_place_mod_n() {
+        for n in [5, 7, 15] {
+            let circuit = Beauregard::new(n, 1, Qft::exact());
+            let qubits = 1 + circuit.num_qubits();
+            for a in (2..n).filter(|&a| coprime(a, n)) {
+                let gates = circuit.controlled_mod_mul(a, 0).unwrap();
+                for on in [0, 1] {
+                    let inputs: Vec<usize> = (0..n).map(|x| place(&circuit.x, x) | on).collect();
+                    for (x, out) in (0..n).zip(run_on_basis_states(qubits, &inputs, &gates)) {
+                        let expected = if on == 1 { a * x % n } else { x };
+                        assert_eq!(read(&circuit.x, out), expected, "{} * {} mod {}", a, x, n);
+                        assert_eq!(read(&circuit.b, out), 0, "b is not clean");
+                        assert_eq!(out >> circuit.ancilla & 1, 0, "the ancilla is not clean");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exponentiates_mod_n() {
+        // x <- 2^c x mod 5 for every 2-bit exponent c on qubits 0 and 1
+        let n = 5;
+        let circuit = Beauregard::new(n, 2, Qft::exact());
+        let gates = circuit.controlled_mod_exp(2, &[0, 1]).unwrap();
+        for c in 0..4 {
+            let inputs: Vec<usize> = (1..n).map(|x| place(&circuit.x, x) | c).collect();
+            for (x, out) in (1..n).zip(run_on_basis_states(2 + circuit.num_qubits(), &inputs, &gates)) {
+                assert_eq!(read(&circuit.x, out), (1 << c) * x % n, "2^{} * {} mod {}", c, x, n);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_multipliers_without_an_inverse() {
+        let circuit = Beauregard::new(15, 1, Qft::exact());
+        for a in [0, 3, 5, 15] {
+            assert_eq!(circuit.controlled_mod_mul(a, 0), Err(NotInvertible { a, n: 15 }));
+        }
+        assert_eq!(circuit.controlled_mod_exp(10, &[0]), Err(NotInvertible { a: 10, n: 15 }));
+    }
+}
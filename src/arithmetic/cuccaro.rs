@@ -0,0 +1,76 @@
+use crate::quantum::Gate;
+
+// Cuccaro's ripple-carry adder: b += a, with one ancilla `carry_in` (|0> before
+// and after) and, when given, the carry out XORed into `carry_out`.
+// a and b have the same length n; 2n Toffolis, no QFT.
+pub fn cuccaro_add(a: &[usize], b: &[usize], carry_in: usize, carry_out: Option<usize>) -> Vec<Gate> {
+    assert_eq!(a.len(), b.len(), "Cuccaro adder registers must have the same size");
+    let n = a.len();
+    let mut gates = Vec::with_capacity(6 * n + 1);
+    if n == 0 {
+        return gates;
+    }
+    // Carry into bit i lives on the previous a qubit (or carry_in for bit 0)
+    let carry = |i: usize| if i == 0 { carry_in } else { a[i - 1] };
+
+    for i in 0..n {
+        gates.extend(maj(carry(i), b[i], a[i]));
+    }
+    if let Some(z) = carry_out {
+        gates.push(Gate::cnot(a[n - 1], z));
+    }
+    for i in (0..n).rev() {
+        gates.extend(uma(carry(i), b[i], a[i]));
+    }
+    gates
+}
+
+// Majority: leaves the carry out of (c, b, a) on a
+fn maj(c: usize, b: usize, a: usize) -> [Gate; 3] {
+    [Gate::cnot(a, b), Gate::cnot(a, c), Gate::toffoli(c, b, a)]
+}
+
+// UnMajority and Add: restores a and c, leaves the sum bit on b
+fn uma(c: usize, b: usize, a: usize) -> [Gate; 3] {
+    [Gate::toffoli(c, b, a), Gate::cnot(a, c), Gate::cnot(c, b)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::testing::{place, read, run_on_basis};
+
+    #[test]
+    fn adds_with_carry_out() {
+        for n in 1..=4 {
+            // a, b interleaved, then the carry in and carry out
+            let a: Vec<usize> = (0..n).map(|i| 2 * i).collect();
+            let b: Vec<usize> = (0..n).map(|i| 2 * i + 1).collect();
+            let (carry_in, carry_out) = (2 * n, 2 * n + 1);
+            let gates = cuccaro_add(&a, &b, carry_in, Some(carry_out));
+            for x in 0..1u64 << n {
+                for y in 0..1u64 << n {
+                    let out = run_on_basis(2 * n + 2, place(&a, x) | place(&b, y), &gates);
+                    assert_eq!(read(&a, out), x);
+                    assert_eq!(read(&b, out), (x + y) % (1 << n), "{} + {} on {} bits", x, y, n);
+                    assert_eq!(out >> carry_in & 1, 0, "the carry-in ancilla is not clean");
+                    assert_eq!((out >> carry_out & 1) as u64, (x + y) >> n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adds_without_carry_out() {
+        let n = 3;
+        let a: Vec<usize> = (0..n).collect();
+        let b: Vec<usize> = (n..2 * n).collect();
+        let gates = cuccaro_add(&a, &b, 2 * n, None);
+        for x in 0..8 {
+            for y in 0..8 {
+                let out = run_on_basis(2 * n + 1, place(&a, x) | place(&b, y), &gates);
+                assert_eq!((read(&a, out), read(&b, out)), (x, (x + y) % 8));
+            }
+        }
+    }
+}
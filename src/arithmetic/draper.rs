@@ -0,0 +1,109 @@
+use crate::circuit::Qft;
+use crate::quantum::Gate;
+use std::f64::consts::TAU;
+
+// The qubit holding bit k of a register's Fourier-space value.
+// Without the final swaps the QFT leaves the register in reverse order.
+pub fn fourier_qubit(register: &[usize], k: usize, qft: &Qft) -> usize {
+    if qft.skip_swaps { register[register.len() - 1 - k] } else { register[k] }
+}
+
+// Draper's φADD(a): adds the constant a (mod 2^m) to a register that is already
+// in Fourier space (after `qft.gates(register)`). Only single-qubit phases,
+// bit k gets 2π a 2^k / 2^m, and every one of them carries `controls`.
+pub fn phi_add_constant(register: &[usize], a: u64, controls: &[usize], qft: &Qft) -> Vec<Gate> {
+    let m = register.len();
+    let modulus = 1u128 << m;
+    let mut gates = Vec::with_capacity(m);
+    for k in 0..m {
+        // Only a 2^k mod 2^m matters, whole turns drop out
+        let turns = ((a as u128) << k) % modulus;
+        if turns == 0 {
+            continue;
+        }
+        let mut gate = Gate::phase(fourier_qubit(register, k, qft), TAU * turns as f64 / modulus as f64);
+        for &c in controls {
+            gate = gate.controlled(c);
+        }
+        gates.push(gate);
+    }
+    gates
+}
+
+// register += a (mod 2^m) in the computational basis: QFT, φADD(a), inverse QFT
+pub fn draper_add_constant(register: &[usize], a: u64, controls: &[usize], qft: &Qft) -> Vec<Gate> {
+    let mut gates = qft.gates(register);
+    gates.extend(phi_add_constant(register, a, controls, qft));
+    gates.extend(qft.inverse_gates(register));
+    gates
+}
+
+// Draper's quantum-quantum adder: y += x (mod 2^|y|), x unchanged.
+// In Fourier space bit k of y picks up 2π x_j 2^(j+k) / 2^m from each bit j of x.
+pub fn draper_add(x: &[usize], y: &[usize], qft: &Qft) -> Vec<Gate> {
+    let m = y.len();
+    let mut gates = qft.gates(y);
+    for k in 0..m {
+        for (j, &xj) in x.iter().enumerate() {
+            if j + k >= m {
+                break;
+            }
+            let turns = 1u128 << (j + k);
+            gates.push(Gate::cphase(xj, fourier_qubit(y, k, qft), TAU * turns as f64 / (1u128 << m) as f64));
+        }
+    }
+    gates.extend(qft.inverse_gates(y));
+    gates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::testing::{place, read, run_on_basis};
+
+    #[test]
+    fn adds_a_constant() {
+        for qft in [Qft::exact(), Qft { skip_swaps: true, ..Qft::exact() }] {
+            for m in 1..=4 {
+                let register: Vec<usize> = (0..m).collect();
+                for a in 0..1u64 << m {
+                    for x in 0..1u64 << m {
+                        let out = run_on_basis(m, place(&register, x), &draper_add_constant(&register, a, &[], &qft));
+                        assert_eq!(read(&register, out), (x + a) % (1 << m), "{} + {} on {} bits", x, a, m);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adds_a_constant_only_under_its_control() {
+        let (register, control) = ([1, 2, 3], 0);
+        for x in 0..8 {
+            for on in [false, true] {
+                let index = place(&register, x) | on as usize;
+                let out = run_on_basis(4, index, &draper_add_constant(&register, 5, &[control], &Qft::exact()));
+                assert_eq!(out & 1, on as usize);
+                assert_eq!(read(&register, out), if on { (x + 5) % 8 } else { x });
+            }
+        }
+    }
+
+    #[test]
+    fn adds_a_register() {
+        for qft in [Qft::exact(), Qft { skip_swaps: true, ..Qft::exact() }] {
+            // |x| = 2 into |y| = 3, and the same widths
+            for (k, m) in [(2, 3), (3, 3)] {
+                let x: Vec<usize> = (0..k).collect();
+                let y: Vec<usize> = (k..k + m).collect();
+                for a in 0..1u64 << k {
+                    for b in 0..1u64 << m {
+                        let out = run_on_basis(k + m, place(&x, a) | place(&y, b), &draper_add(&x, &y, &qft));
+                        assert_eq!(read(&x, out), a);
+                        assert_eq!(read(&y, out), (a + b) % (1 << m), "{} + {} on {} bits", a, b, m);
+                    }
+                }
+            }
+        }
+    }
+}
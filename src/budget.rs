@@ -0,0 +1,162 @@
+use crate::config::Config;
+use num_bigint::BigUint;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Cooperative cancellation. Clone it, hand a copy to `Config`, and call
+// `cancel` from any thread; the factoring loop notices at its next check.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Why a run stopped before it was done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Deadline,
+    Cancelled,
+}
+
+// What had been learned when a run gave up
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    // Values of 'a' tried for the split that gave up
+    pub attempts: u64,
+    pub elapsed: Duration,
+    // (a, r) pairs whose period was found but gave no factor
+    pub periods: Vec<(BigUint, BigUint)>,
+    // Prime factors already found, with multiplicity (only `factorize` fills this)
+    pub factors: Vec<(BigUint, u32)>,
+    // Parts of N not split yet, with multiplicity
+    pub remaining: Vec<(BigUint, u32)>,
+    // Seed of the generator that picked the bases, when `factor` created it
+    pub seed: Option<u64>,
+}
+
+// The wall-clock deadline and cancellation token of one run
+#[derive(Debug, Clone)]
+pub struct Budget {
+    start: Instant,
+    deadline: Option<Instant>,
+    cancel: Option<CancelToken>,
+}
+
+impl Budget {
+    // Start the clock for a run under `config`
+    pub fn start(config: &Config) -> Self {
+        let start = Instant::now();
+        Budget {
+            start,
+            deadline: config.timeout.map(|t| start + t),
+            cancel: config.cancel.clone(),
+        }
+    }
+
+    // A budget that never runs out
+    pub fn unlimited() -> Self {
+        Budget { start: Instant::now(), deadline: None, cancel: None }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    // Err when the run should stop now
+    pub fn check(&self) -> Result<(), Interrupt> {
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(Interrupt::Cancelled);
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(Interrupt::Deadline);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FactorError;
+    use crate::events::Event;
+    use crate::{factor, factor_observed, factorize_observed};
+
+    #[test]
+    fn check_reports_why() {
+        assert_eq!(Budget::unlimited().check(), Ok(()));
+        let cancel = CancelToken::new();
+        let config = Config { timeout: Some(Duration::from_secs(3600)), cancel: Some(cancel.clone()), ..Config::default() };
+        let budget = Budget::start(&config);
+        assert_eq!(budget.check(), Ok(()));
+        cancel.cancel();
+        assert_eq!(budget.check(), Err(Interrupt::Cancelled));
+        assert_eq!(Budget::start(&Config { timeout: Some(Duration::ZERO), ..Config::default() }).check(), Err(Interrupt::Deadline));
+    }
+
+    #[test]
+    fn expired_deadline_interrupts_before_the_first_base() {
+        let config = Config { timeout: Some(Duration::ZERO), seed: Some(3), ..Config::default() };
+        match factor(&BigUint::from(15u32), &config) {
+            Err(FactorError::Interrupted(Interrupt::Deadline, progress)) => {
+                assert_eq!(progress.attempts, 0);
+                assert_eq!(progress.remaining, vec![(BigUint::from(15u32), 1)]);
+                assert_eq!(progress.seed, Some(3));
+            }
+            other => panic!("expected a deadline interruption, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cancelling_during_period_finding_keeps_the_attempts() {
+        // Orders mod this N run to about 10^12, far past the first budget check
+        let n = BigUint::from(1_000_003u64 * 1_000_033);
+        let cancel = CancelToken::new();
+        let config = Config { cancel: Some(cancel.clone()), seed: Some(1), ..Config::default() };
+        let mut observer = |event: &Event| {
+            if let Event::FindingPeriod { .. } = event {
+                cancel.cancel();
+            }
+        };
+        match factor_observed(&n, &config, &mut observer) {
+            Err(FactorError::Interrupted(Interrupt::Cancelled, progress)) => {
+                assert_eq!(progress.attempts, 1);
+                assert_eq!(progress.seed, Some(1));
+            }
+            other => panic!("expected a cancellation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cancelled_factorization_reports_what_is_left() {
+        let n = BigUint::from(3u32 * 5 * 7 * 11 * 13);
+        let cancel = CancelToken::new();
+        let config = Config { cancel: Some(cancel.clone()), seed: Some(2), ..Config::default() };
+        // Stop once the first split is done
+        let mut observer = |event: &Event| {
+            if let Event::GcdFactor { .. } | Event::FactorFound { .. } = event {
+                cancel.cancel();
+            }
+        };
+        match factorize_observed(&n, &config, &mut observer) {
+            Err(FactorError::Interrupted(Interrupt::Cancelled, progress)) => {
+                assert_eq!(progress.attempts, 0);
+                assert!(!progress.remaining.is_empty());
+                let product = progress.factors.iter().chain(&progress.remaining).fold(BigUint::from(1u32), |acc, (p, k)| acc * p.pow(*k));
+                assert_eq!(product, n);
+            }
+            other => panic!("expected a cancellation, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+// Circuits: the list of operations generators emit and simulators run.
+
+use crate::quantum::{Gate, NoiseModel, SimError, Simulator, SimulatorKind, ORACLE_NOISE};
+use rand::RngCore;
+use std::collections::BTreeMap;
+
+mod optimize;
+mod order_finding;
+mod qasm;
+mod qft;
+
+pub use optimize::{CircuitStats, OptimizationReport, Optimizer, Pass, DEFAULT_MIN_ANGLE};
+pub use order_finding::{modexp_circuit, order_finding_circuit, semiclassical_order_finding_circuit, OrderFindingCircuit, Oracle};
+pub use qasm::{from_qasm2, to_qasm3, QasmError, MAX_PERMUTATION_MODULUS};
+pub use qft::{dft, fidelity, qft_fidelity, Qft};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Gate(Gate),
+    // Measure `qubit` into classical bit `clbit`
+    Measure { qubit: usize, clbit: usize },
+    Reset(usize),
+    // `gate`, applied only when the classical bits read `condition.value`
+    Conditional { condition: Condition, gate: Gate },
+    // |x> -> |a x mod n> on `register` for x < n (other values untouched),
+    // when every control is |1>. Simulated as a permutation, not as gates.
+    ModMul { controls: Vec<usize>, register: Vec<usize>, a: u64, n: u64 },
+}
+
+impl Instruction {
+    // Every qubit the instruction acts on
+    pub fn qubits(&self) -> Vec<usize> {
+        match self {
+            Instruction::Gate(g) | Instruction::Conditional { gate: g, .. } => g.qubits().collect(),
+            Instruction::Measure { qubit, .. } | Instruction::Reset(qubit) => vec![*qubit],
+            Instruction::ModMul { controls, register, .. } => controls.iter().chain(register).copied().collect(),
+        }
+    }
+
+    // Classical bits it writes or reads
+    pub fn clbits(&self) -> Vec<usize> {
+        match self {
+            Instruction::Measure { clbit, .. } => vec![*clbit],
+            Instruction::Conditional { condition, .. } => condition.clbits.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Classical bits `clbits` read as a number (bit k = clbits[k]) equal to `value`.
+// A single bit being set is Condition { clbits: vec![j], value: 1 }.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub clbits: Vec<usize>,
+    pub value: u64,
+}
+
+impl Condition {
+    pub fn bit_set(clbit: usize) -> Self {
+        Condition { clbits: vec![clbit], value: 1 }
+    }
+
+    pub fn holds(&self, clbits: &[bool]) -> bool {
+        let read = self.clbits.iter().enumerate().fold(0u64, |acc, (k, &c)| if clbits[c] { acc | 1 << k } else { acc });
+        read == self.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Circuit {
+    pub num_qubits: usize,
+    pub num_clbits: usize,
+    pub instructions: Vec<Instruction>,
+}
+
+impl Circuit {
+    pub fn new(num_qubits: usize, num_clbits: usize) -> Self {
+        Circuit { num_qubits, num_clbits, instructions: Vec::new() }
+    }
+
+    pub fn push(&mut self, gate: Gate) {
+        self.instructions.push(Instruction::Gate(gate));
+    }
+
+    pub fn extend(&mut self, gates: impl IntoIterator<Item = Gate>) {
+        self.instructions.extend(gates.into_iter().map(Instruction::Gate));
+    }
+
+    pub fn measure(&mut self, qubit: usize, clbit: usize) {
+        self.instructions.push(Instruction::Measure { qubit, clbit });
+    }
+
+    pub fn reset(&mut self, qubit: usize) {
+        self.instructions.push(Instruction::Reset(qubit));
+    }
+
+    // `gate` under classical control
+    pub fn push_conditional(&mut self, condition: Condition, gate: Gate) {
+        self.instructions.push(Instruction::Conditional { condition, gate });
+    }
+
+    // Append another circuit's instructions (qubit and clbit numbers unchanged)
+    pub fn append(&mut self, other: &Circuit) {
+        self.num_qubits = self.num_qubits.max(other.num_qubits);
+        self.num_clbits = self.num_clbits.max(other.num_clbits);
+        self.instructions.extend(other.instructions.iter().cloned());
+    }
+
+    pub fn gates(&self) -> impl Iterator<Item = &Gate> {
+        self.instructions.iter().filter_map(|i| match i {
+            Instruction::Gate(g) => Some(g),
+            _ => None,
+        })
+    }
+
+    pub fn gate_count(&self) -> usize {
+        self.gates().count()
+    }
+
+    // Layers of instructions that could run at the same time: each one starts
+    // after the last instruction on any of its qubits or classical bits
+    pub fn depth(&self) -> usize {
+        let mut qubit_layer = vec![0; self.num_qubits];
+        let mut clbit_layer = vec![0; self.num_clbits];
+        let mut depth = 0;
+        for instruction in &self.instructions {
+            let (qubits, clbits) = (instruction.qubits(), instruction.clbits());
+            let layer = 1 + qubits.iter().map(|&q| qubit_layer[q]).chain(clbits.iter().map(|&c| clbit_layer[c])).max().unwrap_or(0);
+            qubits.iter().for_each(|&q| qubit_layer[q] = layer);
+            clbits.iter().for_each(|&c| clbit_layer[c] = layer);
+            depth = depth.max(layer);
+        }
+        depth
+    }
+
+    // The circuit that undoes this one. Only unitary circuits have one.
+    pub fn inverse(&self) -> Option<Circuit> {
+        let mut inverse = Circuit::new(self.num_qubits, self.num_clbits);
+        for instruction in self.instructions.iter().rev() {
+            match instruction {
+                Instruction::Gate(g) => inverse.push(g.inverse()),
+                Instruction::ModMul { controls, register, a, n } => {
+                    let a_inverse = mod_inverse(*a, *n)?;
+                    inverse.instructions.push(Instruction::ModMul { controls: controls.clone(), register: register.clone(), a: a_inverse, n: *n });
+                }
+                _ => return None,
+            }
+        }
+        Some(inverse)
+    }
+
+    // The circuit without its trailing measurements, and those measurements as
+    // (qubit, clbit) pairs. Sampling the final state instead of running them gives
+    // the same distribution, and the state only has to be prepared once.
+    pub fn split_final_measurements(&self) -> (Circuit, Vec<(usize, usize)>) {
+        let mut body = self.clone();
+        let mut measurements = Vec::new();
+        while let Some(Instruction::Measure { qubit, clbit }) = body.instructions.last() {
+            measurements.push((*qubit, *clbit));
+            body.instructions.pop();
+        }
+        measurements.reverse();
+        (body, measurements)
+    }
+
+    // Run on `sim`, returning the classical bits (false for bits never written)
+    pub fn run(&self, sim: &mut dyn Simulator, rng: &mut dyn RngCore) -> Vec<bool> {
+        self.run_noisy(sim, &NoiseModel::ideal(), rng)
+    }
+
+    // Run on `sim` with the errors of `noise` after every gate and oracle, and
+    // on every measurement result (which is what conditions see)
+    pub fn run_noisy(&self, sim: &mut dyn Simulator, noise: &NoiseModel, rng: &mut dyn RngCore) -> Vec<bool> {
+        assert!(sim.num_qubits() >= self.num_qubits, "circuit needs {} qubits, simulator has {}", self.num_qubits, sim.num_qubits());
+        let mut clbits = vec![false; self.num_clbits];
+        for instruction in &self.instructions {
+            match instruction {
+                Instruction::Gate(g) => {
+                    sim.apply(g);
+                    noise.after_gate(g, sim, rng);
+                }
+                Instruction::Measure { qubit, clbit } => {
+                    let outcome = sim.measure(*qubit, rng);
+                    clbits[*clbit] = noise.read(outcome, rng);
+                }
+                Instruction::Reset(qubit) => sim.reset(*qubit, rng),
+                Instruction::Conditional { condition, gate } => {
+                    if condition.holds(&clbits) {
+                        sim.apply(gate);
+                        noise.after_gate(gate, sim, rng);
+                    }
+                }
+                Instruction::ModMul { controls, register, a, n } => {
+                    let (a, n) = (*a, *n);
+                    if n <= u32::MAX as u64 {
+                        sim.apply_permutation(controls, register, &|x| if x < n { a * x % n } else { x });
+                    } else {
+                        sim.apply_permutation(controls, register, &|x| if x < n { (a as u128 * x as u128 % n as u128) as u64 } else { x });
+                    }
+                    noise.after(ORACLE_NOISE, controls.iter().chain(register).copied(), sim, rng);
+                }
+            }
+        }
+        clbits
+    }
+
+    // How often each outcome came up in `shots` runs on a new `simulator` (with the errors of
+    // `noise`), an outcome being the classical bits read as a number (bit k = clbit k). When the
+    // only measurements come last and no trajectory has to be redrawn, the state is prepared
+    // once and the outcomes are sampled from it.
+    pub fn histogram(&self, simulator: SimulatorKind, noise: &NoiseModel, shots: usize, rng: &mut dyn RngCore) -> Result<BTreeMap<u64, usize>, SimError> {
+        if self.num_clbits > 64 {
+            return Err(SimError::InvalidState(format!("{} classical bits do not fit in one outcome", self.num_clbits)));
+        }
+        let read = |clbits: &[bool]| clbits.iter().enumerate().fold(0u64, |y, (k, &b)| if b { y | 1 << k } else { y });
+        let mut histogram = BTreeMap::new();
+        let (body, measured) = self.split_final_measurements();
+        let unitary = body.instructions.iter().all(|i| matches!(i, Instruction::Gate(_) | Instruction::ModMul { .. }));
+        if unitary && (noise.is_ideal() || simulator == SimulatorKind::DensityMatrix) {
+            let mut state = simulator.create(self.num_qubits)?;
+            body.run_noisy(state.as_mut(), noise, rng);
+            let mut qubits: Vec<usize> = measured.iter().map(|&(q, _)| q).collect();
+            qubits.sort_unstable();
+            qubits.dedup();
+            for sample in state.sample_qubits(&qubits, shots, rng) {
+                let mut clbits = vec![false; self.num_clbits];
+                for &(qubit, clbit) in &measured {
+                    let k = qubits.iter().position(|&q| q == qubit).unwrap_or(0);
+                    clbits[clbit] = noise.read(sample >> k & 1 == 1, rng);
+                }
+                *histogram.entry(read(&clbits)).or_insert(0) += 1;
+            }
+        } else {
+            for _ in 0..shots {
+                let mut state = simulator.create(self.num_qubits)?;
+                let clbits = self.run_noisy(state.as_mut(), noise, rng);
+                *histogram.entry(read(&clbits)).or_insert(0) += 1;
+            }
+        }
+        Ok(histogram)
+    }
+}
+
+// a^-1 mod n, None when gcd(a, n) > 1
+pub(crate) fn mod_inverse(a: u64, n: u64) -> Option<u64> {
+    let (mut t, mut new_t) = (0i128, 1i128);
+    let (mut r, mut new_r) = (n as i128, (a % n) as i128);
+    while new_r != 0 {
+        let q = r / new_r;
+        (t, new_t) = (new_t, t - q * new_t);
+        (r, new_r) = (new_r, r - q * new_r);
+    }
+    (r == 1).then(|| t.rem_euclid(n as i128) as u64)
+}
@@ -0,0 +1,501 @@
+// Optimization passes over circuits: the same circuit, with fewer gates.
+//
+// Every pass keeps the circuit's unitary exactly, global phase included (so the
+// result stays correct under controls), except `Drop`, which trades rotations
+// smaller than a threshold for an error of about half their angle each.
+
+use super::{Circuit, Instruction};
+use crate::quantum::{Gate, GateKind};
+use std::collections::{BTreeMap, BTreeSet};
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+use std::fmt;
+use std::str::FromStr;
+
+// Rotations below π/2^20 are dropped by default: far below the QFT's angles for
+// any register a simulator holds, and each costs less than 1e-11 in fidelity
+pub const DEFAULT_MIN_ANGLE: f64 = PI / (1u64 << 20) as f64;
+
+// Angles closer than this are equal
+const EPSILON: f64 = 1e-12;
+
+// Each round only removes or brings together gates, this is a safety net
+const MAX_ROUNDS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    // Remove a gate followed by its inverse on the same qubits, and multiplications by 1
+    Cancel,
+    // Add up consecutive rotations about the same axis on the same qubits
+    Merge,
+    // Remove rotations by less than the optimizer's `min_angle`
+    Drop,
+    // Move a gate back past gates it commutes with, next to one it cancels or merges with
+    Commute,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [Pass::Cancel, Pass::Merge, Pass::Drop, Pass::Commute];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Cancel => "cancel",
+            Pass::Merge => "merge",
+            Pass::Drop => "drop",
+            Pass::Commute => "commute",
+        }
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pass::ALL.iter().copied().find(|p| p.name() == s).ok_or_else(|| {
+            let names: Vec<_> = Pass::ALL.iter().map(|p| p.name()).collect();
+            format!("Unknown pass {} (available: {})", s, names.join(", "))
+        })
+    }
+}
+
+// Runs its passes in order, over and over until none of them changes anything
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimizer {
+    pub passes: Vec<Pass>,
+    // Smallest rotation angle `Drop` keeps
+    pub min_angle: f64,
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Optimizer { passes: Pass::ALL.to_vec(), min_angle: DEFAULT_MIN_ANGLE }
+    }
+}
+
+impl Optimizer {
+    pub fn optimize(&self, circuit: &Circuit) -> (Circuit, OptimizationReport) {
+        let mut optimized = circuit.clone();
+        let mut changes: Vec<(Pass, usize)> = self.passes.iter().map(|&p| (p, 0)).collect();
+        let mut rounds = 0;
+        loop {
+            rounds += 1;
+            let mut changed = false;
+            for (pass, count) in changes.iter_mut() {
+                let instructions = std::mem::take(&mut optimized.instructions);
+                let (instructions, n) = match pass {
+                    Pass::Cancel => {
+                        let (instructions, identities) = drop_identity_oracles(instructions);
+                        let (instructions, n) = peephole(instructions, optimized.num_qubits, cancel);
+                        (instructions, identities + n)
+                    }
+                    Pass::Merge => peephole(instructions, optimized.num_qubits, merge),
+                    Pass::Drop => drop_rotations(instructions, self.min_angle),
+                    Pass::Commute => commute(instructions),
+                };
+                optimized.instructions = instructions;
+                *count += n;
+                changed |= n > 0;
+            }
+            if !changed || rounds == MAX_ROUNDS {
+                break;
+            }
+        }
+        let report = OptimizationReport { before: CircuitStats::of(circuit), after: CircuitStats::of(&optimized), changes, rounds };
+        (optimized, report)
+    }
+}
+
+// Instruction counts by name ("h", "cp", "ccx", "measure", "if x", "modmul", ...) and depth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub counts: BTreeMap<String, usize>,
+    pub gates: usize,
+    pub depth: usize,
+}
+
+impl CircuitStats {
+    pub fn of(circuit: &Circuit) -> Self {
+        let mut counts = BTreeMap::new();
+        for instruction in &circuit.instructions {
+            let name = match instruction {
+                Instruction::Gate(g) => g.name(),
+                Instruction::Conditional { gate, .. } => format!("if {}", gate.name()),
+                Instruction::Measure { .. } => "measure".to_string(),
+                Instruction::Reset(_) => "reset".to_string(),
+                Instruction::ModMul { .. } => "modmul".to_string(),
+            };
+            *counts.entry(name).or_insert(0) += 1;
+        }
+        CircuitStats { counts, gates: circuit.gate_count(), depth: circuit.depth() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizationReport {
+    pub before: CircuitStats,
+    pub after: CircuitStats,
+    // Gates each pass removed (moved, for `Commute`), over all rounds
+    pub changes: Vec<(Pass, usize)>,
+    pub rounds: usize,
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<10} {:>10} {:>10}", "", "before", "after")?;
+        let names: BTreeSet<&String> = self.before.counts.keys().chain(self.after.counts.keys()).collect();
+        for name in names {
+            let count = |stats: &CircuitStats| stats.counts.get(name).copied().unwrap_or(0);
+            writeln!(f, "{:<10} {:>10} {:>10}", name, count(&self.before), count(&self.after))?;
+        }
+        writeln!(f, "{:<10} {:>10} {:>10}", "gates", self.before.gates, self.after.gates)?;
+        writeln!(f, "{:<10} {:>10} {:>10}", "depth", self.before.depth, self.after.depth)?;
+        let changes: Vec<String> = self
+            .changes
+            .iter()
+            .map(|(pass, n)| format!("{} {} {}", pass, if *pass == Pass::Commute { "moved" } else { "removed" }, n))
+            .collect();
+        writeln!(f, "{} ({} rounds)", changes.join(", "), self.rounds)
+    }
+}
+
+// What a peephole rule does with a gate and the one right before it on the same qubits
+enum Rewrite {
+    // Both go
+    Remove,
+    // The earlier one becomes this, the later one goes
+    Replace(GateKind),
+}
+
+// Apply `rule` to every pair of gates on the same qubits with nothing between them
+// on those qubits. Each qubit keeps a stack of the instructions on it, so when a pair
+// goes the gate before it is next in line, and nested pairs such as QFT† QFT go in one sweep.
+// Returns the gates removed.
+fn peephole(instructions: Vec<Instruction>, num_qubits: usize, rule: fn(&Gate, &Gate) -> Option<Rewrite>) -> (Vec<Instruction>, usize) {
+    let mut out: Vec<Option<Instruction>> = Vec::with_capacity(instructions.len());
+    let mut stacks: Vec<Vec<usize>> = vec![Vec::new(); num_qubits];
+    let mut removed = 0;
+    for instruction in instructions {
+        if let Instruction::Gate(gate) = &instruction {
+            let qubits: Vec<usize> = gate.qubits().collect();
+            let last = stacks[gate.target].last().copied().filter(|&k| qubits.iter().all(|&q| stacks[q].last() == Some(&k)));
+            if let Some(k) = last
+                && let Some(Instruction::Gate(previous)) = &out[k]
+            {
+                match same_qubits(previous, gate).then(|| rule(previous, gate)).flatten() {
+                    Some(Rewrite::Remove) => {
+                        out[k] = None;
+                        for &q in &qubits {
+                            stacks[q].pop();
+                        }
+                        removed += 2;
+                        continue;
+                    }
+                    Some(Rewrite::Replace(kind)) => {
+                        out[k] = Some(Instruction::Gate(Gate { kind, ..previous.clone() }));
+                        removed += 1;
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+        }
+        for q in instruction.qubits() {
+            stacks[q].push(out.len());
+        }
+        out.push(Some(instruction));
+    }
+    (out.into_iter().flatten().collect(), removed)
+}
+
+// Same target and the same controls, in any order
+fn same_qubits(a: &Gate, b: &Gate) -> bool {
+    a.target == b.target && a.controls.len() == b.controls.len() && a.controls.iter().all(|c| b.controls.contains(c))
+}
+
+fn cancel(previous: &Gate, gate: &Gate) -> Option<Rewrite> {
+    same_matrix(&previous.kind.inverse(), &gate.kind).then_some(Rewrite::Remove)
+}
+
+fn merge(previous: &Gate, gate: &Gate) -> Option<Rewrite> {
+    let (axis, a) = rotation(&previous.kind)?;
+    let (other, b) = rotation(&gate.kind)?;
+    if axis != other {
+        return None;
+    }
+    let angle = reduce(axis, a + b);
+    Some(if angle.abs() < EPSILON { Rewrite::Remove } else { Rewrite::Replace(rotation_kind(axis, angle)) })
+}
+
+fn same_matrix(a: &GateKind, b: &GateKind) -> bool {
+    let (a, b) = (a.matrix(), b.matrix());
+    (0..2).all(|i| (0..2).all(|j| (a[i][j] - b[i][j]).norm() < EPSILON))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    // diag(1, e^(iθ)), which S, T, Z and their inverses are too
+    Phase,
+    X,
+    Y,
+    Z,
+}
+
+// The kind as a rotation by an angle about an axis
+fn rotation(kind: &GateKind) -> Option<(Axis, f64)> {
+    Some(match *kind {
+        GateKind::Phase(theta) => (Axis::Phase, theta),
+        GateKind::Z => (Axis::Phase, PI),
+        GateKind::S => (Axis::Phase, FRAC_PI_2),
+        GateKind::Sdg => (Axis::Phase, -FRAC_PI_2),
+        GateKind::T => (Axis::Phase, FRAC_PI_4),
+        GateKind::Tdg => (Axis::Phase, -FRAC_PI_4),
+        GateKind::Rx(theta) => (Axis::X, theta),
+        GateKind::Ry(theta) => (Axis::Y, theta),
+        GateKind::Rz(theta) => (Axis::Z, theta),
+        _ => return None,
+    })
+}
+
+// The angle in (-period/2, period/2]: a phase repeats after 2π, a rotation after 4π
+fn reduce(axis: Axis, angle: f64) -> f64 {
+    let period = if axis == Axis::Phase { TAU } else { 2.0 * TAU };
+    let reduced = angle.rem_euclid(period);
+    if reduced > period / 2.0 { reduced - period } else { reduced }
+}
+
+// The rotation as a kind, by its name when it has one
+fn rotation_kind(axis: Axis, angle: f64) -> GateKind {
+    match axis {
+        Axis::Phase => {
+            let named = [(PI, GateKind::Z), (FRAC_PI_2, GateKind::S), (-FRAC_PI_2, GateKind::Sdg), (FRAC_PI_4, GateKind::T), (-FRAC_PI_4, GateKind::Tdg)];
+            named.iter().find(|(theta, _)| (theta - angle).abs() < EPSILON).map_or(GateKind::Phase(angle), |&(_, kind)| kind)
+        }
+        Axis::X => GateKind::Rx(angle),
+        Axis::Y => GateKind::Ry(angle),
+        Axis::Z => GateKind::Rz(angle),
+    }
+}
+
+// Remove the `ModMul`s by 1 (mod n), which higher powers of a often are
+fn drop_identity_oracles(instructions: Vec<Instruction>) -> (Vec<Instruction>, usize) {
+    let before = instructions.len();
+    let kept: Vec<Instruction> = instructions.into_iter().filter(|i| !matches!(i, Instruction::ModMul { a, n, .. } if a % n == 1)).collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+// Remove rotations by less than `min_angle`, returning how many
+fn drop_rotations(instructions: Vec<Instruction>, min_angle: f64) -> (Vec<Instruction>, usize) {
+    let before = instructions.len();
+    let kept: Vec<Instruction> = instructions
+        .into_iter()
+        .filter(|i| match i {
+            Instruction::Gate(g) => !rotation(&g.kind).is_some_and(|(axis, angle)| reduce(axis, angle).abs() < min_angle),
+            _ => true,
+        })
+        .collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+// How a gate acts on one of its qubits: a control or a diagonal target only reads
+// it in the computational basis, an X or Y rotation acts within span{1, X} or span{1, Y}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Diagonal,
+    X,
+    Y,
+    Other,
+}
+
+fn role(gate: &Gate, qubit: usize) -> Role {
+    if qubit != gate.target {
+        return Role::Diagonal;
+    }
+    match gate.kind {
+        kind if kind.is_diagonal() => Role::Diagonal,
+        GateKind::X | GateKind::Rx(_) => Role::X,
+        GateKind::Y | GateKind::Ry(_) => Role::Y,
+        _ => Role::Other,
+    }
+}
+
+// Sufficient for two gates to commute: on every qubit they share, both act within
+// the same commuting algebra (diagonal, or the same Pauli axis)
+fn commutes(a: &Gate, b: &Gate) -> bool {
+    a.qubits().filter(|&q| b.qubits().any(|r| r == q)).all(|q| {
+        let on_a = role(a, q);
+        on_a != Role::Other && on_a == role(b, q)
+    })
+}
+
+// For each gate, look ahead past the gates on its qubits it commutes with; when the
+// first one it does not commute with is on the same qubits and would cancel or merge
+// with it, move that one back to right after it. Returns the gates moved.
+fn commute(mut instructions: Vec<Instruction>) -> (Vec<Instruction>, usize) {
+    let mut moved = 0;
+    let mut i = 0;
+    while i < instructions.len() {
+        if let Instruction::Gate(gate) = &instructions[i] {
+            let gate = gate.clone();
+            let mut between = false;
+            for j in i + 1..instructions.len() {
+                let other = &instructions[j];
+                if !other.qubits().iter().any(|&q| gate.qubits().any(|r| r == q)) {
+                    continue;
+                }
+                let Instruction::Gate(other) = other else { break };
+                if same_qubits(&gate, other) && (cancel(&gate, other).is_some() || merge(&gate, other).is_some()) {
+                    if between {
+                        let partner = instructions.remove(j);
+                        instructions.insert(i + 1, partner);
+                        moved += 1;
+                    }
+                    break;
+                }
+                if !commutes(&gate, other) {
+                    break;
+                }
+                between = true;
+            }
+        }
+        i += 1;
+    }
+    (instructions, moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::testing::random_circuit;
+    use crate::quantum::StateVector;
+    use crate::seeded_rng;
+
+    // The circuit's matrix, column by column
+    fn unitary(circuit: &Circuit) -> Vec<Vec<num_complex::Complex64>> {
+        (0..1 << circuit.num_qubits)
+            .map(|index| {
+                let mut state = StateVector::basis(circuit.num_qubits, index).unwrap();
+                circuit.run(&mut state, &mut seeded_rng(0));
+                state.amplitudes().to_vec()
+            })
+            .collect()
+    }
+
+    // Largest difference between entries of the two circuits' matrices
+    fn distance(a: &Circuit, b: &Circuit) -> f64 {
+        let (a, b) = (unitary(a), unitary(b));
+        a.iter().flatten().zip(b.iter().flatten()).map(|(x, y)| (x - y).norm()).fold(0.0, f64::max)
+    }
+
+    // `pass` alone, and what it did: instructions before and after and the pass's count
+    fn run(pass: Pass, circuit: &Circuit) -> (Circuit, usize, usize, usize) {
+        let (optimized, report) = Optimizer { passes: vec![pass], ..Optimizer::default() }.optimize(circuit);
+        assert!(distance(circuit, &optimized) < 1e-12, "{} changed the unitary", pass);
+        assert_eq!(report.changes.len(), 1);
+        let (instructions, after) = (circuit.instructions.len(), optimized.instructions.len());
+        (optimized, instructions, after, report.changes[0].1)
+    }
+
+    #[test]
+    fn cancel_removes_inverse_pairs() {
+        let mut circuit = Circuit::new(4, 0);
+        let forward = [Gate::h(0), Gate::cphase(0, 1, 0.3), Gate::t(2), Gate::toffoli(0, 1, 2), Gate::x(3).controlled(2)];
+        circuit.push(Gate::h(1));
+        circuit.extend(forward.clone());
+        circuit.extend(forward.iter().rev().map(Gate::inverse));
+        // Controls in another order, and a multiplication by 8 = 1 (mod 7)
+        circuit.extend([Gate::toffoli(0, 1, 3), Gate::toffoli(1, 0, 3)]);
+        circuit.instructions.push(Instruction::ModMul { controls: vec![0], register: vec![1, 2, 3], a: 8, n: 7 });
+        // Not inverses: these stay
+        circuit.extend([Gate::h(2), Gate::x(2), Gate::s(3), Gate::s(3)]);
+
+        let (optimized, before, after, removed) = run(Pass::Cancel, &circuit);
+        assert_eq!((before - after, removed), (13, 13));
+        assert_eq!(optimized.gate_count(), 5);
+    }
+
+    #[test]
+    fn merge_adds_up_rotations() {
+        let mut circuit = Circuit::new(2, 0);
+        circuit.extend([Gate::phase(0, 0.3), Gate::phase(0, 0.4), Gate::h(0)]);
+        circuit.extend([Gate::cphase(0, 1, 0.1), Gate::cphase(0, 1, 0.2), Gate::h(0)]);
+        circuit.extend([Gate::new(GateKind::Rx(0.2), 0).controlled(1), Gate::new(GateKind::Rx(0.5), 0).controlled(1), Gate::h(0)]);
+        circuit.extend([Gate::new(GateKind::Rz(0.5), 1), Gate::new(GateKind::Rz(-0.5), 1), Gate::h(1)]);
+        // Eight T gates are the identity, two Rz(π) are -1 (which matters under a control)
+        circuit.extend((0..8).map(|_| Gate::t(1)));
+        circuit.extend([Gate::h(1), Gate::new(GateKind::Rz(PI), 1), Gate::new(GateKind::Rz(PI), 1)]);
+        // Different axes: these stay
+        circuit.extend([Gate::h(0), Gate::new(GateKind::Rx(0.2), 0), Gate::new(GateKind::Rz(0.2), 0)]);
+
+        let (optimized, before, after, removed) = run(Pass::Merge, &circuit);
+        assert_eq!((before - after, removed), (14, 14));
+        assert_eq!(optimized.gates().filter(|g| matches!(g.kind, GateKind::Rz(theta) if theta == TAU)).count(), 1);
+    }
+
+    #[test]
+    fn drop_removes_tiny_rotations() {
+        let mut circuit = Circuit::new(2, 0);
+        circuit.extend([Gate::h(0), Gate::h(1), Gate::phase(0, 1e-7), Gate::cphase(0, 1, 1e-8)]);
+        circuit.extend([Gate::new(GateKind::Rz(-1e-7), 1), Gate::new(GateKind::Rx(2.0 * TAU - 1e-8), 1)]);
+        circuit.extend([Gate::phase(0, PI / (1u64 << 19) as f64), Gate::phase(1, PI / (1u64 << 21) as f64), Gate::phase(0, 0.1)]);
+
+        let (optimized, report) = Optimizer { passes: vec![Pass::Drop], ..Optimizer::default() }.optimize(&circuit);
+        assert_eq!(report.changes, [(Pass::Drop, 5)]);
+        assert_eq!(circuit.gate_count() - optimized.gate_count(), 5);
+        // No more than the dropped angles add up to
+        let error = distance(&circuit, &optimized);
+        assert!(error > 1e-7 && error < 1.8e-6, "{}", error);
+
+        // A larger threshold takes the others too
+        let (optimized, _) = Optimizer { passes: vec![Pass::Drop], min_angle: 0.2 }.optimize(&circuit);
+        assert_eq!(optimized.gate_count(), 2);
+    }
+
+    #[test]
+    fn commute_brings_partners_together() {
+        let mut circuit = Circuit::new(3, 0);
+        // A T past a control, an X past a target, an Rx past a target
+        circuit.extend([Gate::t(0), Gate::cnot(0, 1), Gate::new(GateKind::Tdg, 0)]);
+        circuit.extend([Gate::x(2), Gate::cnot(1, 2), Gate::x(2)]);
+        circuit.extend([Gate::new(GateKind::Rx(0.3), 1), Gate::cnot(0, 1), Gate::new(GateKind::Rx(0.4), 1)]);
+        // H and Z do not commute with a CNOT's target
+        circuit.extend([Gate::h(1), Gate::cnot(0, 1), Gate::h(1)]);
+        circuit.extend([Gate::z(1), Gate::cnot(0, 1), Gate::z(1)]);
+
+        let (optimized, before, after, moved) = run(Pass::Commute, &circuit);
+        assert_eq!((before, after, moved), (15, 15, 3));
+        let kinds: Vec<GateKind> = optimized.gates().take(3).map(|g| g.kind).collect();
+        assert_eq!(kinds, [GateKind::T, GateKind::Tdg, GateKind::X]);
+
+        // Which is what lets the other passes at them
+        let (optimized, report) = Optimizer { passes: vec![Pass::Cancel, Pass::Merge, Pass::Commute], ..Optimizer::default() }.optimize(&circuit);
+        assert!(distance(&circuit, &optimized) < 1e-12);
+        assert_eq!(report.before.gates - report.after.gates, 5);
+        let (_, without) = Optimizer { passes: vec![Pass::Cancel, Pass::Merge], ..Optimizer::default() }.optimize(&circuit);
+        assert_eq!(without.after.gates, without.before.gates);
+    }
+
+    #[test]
+    fn passes_keep_random_circuits() {
+        let mut rng = seeded_rng(22);
+        for _ in 0..10 {
+            let mut circuit = random_circuit(4, 40, &mut rng);
+            for pass in Pass::ALL {
+                run(pass, &circuit);
+            }
+            // Followed by its own inverse, nothing is left
+            circuit.instructions.retain(|i| matches!(i, Instruction::Gate(_)));
+            let mut mirrored = circuit.clone();
+            mirrored.append(&circuit.inverse().unwrap());
+            let (optimized, before, _, removed) = run(Pass::Cancel, &mirrored);
+            assert!(optimized.instructions.is_empty());
+            assert_eq!(removed, before);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use super::qft::rotation_angle;
+use super::{mod_inverse, Circuit, Condition, Instruction, Qft};
+use crate::arithmetic::{Beauregard, NotInvertible};
+use crate::quantum::Gate;
+use std::fmt;
+use std::str::FromStr;
+
+// How the controlled multiplications by a^(2^j) mod n are built
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Oracle {
+    // One `ModMul` instruction each, simulated as a permutation of the work register
+    #[default]
+    Permutation,
+    // Gates only, Beauregard's QFT-adder construction with m + 2 ancilla qubits
+    Beauregard,
+}
+
+impl Oracle {
+    pub const ALL: [Oracle; 2] = [Oracle::Permutation, Oracle::Beauregard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Oracle::Permutation => "permutation",
+            Oracle::Beauregard => "beauregard",
+        }
+    }
+
+    // Qubits the oracle needs besides the control and the m-qubit work register
+    pub fn ancillas(self, n: u64) -> usize {
+        match self {
+            Oracle::Permutation => 0,
+            Oracle::Beauregard => (u64::BITS - n.leading_zeros()) as usize + 2,
+        }
+    }
+
+    // Controlled multiplication of the work register `work` (starting at qubit
+    // `work[0]`, ancillas right after it) by a mod n, for a coprime to n
+    fn push(self, circuit: &mut Circuit, control: usize, work: &[usize], a: u64, n: u64, qft: &Qft) -> Result<(), NotInvertible> {
+        match self {
+            Oracle::Permutation => {
+                // Otherwise x -> a x mod n is not a permutation
+                mod_inverse(a, n).ok_or(NotInvertible { a, n })?;
+                circuit.instructions.push(Instruction::ModMul { controls: vec![control], register: work.to_vec(), a, n });
+            }
+            Oracle::Beauregard => {
+                circuit.extend(Beauregard::new(n, work[0], *qft).controlled_mod_mul(a, control)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Oracle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Oracle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Oracle::ALL.iter().copied().find(|o| o.name() == s).ok_or_else(|| {
+            let names: Vec<_> = Oracle::ALL.iter().map(|o| o.name()).collect();
+            format!("Unknown oracle {} (available: {})", s, names.join(", "))
+        })
+    }
+}
+
+// The order-finding circuit for a mod n, with the qubit layout needed to read it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderFindingCircuit {
+    pub circuit: Circuit,
+    // Phase register, least significant qubit first. Measured into clbits 0..t.
+    // The semi-classical circuit has a single, recycled phase qubit.
+    pub phase: Vec<usize>,
+    // Work register holding a^x mod n
+    pub work: Vec<usize>,
+}
+
+impl OrderFindingCircuit {
+    // t of Q = 2^t, the denominator of the phase read from the measurement
+    pub fn phase_bits(&self) -> usize {
+        self.circuit.num_clbits
+    }
+}
+
+// The modular exponentiation of Shor's circuit on its own: the m-qubit work register
+// is set to |1> and multiplied by a^x mod n, x being the 2m-qubit control register
+// (one controlled multiplication by a^(2^j) mod n per control qubit, built by `oracle`,
+// whose ancillas follow the work register). No measurements. An error unless gcd(a, n) = 1.
+pub fn modexp_circuit(a: u64, n: u64, qft: &Qft, oracle: Oracle) -> Result<OrderFindingCircuit, NotInvertible> {
+    let m = (u64::BITS - n.leading_zeros()) as usize;
+    let t = 2 * m;
+    let phase: Vec<usize> = (0..t).collect();
+    let work: Vec<usize> = (t..t + m).collect();
+    let mut circuit = Circuit::new(t + m + oracle.ancillas(n), 0);
+    circuit.push(Gate::x(work[0]));
+
+    // a^(2^j) mod n by repeated squaring
+    let mut multiplier = a % n;
+    for &q in &phase {
+        oracle.push(&mut circuit, q, &work, multiplier, n, qft).map_err(|_| NotInvertible { a, n })?;
+        multiplier = (multiplier as u128 * multiplier as u128 % n as u128) as u64;
+    }
+    Ok(OrderFindingCircuit { circuit, phase, work })
+}
+
+// Shor's order-finding circuit with a 2m-qubit phase register, m = bits of n:
+//
+//   phase register: H on every qubit, uniform superposition over x < 2^2m
+//   work register:  |1>, then controlled-U^(2^j) from phase qubit j, where U|y> = |a y mod n>,
+//                   leaving Σ_x |x>|a^x mod n>
+//   phase register: inverse QFT, then measure
+//
+// The modular exponentiation is `modexp_circuit`.
+pub fn order_finding_circuit(a: u64, n: u64, qft: &Qft, oracle: Oracle) -> Result<OrderFindingCircuit, NotInvertible> {
+    let OrderFindingCircuit { circuit: modexp, phase, work } = modexp_circuit(a, n, qft, oracle)?;
+    let mut circuit = Circuit::new(modexp.num_qubits, phase.len());
+    for &q in &phase {
+        circuit.push(Gate::h(q));
+    }
+    circuit.append(&modexp);
+
+    circuit.extend(qft.inverse_gates(&phase));
+    for (clbit, &q) in phase.iter().enumerate() {
+        circuit.measure(q, clbit);
+    }
+    Ok(OrderFindingCircuit { circuit, phase, work })
+}
+
+// The semi-classical (Griffiths-Niu, Kitaev) version: one control qubit does the
+// work of the whole 2m-qubit phase register, so the circuit needs m + 1 qubits.
+//
+// Round k = 0 .. 2m-1 prepares the control in |+>, applies controlled-U^(2^(2m-1-k)),
+// undoes the phase of the bits already measured with rotations conditioned on them,
+// and measures bit k of y in the X basis. The outcome y has the same distribution as
+// the full circuit's; the inverse QFT has become measurement plus classical feedback.
+// Corrections further than `qft.cutoff` bits back are dropped, as in the approximate QFT.
+// With the Beauregard oracle this is the 2m + 3 qubit circuit of quant-ph/0205095.
+pub fn semiclassical_order_finding_circuit(a: u64, n: u64, qft: &Qft, oracle: Oracle) -> Result<OrderFindingCircuit, NotInvertible> {
+    let m = (u64::BITS - n.leading_zeros()) as usize;
+    let t = 2 * m;
+    let control = 0;
+    let work: Vec<usize> = (1..=m).collect();
+    let mut circuit = Circuit::new(m + 1 + oracle.ancillas(n), t);
+    circuit.push(Gate::x(work[0]));
+
+    // a^(2^j) mod n for every j, used from the highest power down
+    let mut powers = Vec::with_capacity(t);
+    let mut multiplier = a % n;
+    for _ in 0..t {
+        powers.push(multiplier);
+        multiplier = (multiplier as u128 * multiplier as u128 % n as u128) as u64;
+    }
+
+    for k in 0..t {
+        if k > 0 {
+            circuit.reset(control);
+        }
+        circuit.push(Gate::h(control));
+        oracle.push(&mut circuit, control, &work, powers[t - 1 - k], n, qft).map_err(|_| NotInvertible { a, n })?;
+        for j in (0..k).rev() {
+            let distance = k - j;
+            if qft.cutoff.is_some_and(|cutoff| distance > cutoff) {
+                continue;
+            }
+            let Some(theta) = rotation_angle(distance) else { continue };
+            circuit.push_conditional(Condition::bit_set(j), Gate::phase(control, -theta));
+        }
+        circuit.push(Gate::h(control));
+        circuit.measure(control, k);
+    }
+    Ok(OrderFindingCircuit { circuit, phase: vec![control], work })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::{NoiseModel, Simulator, SimulatorKind, StateVector};
+    use crate::sampling::ideal_probability;
+    use crate::seeded_rng;
+
+    // The exact distribution of y from the full circuit
+    fn full_distribution(a: u64, n: u64) -> Vec<f64> {
+        let of = order_finding_circuit(a, n, &Qft::exact(), Oracle::Permutation).unwrap();
+        let (body, measured) = of.circuit.split_final_measurements();
+        assert_eq!(measured, of.phase.iter().enumerate().map(|(clbit, &q)| (q, clbit)).collect::<Vec<_>>());
+        let mut state = StateVector::new(body.num_qubits).unwrap();
+        body.run(&mut state, &mut seeded_rng(0));
+        state.marginal_probabilities(&of.phase)
+    }
+
+    #[test]
+    fn full_circuit_gives_the_ideal_distribution() {
+        for (a, n, r) in [(7, 15, 4), (2, 21, 6), (5, 21, 6)] {
+            let distribution = full_distribution(a, n);
+            let t = 2 * (u64::BITS - n.leading_zeros()) as usize;
+            assert_eq!(distribution.len(), 1 << t);
+            for (y, p) in distribution.iter().enumerate() {
+                assert!((p - ideal_probability(y as u64, t, r)).abs() < 1e-9, "a = {}, n = {}, y = {}", a, n, y);
+            }
+        }
+    }
+
+    #[test]
+    fn semiclassical_circuit_matches_the_full_one() {
+        let shots = 4000;
+        for (a, n) in [(7, 15), (2, 21)] {
+            let expected = full_distribution(a, n);
+            let of = semiclassical_order_finding_circuit(a, n, &Qft::exact(), Oracle::Permutation).unwrap();
+            let histogram = of.circuit.histogram(SimulatorKind::StateVector, &NoiseModel::ideal(), shots, &mut seeded_rng(n)).unwrap();
+            assert!(histogram.keys().all(|&y| expected[y as usize] > 1e-9), "an outcome the full circuit never gives");
+            let distance: f64 = expected
+                .iter()
+                .enumerate()
+                .map(|(y, p)| (histogram.get(&(y as u64)).copied().unwrap_or(0) as f64 / shots as f64 - p).abs())
+                .sum::<f64>()
+                / 2.0;
+            assert!(distance < 0.06, "a = {}, n = {}: total variation {}", a, n, distance);
+        }
+    }
+
+    #[test]
+    fn refuses_bases_with_a_common_factor() {
+        for oracle in Oracle::ALL {
+            assert_eq!(modexp_circuit(6, 15, &Qft::exact(), oracle), Err(NotInvertible { a: 6, n: 15 }));
+            assert_eq!(order_finding_circuit(5, 15, &Qft::exact(), oracle), Err(NotInvertible { a: 5, n: 15 }));
+            assert_eq!(semiclassical_order_finding_circuit(9, 21, &Qft::exact(), oracle), Err(NotInvertible { a: 9, n: 21 }));
+        }
+    }
+}
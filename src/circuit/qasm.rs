@@ -0,0 +1,326 @@
+// OpenQASM 3 export, so circuits can leave the process for other toolchains,
+// and OpenQASM 2 import (`import.rs`), so theirs can come in.
+
+use super::{Circuit, Condition, Instruction};
+use crate::quantum::{Gate, GateKind};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt::{self, Write};
+
+mod import;
+
+pub use import::from_qasm2;
+
+// Largest modulus a `ModMul` is written out for. The gate has one transposition per
+// element of the permutation, so past 12 bits the program runs to hundreds of megabytes.
+pub const MAX_PERMUTATION_MODULUS: u64 = 1 << 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QasmError {
+    // x -> a x mod n only permutes the register when gcd(a, n) = 1
+    Irreversible { a: u64, n: u64 },
+    // A `ModMul` whose modulus is above MAX_PERMUTATION_MODULUS
+    TooLarge { n: u64 },
+    // A program that could not be read, with the line it went wrong on
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for QasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QasmError::Irreversible { a, n } => write!(f, "multiplication by {} mod {} is not reversible", a, n),
+            QasmError::TooLarge { n } => write!(
+                f,
+                "modulus {} too large for a permutation-table gate (at most {}, use the beauregard oracle)",
+                n, MAX_PERMUTATION_MODULUS
+            ),
+            QasmError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for QasmError {}
+
+// The circuit as an OpenQASM 3 program over `qubit[num_qubits] q` and `bit[num_clbits] c`.
+// Gates map to stdgates.inc names ("ctrl(k) @" for more controls than those have),
+// conditionals to `if` on the measured bits, and every `ModMul` oracle to a gate
+// definition of its own: the permutation x -> a x mod n written as multi-controlled
+// X gates, one round per transposition, exact but exponential in the register size
+// (so only up to MAX_PERMUTATION_MODULUS).
+pub fn to_qasm3(circuit: &Circuit) -> Result<String, QasmError> {
+    let mut definitions: BTreeMap<String, String> = BTreeMap::new();
+    let mut body = String::new();
+    for instruction in &circuit.instructions {
+        match instruction {
+            Instruction::Gate(g) => writeln!(body, "{};", gate(g)).unwrap(),
+            Instruction::Measure { qubit, clbit } => writeln!(body, "c[{}] = measure q[{}];", clbit, qubit).unwrap(),
+            Instruction::Reset(qubit) => writeln!(body, "reset q[{}];", qubit).unwrap(),
+            Instruction::Conditional { condition, gate: g } => match condition_expr(condition) {
+                Some(expr) => writeln!(body, "if ({}) {{\n    {};\n}}", expr, gate(g)).unwrap(),
+                None => writeln!(body, "{};", gate(g)).unwrap(),
+            },
+            Instruction::ModMul { controls, register, a, n } => {
+                let name = modmul_name(*a, *n, controls.len());
+                if !definitions.contains_key(&name) {
+                    definitions.insert(name.clone(), modmul_definition(&name, *a, *n, controls.len(), register.len())?);
+                }
+                let qubits: Vec<String> = controls.iter().chain(register).map(|q| format!("q[{}]", q)).collect();
+                writeln!(body, "{} {};", name, qubits.join(", ")).unwrap();
+            }
+        }
+    }
+
+    let mut out = String::from("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n");
+    for definition in definitions.values() {
+        out.push('\n');
+        out.push_str(definition);
+    }
+    out.push('\n');
+    writeln!(out, "qubit[{}] q;", circuit.num_qubits).unwrap();
+    if circuit.num_clbits > 0 {
+        writeln!(out, "bit[{}] c;", circuit.num_clbits).unwrap();
+    }
+    out.push('\n');
+    out.push_str(&body);
+    Ok(out)
+}
+
+// Gate names of stdgates.inc with a single control
+const CONTROLLED: [&str; 8] = ["x", "y", "z", "h", "p", "rx", "ry", "rz"];
+
+fn gate(g: &Gate) -> String {
+    let qubits: Vec<String> = g.qubits().map(|q| format!("q[{}]", q)).collect();
+    format!("{} {}", gate_name(g), qubits.join(", "))
+}
+
+// The gate's OpenQASM name with its parameters and control modifiers
+fn gate_name(g: &Gate) -> String {
+    let (name, params) = kind(&g.kind);
+    let params = if params.is_empty() { String::new() } else { format!("({})", params.join(", ")) };
+    let base = format!("{}{}", name, params);
+    match g.controls.len() {
+        0 => base,
+        1 if CONTROLLED.contains(&name) => format!("c{}", base),
+        2 if g.kind == GateKind::X => "ccx".to_string(),
+        1 => format!("ctrl @ {}", base),
+        k => format!("ctrl({}) @ {}", k, base),
+    }
+}
+
+fn kind(kind: &GateKind) -> (&'static str, Vec<String>) {
+    match *kind {
+        GateKind::Phase(theta) | GateKind::Rx(theta) | GateKind::Ry(theta) | GateKind::Rz(theta) => (kind.name(), vec![angle(theta)]),
+        // U is a builtin of OpenQASM 3, with the same convention as ours
+        GateKind::U(theta, phi, lambda) => ("U", vec![angle(theta), angle(phi), angle(lambda)]),
+        _ => (kind.name(), Vec::new()),
+    }
+}
+
+// θ as a multiple of pi when it is one over a power of two (the QFT's angles), else as a number
+fn angle(theta: f64) -> String {
+    if theta == 0.0 {
+        return "0".to_string();
+    }
+    let turns = theta / PI;
+    for k in 0..40 {
+        let scaled = turns * (1u64 << k) as f64;
+        // Scaling by 2^k is exact, so the QFT's angles land on an integer
+        if (scaled - scaled.round()).abs() < 1e-9 {
+            let numerator = scaled.round() as i64;
+            let head = match numerator {
+                1 => "pi".to_string(),
+                -1 => "-pi".to_string(),
+                _ => format!("{}*pi", numerator),
+            };
+            return if k == 0 { head } else { format!("{}/{}", head, 1u64 << k) };
+        }
+    }
+    format!("{}", theta)
+}
+
+// The condition as a boolean expression, None when it always holds
+fn condition_expr(condition: &Condition) -> Option<String> {
+    let terms: Vec<String> = condition
+        .clbits
+        .iter()
+        .enumerate()
+        .map(|(k, c)| if condition.value >> k & 1 == 1 { format!("c[{}]", c) } else { format!("!c[{}]", c) })
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" && "))
+}
+
+fn modmul_name(a: u64, n: u64, controls: usize) -> String {
+    match controls {
+        1 => format!("modmul_{}_{}", a, n),
+        k => format!("modmul_{}_{}_c{}", a, n, k),
+    }
+}
+
+// gate modmul_a_n c0, .., x0, .. { ... }: |x> -> |a x mod n> for x < n when every control is 1.
+// The permutation is split into cycles, a cycle (c0 c1 .. ck) into the transpositions
+// (c0 c1), (c0 c2), .., (c0 ck), and each transposition (u v) into A B A: A flips the
+// other bits where u and v differ when pivot bit p reads v_p, which turns v into u with
+// bit p flipped, and B flips bit p when every other bit matches u (and the controls are 1).
+fn modmul_definition(name: &str, a: u64, n: u64, controls: usize, width: usize) -> Result<String, QasmError> {
+    if n > MAX_PERMUTATION_MODULUS {
+        return Err(QasmError::TooLarge { n });
+    }
+    if a == 0 || super::mod_inverse(a, n).is_none() {
+        return Err(QasmError::Irreversible { a, n });
+    }
+    let control_names: Vec<String> = (0..controls).map(|k| format!("c{}", k)).collect();
+    let bit_names: Vec<String> = (0..width).map(|k| format!("x{}", k)).collect();
+    let mut out = String::new();
+    writeln!(out, "// |x> -> |{} x mod {}> for x < {}, controlled on {}", a, n, n, if controls == 1 { "c0" } else { "every c" }).unwrap();
+    writeln!(out, "gate {} {} {{", name, control_names.iter().chain(&bit_names).cloned().collect::<Vec<_>>().join(", ")).unwrap();
+
+    let mut visited = vec![false; n as usize];
+    for start in 0..n {
+        if visited[start as usize] {
+            continue;
+        }
+        let mut cycle = vec![start];
+        visited[start as usize] = true;
+        let mut x = (a as u128 * start as u128 % n as u128) as u64;
+        while x != start {
+            visited[x as usize] = true;
+            cycle.push(x);
+            x = (a as u128 * x as u128 % n as u128) as u64;
+        }
+        for &v in &cycle[1..] {
+            transposition(&mut out, cycle[0], v, &control_names, &bit_names);
+        }
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+// Swap the basis states |u> and |v> of the register
+fn transposition(out: &mut String, u: u64, v: u64, controls: &[String], bits: &[String]) {
+    let differ = u ^ v;
+    let pivot = differ.trailing_zeros() as usize;
+    let others: Vec<usize> = (0..bits.len()).filter(|&k| k != pivot && differ >> k & 1 == 1).collect();
+    let flip = |out: &mut String| {
+        let modifier = if v >> pivot & 1 == 1 { "ctrl" } else { "negctrl" };
+        for &k in &others {
+            writeln!(out, "    {} @ x {}, {};", modifier, bits[pivot], bits[k]).unwrap();
+        }
+    };
+    flip(out);
+    let (mut positive, mut negative): (Vec<&String>, Vec<&String>) = (controls.iter().collect(), Vec::new());
+    for (k, bit) in bits.iter().enumerate().filter(|&(k, _)| k != pivot) {
+        if u >> k & 1 == 1 { positive.push(bit) } else { negative.push(bit) }
+    }
+    let mut modifiers = String::new();
+    match positive.len() {
+        0 => {}
+        1 => modifiers.push_str("ctrl @ "),
+        k => write!(modifiers, "ctrl({}) @ ", k).unwrap(),
+    }
+    match negative.len() {
+        0 => {}
+        1 => modifiers.push_str("negctrl @ "),
+        k => write!(modifiers, "negctrl({}) @ ", k).unwrap(),
+    }
+    let operands: Vec<&str> = positive.iter().chain(&negative).map(|s| s.as_str()).chain([bits[pivot].as_str()]).collect();
+    writeln!(out, "    {}x {};", modifiers, operands.join(", ")).unwrap();
+    flip(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{semiclassical_order_finding_circuit, Oracle, Qft};
+
+    // The semi-classical circuit for 2 mod 3: the permutation gates for 2^(2^j) mod 3
+    // and the feed-forward rotations conditioned on the bits measured so far
+    const SEMICLASSICAL_2_MOD_3: &str = "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+
+// |x> -> |1 x mod 3> for x < 3, controlled on c0
+gate modmul_1_3 c0, x0, x1 {
+}
+
+// |x> -> |2 x mod 3> for x < 3, controlled on c0
+gate modmul_2_3 c0, x0, x1 {
+    negctrl @ x x0, x1;
+    ctrl @ negctrl @ x c0, x1, x0;
+    negctrl @ x x0, x1;
+}
+
+qubit[3] q;
+bit[4] c;
+
+x q[1];
+h q[0];
+modmul_1_3 q[0], q[1], q[2];
+h q[0];
+c[0] = measure q[0];
+reset q[0];
+h q[0];
+modmul_1_3 q[0], q[1], q[2];
+if (c[0]) {
+    p(-pi/2) q[0];
+}
+h q[0];
+c[1] = measure q[0];
+reset q[0];
+h q[0];
+modmul_1_3 q[0], q[1], q[2];
+if (c[1]) {
+    p(-pi/2) q[0];
+}
+if (c[0]) {
+    p(-pi/4) q[0];
+}
+h q[0];
+c[2] = measure q[0];
+reset q[0];
+h q[0];
+modmul_2_3 q[0], q[1], q[2];
+if (c[2]) {
+    p(-pi/2) q[0];
+}
+if (c[1]) {
+    p(-pi/4) q[0];
+}
+if (c[0]) {
+    p(-pi/8) q[0];
+}
+h q[0];
+c[3] = measure q[0];
+";
+
+    #[test]
+    fn semiclassical_order_finding_program() {
+        let of = semiclassical_order_finding_circuit(2, 3, &Qft::exact(), Oracle::Permutation).unwrap();
+        assert_eq!(to_qasm3(&of.circuit).unwrap(), SEMICLASSICAL_2_MOD_3);
+    }
+
+    #[test]
+    fn gate_names_and_angles() {
+        let mut circuit = Circuit::new(4, 0);
+        circuit.extend([Gate::cphase(0, 1, -PI / 4.0), Gate::toffoli(0, 1, 2), Gate::h(3).controlled(0).controlled(1), Gate::new(GateKind::Rz(0.3), 2)]);
+        circuit.push(Gate::new(GateKind::U(PI, 0.0, -3.0 * PI / 2.0), 1));
+        let program = to_qasm3(&circuit).unwrap();
+        let body: Vec<&str> = program.lines().skip(5).collect();
+        assert_eq!(body, ["cp(-pi/4) q[0], q[1];", "ccx q[0], q[1], q[2];", "ctrl(2) @ h q[0], q[1], q[3];", "rz(0.3) q[2];", "U(pi, 0, -3*pi/2) q[1];"]);
+    }
+
+    fn modmul(a: u64, n: u64) -> Circuit {
+        let width = (u64::BITS - n.leading_zeros()) as usize;
+        let mut circuit = Circuit::new(width + 1, 0);
+        circuit.instructions.push(Instruction::ModMul { controls: vec![0], register: (1..=width).collect(), a, n });
+        circuit
+    }
+
+    #[test]
+    fn refuses_large_and_irreversible_multiplications() {
+        assert!(to_qasm3(&modmul(2, MAX_PERMUTATION_MODULUS - 1)).is_ok());
+        assert_eq!(to_qasm3(&modmul(5, MAX_PERMUTATION_MODULUS + 1)), Err(QasmError::TooLarge { n: MAX_PERMUTATION_MODULUS + 1 }));
+        assert_eq!(to_qasm3(&modmul(2, 1 << 40)), Err(QasmError::TooLarge { n: 1 << 40 }));
+        assert_eq!(to_qasm3(&modmul(3, 6)), Err(QasmError::Irreversible { a: 3, n: 6 }));
+    }
+}
@@ -0,0 +1,773 @@
+// OpenQASM 2.0 import: circuits written elsewhere (papers, other toolchains) as a `Circuit`.
+
+use super::QasmError;
+use crate::circuit::{Circuit, Condition, Instruction};
+use crate::quantum::{Gate, GateKind};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+// qelib1.inc gates that are a single `GateKind` with controls: name, parameters,
+// controls (the first operands, the target is the last one) and the kind
+type Kind = fn(&[f64]) -> GateKind;
+const STANDARD: [(&str, usize, usize, Kind); 29] = [
+    ("u3", 3, 0, |p| GateKind::U(p[0], p[1], p[2])),
+    ("u", 3, 0, |p| GateKind::U(p[0], p[1], p[2])),
+    ("u2", 2, 0, |p| GateKind::U(PI / 2.0, p[0], p[1])),
+    ("u1", 1, 0, |p| GateKind::Phase(p[0])),
+    ("p", 1, 0, |p| GateKind::Phase(p[0])),
+    ("x", 0, 0, |_| GateKind::X),
+    ("y", 0, 0, |_| GateKind::Y),
+    ("z", 0, 0, |_| GateKind::Z),
+    ("h", 0, 0, |_| GateKind::H),
+    ("s", 0, 0, |_| GateKind::S),
+    ("sdg", 0, 0, |_| GateKind::Sdg),
+    ("t", 0, 0, |_| GateKind::T),
+    ("tdg", 0, 0, |_| GateKind::Tdg),
+    ("rx", 1, 0, |p| GateKind::Rx(p[0])),
+    ("ry", 1, 0, |p| GateKind::Ry(p[0])),
+    ("rz", 1, 0, |p| GateKind::Rz(p[0])),
+    ("cx", 0, 1, |_| GateKind::X),
+    ("cy", 0, 1, |_| GateKind::Y),
+    ("cz", 0, 1, |_| GateKind::Z),
+    ("ch", 0, 1, |_| GateKind::H),
+    ("crx", 1, 1, |p| GateKind::Rx(p[0])),
+    ("cry", 1, 1, |p| GateKind::Ry(p[0])),
+    ("crz", 1, 1, |p| GateKind::Rz(p[0])),
+    ("cu1", 1, 1, |p| GateKind::Phase(p[0])),
+    ("cp", 1, 1, |p| GateKind::Phase(p[0])),
+    ("cu3", 3, 1, |p| GateKind::U(p[0], p[1], p[2])),
+    ("ccx", 0, 2, |_| GateKind::X),
+    ("c3x", 0, 3, |_| GateKind::X),
+    ("c4x", 0, 4, |_| GateKind::X),
+];
+
+// The rest of qelib1.inc, with the definitions of the standard file
+// (id and u0 do nothing, so their bodies are empty)
+const QELIB1: &str = "
+gate id a { }
+gate u0(gamma) a { }
+gate swap a, b { cx a, b; cx b, a; cx a, b; }
+gate cswap a, b, c { cx c, b; ccx a, b, c; cx c, b; }
+gate sx a { sdg a; h a; sdg a; }
+gate sxdg a { s a; h a; s a; }
+gate csx a, b { h b; cu1(pi/2) a, b; h b; }
+gate cu(theta, phi, lambda, gamma) c, t { p(gamma) c; p((lambda+phi)/2) c; p((lambda-phi)/2) t; cx c, t; u(-theta/2, 0, -(phi+lambda)/2) t; cx c, t; u(theta/2, phi, 0) t; }
+gate rxx(theta) a, b { u3(pi/2, theta, 0) a; h b; cx a, b; u1(-theta) b; cx a, b; h b; u2(-pi, pi-theta) a; }
+gate rzz(theta) a, b { cx a, b; u1(theta) b; cx a, b; }
+gate rccx a, b, c { u2(0, pi) c; u1(pi/4) c; cx b, c; u1(-pi/4) c; cx a, c; u1(pi/4) c; cx b, c; u1(-pi/4) c; u2(0, pi) c; }
+gate rc3x a, b, c, d { u2(0, pi) d; u1(pi/4) d; cx c, d; u1(-pi/4) d; u2(0, pi) d; cx a, d; u1(pi/4) d; cx b, d; u1(-pi/4) d; cx a, d; u1(pi/4) d; cx b, d; u1(-pi/4) d; u2(0, pi) d; u1(pi/4) d; cx c, d; u1(-pi/4) d; u2(0, pi) d; }
+gate c3sqrtx a, b, c, d { h d; cu1(pi/8) a, d; h d; cx a, b; h d; cu1(-pi/8) b, d; h d; cx a, b; h d; cu1(pi/8) b, d; h d; cx b, c; h d; cu1(-pi/8) c, d; h d; cx a, c; h d; cu1(pi/8) c, d; h d; cx b, c; h d; cu1(-pi/8) c, d; h d; cx a, c; h d; cu1(pi/8) c, d; h d; }
+";
+
+// The OpenQASM 2.0 program `source` as a circuit. Quantum registers are laid out
+// one after the other in declaration order (the first one's q[0] is qubit 0), and
+// so are the classical ones, so a register c read as a number is bits c[0] (low)
+// to c[k-1] of the outcome. `include "qelib1.inc"` is built in, other includes are
+// refused. Gate calls on whole registers are broadcast, custom gates are expanded
+// into their bodies, barriers are dropped, and `if (c == v)` conditions every gate
+// of the operation on register c reading v.
+pub fn from_qasm2(source: &str) -> Result<Circuit, QasmError> {
+    let mut program = Program::default();
+    let mut parser = Parser::new(source)?;
+    parser.version()?;
+    while !parser.at_end() {
+        program.statement(&mut parser)?;
+    }
+    Ok(program.circuit)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(u64),
+    Real(f64),
+    Str(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 15] = ["->", "==", ";", ",", "(", ")", "[", "]", "{", "}", "+", "-", "*", "/", "^"];
+
+fn error(line: usize, message: impl Into<String>) -> QasmError {
+    QasmError::Parse { line, message: message.into() }
+}
+
+// The tokens of `source` with their line numbers
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, QasmError> {
+    let mut tokens = Vec::new();
+    for (number, text) in source.lines().enumerate() {
+        let line = number + 1;
+        let text = text.split("//").next().unwrap_or("");
+        let mut rest = text.trim_start();
+        while !rest.is_empty() {
+            let c = rest.chars().next().unwrap_or(' ');
+            let len = if c.is_ascii_alphabetic() || c == '_' {
+                let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                tokens.push((Token::Ident(rest[..len].to_string()), line));
+                len
+            } else if c.is_ascii_digit() || c == '.' {
+                let len = number_length(rest);
+                let literal = &rest[..len];
+                let token = match literal.parse::<u64>() {
+                    Ok(n) => Token::Int(n),
+                    Err(_) => Token::Real(literal.parse().map_err(|_| error(line, format!("invalid number {}", literal)))?),
+                };
+                tokens.push((token, line));
+                len
+            } else if c == '"' {
+                let end = rest[1..].find('"').ok_or_else(|| error(line, "unterminated string"))?;
+                tokens.push((Token::Str(rest[1..end + 1].to_string()), line));
+                end + 2
+            } else {
+                let symbol = SYMBOLS.iter().find(|s| rest.starts_with(**s)).ok_or_else(|| error(line, format!("unexpected character {}", c)))?;
+                tokens.push((Token::Symbol(symbol), line));
+                symbol.len()
+            };
+            rest = rest[len..].trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+// Length of the number literal at the start of `s`: digits, a fraction, an exponent
+fn number_length(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = digits(0);
+    if i < bytes.len() && bytes[i] == b'.' {
+        i = digits(i + 1);
+    }
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        let sign = usize::from(i + 1 < bytes.len() && (bytes[i + 1] == b'+' || bytes[i + 1] == b'-'));
+        let end = digits(i + 1 + sign);
+        if end > i + 1 + sign {
+            i = end;
+        }
+    }
+    i
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, QasmError> {
+        Ok(Parser { tokens: tokenize(source)?, pos: 0 })
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    // Line of the next token (of the last one at the end)
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, QasmError> {
+        let token = self.peek().cloned().ok_or_else(|| error(self.line(), "unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    // Consume `symbol` if it comes next
+    fn eat(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), QasmError> {
+        let line = self.line();
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            other => Err(error(line, format!("expected {}, found {}", symbol, describe(&other)))),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, QasmError> {
+        let line = self.line();
+        match self.next()? {
+            Token::Ident(name) => Ok(name),
+            other => Err(error(line, format!("expected a name, found {}", describe(&other)))),
+        }
+    }
+
+    fn int(&mut self) -> Result<u64, QasmError> {
+        let line = self.line();
+        match self.next()? {
+            Token::Int(n) => Ok(n),
+            other => Err(error(line, format!("expected an integer, found {}", describe(&other)))),
+        }
+    }
+
+    // OPENQASM 2.0;
+    fn version(&mut self) -> Result<(), QasmError> {
+        let line = self.line();
+        if self.peek() != Some(&Token::Ident("OPENQASM".to_string())) {
+            return Err(error(line, "the program must start with OPENQASM 2.0;"));
+        }
+        self.pos += 1;
+        let version = match self.next()? {
+            Token::Real(v) => v,
+            Token::Int(v) => v as f64,
+            other => return Err(error(line, format!("expected a version, found {}", describe(&other)))),
+        };
+        if version.trunc() != 2.0 {
+            return Err(error(line, format!("OpenQASM {} is not supported, only 2.0", version)));
+        }
+        self.expect(";")
+    }
+
+    // Names separated by commas, up to (not including) `end`
+    fn names(&mut self, end: &str) -> Result<Vec<String>, QasmError> {
+        let mut names = Vec::new();
+        if self.is_symbol(end) {
+            return Ok(names);
+        }
+        loop {
+            names.push(self.ident()?);
+            if !self.eat(",") {
+                return Ok(names);
+            }
+        }
+    }
+
+    // ( expr, ... ) when a parenthesis comes next, nothing otherwise
+    fn arguments(&mut self, params: &[String]) -> Result<Vec<Expr>, QasmError> {
+        let mut args = Vec::new();
+        if !self.eat("(") {
+            return Ok(args);
+        }
+        if self.eat(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr(params)?);
+            if self.eat(")") {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    // expr = term (('+' | '-') term)*
+    fn expr(&mut self, params: &[String]) -> Result<Expr, QasmError> {
+        let mut lhs = self.term(params)?;
+        while let Some(op) = ["+", "-"].into_iter().find(|s| self.is_symbol(s)) {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term(params)?));
+        }
+        Ok(lhs)
+    }
+
+    // term = unary (('*' | '/') unary)*
+    fn term(&mut self, params: &[String]) -> Result<Expr, QasmError> {
+        let mut lhs = self.unary(params)?;
+        while let Some(op) = ["*", "/"].into_iter().find(|s| self.is_symbol(s)) {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary(params)?));
+        }
+        Ok(lhs)
+    }
+
+    // unary = '-' unary | primary ('^' unary)?
+    fn unary(&mut self, params: &[String]) -> Result<Expr, QasmError> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary(params)?)));
+        }
+        let base = self.primary(params)?;
+        if self.eat("^") {
+            return Ok(Expr::Binary("^", Box::new(base), Box::new(self.unary(params)?)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self, params: &[String]) -> Result<Expr, QasmError> {
+        let line = self.line();
+        match self.next()? {
+            Token::Int(n) => Ok(Expr::Number(n as f64)),
+            Token::Real(x) => Ok(Expr::Number(x)),
+            Token::Symbol("(") => {
+                let inner = self.expr(params)?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Token::Ident(name) if name == "pi" => Ok(Expr::Number(PI)),
+            Token::Ident(name) => {
+                if let Some(k) = params.iter().position(|p| *p == name) {
+                    return Ok(Expr::Param(k));
+                }
+                let function: fn(f64) -> f64 = match name.as_str() {
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "tan" => f64::tan,
+                    "exp" => f64::exp,
+                    "ln" => f64::ln,
+                    "sqrt" => f64::sqrt,
+                    _ => return Err(error(line, format!("unknown parameter {}", name))),
+                };
+                self.expect("(")?;
+                let arg = self.expr(params)?;
+                self.expect(")")?;
+                Ok(Expr::Call(function, Box::new(arg)))
+            }
+            other => Err(error(line, format!("expected an expression, found {}", describe(&other)))),
+        }
+    }
+
+    // A qubit or bit argument: name or name[index]
+    fn operand(&mut self) -> Result<Operand, QasmError> {
+        let line = self.line();
+        let name = self.ident()?;
+        let index = if self.eat("[") {
+            let index = self.int()?;
+            self.expect("]")?;
+            Some(index as usize)
+        } else {
+            None
+        };
+        Ok(Operand { name, index, line })
+    }
+
+    fn operands(&mut self) -> Result<Vec<Operand>, QasmError> {
+        let mut operands = vec![self.operand()?];
+        while self.eat(",") {
+            operands.push(self.operand()?);
+        }
+        Ok(operands)
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(name) => name.clone(),
+        Token::Int(n) => n.to_string(),
+        Token::Real(x) => x.to_string(),
+        Token::Str(s) => format!("\"{}\"", s),
+        Token::Symbol(s) => s.to_string(),
+    }
+}
+
+// A gate parameter expression, over the parameters of the enclosing definition
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(f64),
+    Param(usize),
+    Neg(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Call(fn(f64) -> f64, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, params: &[f64]) -> f64 {
+        match self {
+            Expr::Number(x) => *x,
+            Expr::Param(k) => params[*k],
+            Expr::Neg(e) => -e.eval(params),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(params), b.eval(params));
+                match *op {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" => a / b,
+                    _ => a.powf(b),
+                }
+            }
+            Expr::Call(f, e) => f(e.eval(params)),
+        }
+    }
+}
+
+struct Operand {
+    name: String,
+    index: Option<usize>,
+    line: usize,
+}
+
+// One gate call inside a definition, on the definition's qubit arguments
+#[derive(Debug, Clone)]
+struct Call {
+    name: String,
+    params: Vec<Expr>,
+    qubits: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    params: usize,
+    qubits: usize,
+    // None for an opaque gate
+    body: Option<Vec<Call>>,
+}
+
+// What has been declared so far, and the circuit built from it
+#[derive(Default)]
+struct Program {
+    circuit: Circuit,
+    // First qubit (or bit) and size of each register
+    qregs: HashMap<String, (usize, usize)>,
+    cregs: HashMap<String, (usize, usize)>,
+    gates: HashMap<String, Definition>,
+    qelib1: bool,
+}
+
+impl Program {
+    fn statement(&mut self, parser: &mut Parser) -> Result<(), QasmError> {
+        let line = parser.line();
+        let keyword = parser.ident()?;
+        match keyword.as_str() {
+            "include" => {
+                match parser.next()? {
+                    Token::Str(file) if file == "qelib1.inc" => {}
+                    Token::Str(file) => return Err(error(line, format!("cannot include {}, only qelib1.inc is available", file))),
+                    other => return Err(error(line, format!("expected a file name, found {}", describe(&other)))),
+                }
+                parser.expect(";")?;
+                if !self.qelib1 {
+                    self.qelib1 = true;
+                    let mut library = Parser::new(QELIB1)?;
+                    while !library.at_end() {
+                        self.statement(&mut library)?;
+                    }
+                }
+                Ok(())
+            }
+            "qreg" | "creg" => {
+                let name = parser.ident()?;
+                parser.expect("[")?;
+                let size = parser.int()? as usize;
+                parser.expect("]")?;
+                parser.expect(";")?;
+                if size == 0 {
+                    return Err(error(line, format!("register {} is empty", name)));
+                }
+                if self.qregs.contains_key(&name) || self.cregs.contains_key(&name) {
+                    return Err(error(line, format!("register {} is already declared", name)));
+                }
+                if keyword == "qreg" {
+                    self.qregs.insert(name, (self.circuit.num_qubits, size));
+                    self.circuit.num_qubits += size;
+                } else {
+                    self.cregs.insert(name, (self.circuit.num_clbits, size));
+                    self.circuit.num_clbits += size;
+                }
+                Ok(())
+            }
+            "gate" | "opaque" => self.definition(parser, keyword == "opaque", line),
+            "barrier" => {
+                for operand in parser.operands()? {
+                    self.qubits(&operand)?;
+                }
+                parser.expect(";")
+            }
+            "if" => {
+                parser.expect("(")?;
+                let register = parser.ident()?;
+                parser.expect("==")?;
+                let value = parser.int()?;
+                parser.expect(")")?;
+                let &(start, size) = self.cregs.get(&register).ok_or_else(|| error(line, format!("unknown classical register {}", register)))?;
+                if size > 64 {
+                    return Err(error(line, format!("register {} has more than 64 bits to compare", register)));
+                }
+                let condition = Condition { clbits: (start..start + size).collect(), value };
+                let line = parser.line();
+                let name = parser.ident()?;
+                if name == "measure" || name == "reset" {
+                    return Err(error(line, format!("only gates can be conditioned, not {}", name)));
+                }
+                for gate in self.application(parser, name, line)? {
+                    self.circuit.push_conditional(condition.clone(), gate);
+                }
+                Ok(())
+            }
+            "measure" => {
+                let qubits = self.qubits(&parser.operand()?)?;
+                parser.expect("->")?;
+                let target = parser.operand()?;
+                let clbits = self.clbits(&target)?;
+                parser.expect(";")?;
+                if qubits.len() != clbits.len() {
+                    return Err(error(line, format!("measuring {} qubits into {} bits", qubits.len(), clbits.len())));
+                }
+                for (qubit, clbit) in qubits.into_iter().zip(clbits) {
+                    self.circuit.measure(qubit, clbit);
+                }
+                Ok(())
+            }
+            "reset" => {
+                let qubits = self.qubits(&parser.operand()?)?;
+                parser.expect(";")?;
+                for qubit in qubits {
+                    self.circuit.reset(qubit);
+                }
+                Ok(())
+            }
+            _ => {
+                let gates = self.application(parser, keyword, line)?;
+                self.circuit.instructions.extend(gates.into_iter().map(Instruction::Gate));
+                Ok(())
+            }
+        }
+    }
+
+    // gate name(params) qubits { body }  or  opaque name(params) qubits;
+    fn definition(&mut self, parser: &mut Parser, opaque: bool, line: usize) -> Result<(), QasmError> {
+        let name = parser.ident()?;
+        if self.gates.contains_key(&name) || self.is_builtin(&name) {
+            return Err(error(line, format!("gate {} is already defined", name)));
+        }
+        let params = if parser.eat("(") {
+            let params = parser.names(")")?;
+            parser.expect(")")?;
+            params
+        } else {
+            Vec::new()
+        };
+        let qubits = parser.names("{")?;
+        if qubits.is_empty() {
+            return Err(error(line, format!("gate {} has no qubits", name)));
+        }
+        for (k, arg) in params.iter().chain(&qubits).enumerate() {
+            if params.iter().chain(&qubits).skip(k + 1).any(|other| other == arg) {
+                return Err(error(line, format!("gate {} has two arguments called {}", name, arg)));
+            }
+        }
+        let body = if opaque {
+            parser.expect(";")?;
+            None
+        } else {
+            parser.expect("{")?;
+            let mut body = Vec::new();
+            while !parser.eat("}") {
+                let line = parser.line();
+                let callee = parser.ident()?;
+                let args = parser.arguments(&params)?;
+                let operands = parser.names(";")?;
+                parser.expect(";")?;
+                let indices = operands
+                    .iter()
+                    .map(|q| qubits.iter().position(|arg| arg == q).ok_or_else(|| error(line, format!("{} is not an argument of gate {}", q, name))))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if callee == "barrier" {
+                    continue;
+                }
+                self.check_call(&callee, args.len(), indices.len(), line)?;
+                body.push(Call { name: callee, params: args, qubits: indices });
+            }
+            Some(body)
+        };
+        self.gates.insert(name, Definition { params: params.len(), qubits: qubits.len(), body });
+        Ok(())
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        name == "U" || name == "CX" || (self.qelib1 && STANDARD.iter().any(|s| s.0 == name))
+    }
+
+    // Parameter and qubit counts of gate `name`, None when no such gate is known
+    fn arity(&self, name: &str) -> Option<(usize, usize)> {
+        match name {
+            "U" => Some((3, 1)),
+            "CX" => Some((0, 2)),
+            _ => match STANDARD.iter().find(|s| self.qelib1 && s.0 == name) {
+                Some(&(_, params, controls, _)) => Some((params, controls + 1)),
+                None => self.gates.get(name).map(|d| (d.params, d.qubits)),
+            },
+        }
+    }
+
+    fn check_call(&self, name: &str, params: usize, qubits: usize, line: usize) -> Result<(), QasmError> {
+        let (expected_params, expected_qubits) = self.arity(name).ok_or_else(|| error(line, format!("unknown gate {}", name)))?;
+        if params != expected_params {
+            return Err(error(line, format!("gate {} takes {} parameters, not {}", name, expected_params, params)));
+        }
+        if qubits != expected_qubits {
+            return Err(error(line, format!("gate {} acts on {} qubits, not {}", name, expected_qubits, qubits)));
+        }
+        Ok(())
+    }
+
+    // The rest of a gate call `name(params) operands;` at the top level,
+    // broadcast over whole registers and expanded into gates
+    fn application(&self, parser: &mut Parser, name: String, line: usize) -> Result<Vec<Gate>, QasmError> {
+        let params: Vec<f64> = parser.arguments(&[])?.iter().map(|e| e.eval(&[])).collect();
+        let operands = parser.operands()?;
+        parser.expect(";")?;
+        self.check_call(&name, params.len(), operands.len(), line)?;
+
+        let resolved = operands.iter().map(|o| self.qubits(o)).collect::<Result<Vec<_>, _>>()?;
+        // Registers must agree in size, single qubits go with every one of their qubits
+        let mut width = None;
+        for (operand, qubits) in operands.iter().zip(&resolved).filter(|(o, _)| o.index.is_none()) {
+            match width {
+                Some(w) if w != qubits.len() => {
+                    return Err(error(operand.line, format!("registers of sizes {} and {} in one call", w, qubits.len())));
+                }
+                _ => width = Some(qubits.len()),
+            }
+        }
+        let mut gates = Vec::new();
+        for k in 0..width.unwrap_or(1) {
+            let qubits: Vec<usize> = resolved.iter().map(|q| if q.len() == 1 { q[0] } else { q[k] }).collect();
+            if qubits.iter().enumerate().any(|(i, q)| qubits[i + 1..].contains(q)) {
+                return Err(error(line, format!("gate {} is given the same qubit twice", name)));
+            }
+            self.expand(&name, &params, &qubits, line, &mut gates)?;
+        }
+        Ok(gates)
+    }
+
+    // Gate `name` with parameter values `params` on `qubits`, as gates of the circuit
+    fn expand(&self, name: &str, params: &[f64], qubits: &[usize], line: usize, gates: &mut Vec<Gate>) -> Result<(), QasmError> {
+        let controlled = |kind: GateKind| Gate { kind, target: qubits[qubits.len() - 1], controls: qubits[..qubits.len() - 1].to_vec() };
+        match name {
+            "U" => gates.push(controlled(GateKind::U(params[0], params[1], params[2]))),
+            "CX" => gates.push(controlled(GateKind::X)),
+            _ => match STANDARD.iter().find(|s| self.qelib1 && s.0 == name) {
+                Some(&(_, _, _, kind)) => gates.push(controlled(kind(params))),
+                None => {
+                    let body = self.gates[name].body.as_ref().ok_or_else(|| error(line, format!("opaque gate {} cannot be simulated", name)))?;
+                    for call in body {
+                        let values: Vec<f64> = call.params.iter().map(|e| e.eval(params)).collect();
+                        let operands: Vec<usize> = call.qubits.iter().map(|&k| qubits[k]).collect();
+                        self.expand(&call.name, &values, &operands, line, gates)?;
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    // The qubits an operand names: one, or a whole register
+    fn qubits(&self, operand: &Operand) -> Result<Vec<usize>, QasmError> {
+        resolve(&self.qregs, operand, "quantum")
+    }
+
+    fn clbits(&self, operand: &Operand) -> Result<Vec<usize>, QasmError> {
+        resolve(&self.cregs, operand, "classical")
+    }
+}
+
+fn resolve(registers: &HashMap<String, (usize, usize)>, operand: &Operand, what: &str) -> Result<Vec<usize>, QasmError> {
+    let &(start, size) = registers.get(&operand.name).ok_or_else(|| error(operand.line, format!("unknown {} register {}", what, operand.name)))?;
+    match operand.index {
+        Some(index) if index >= size => Err(error(operand.line, format!("index {} is out of range for {}[{}]", index, operand.name, size))),
+        Some(index) => Ok(vec![start + index]),
+        None => Ok((start..start + size).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::postprocess::{analyze_histogram, DEFAULT_MAX_MULTIPLE};
+    use crate::quantum::{NoiseModel, SimulatorKind, StateVector};
+    use crate::seeded_rng;
+    use num_bigint::BigUint;
+
+    const SHOR15: &str = include_str!("../../../circuits/shor15.qasm");
+
+    // The line and message of a program that should not parse
+    fn parse_error(body: &str) -> (usize, String) {
+        let source = format!("OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\ncreg c[2];\n{}", body);
+        match from_qasm2(&source) {
+            Err(QasmError::Parse { line, message }) => (line, message),
+            other => panic!("{:?} parsed: {:?}", body, other),
+        }
+    }
+
+    fn final_state(circuit: &Circuit) -> StateVector {
+        let mut state = StateVector::new(circuit.num_qubits).unwrap();
+        circuit.run(&mut state, &mut seeded_rng(0));
+        state
+    }
+
+    #[test]
+    fn shor15_matches_the_same_gates_built_by_hand() {
+        let imported = from_qasm2(SHOR15).unwrap();
+        assert_eq!((imported.num_qubits, imported.num_clbits), (7, 3));
+        let (body, measurements) = imported.split_final_measurements();
+        assert_eq!(measurements, [(0, 0), (1, 1), (2, 2)]);
+
+        let mut by_hand = Circuit::new(7, 0);
+        by_hand.push(Gate::x(3));
+        by_hand.extend((0..3).map(Gate::h));
+        let cswap = |c: usize, a: usize, b: usize| Gate::swap(a, b).map(|g| g.controlled(c));
+        by_hand.extend([cswap(0, 3, 4), cswap(0, 4, 5), cswap(0, 5, 6)].concat());
+        by_hand.extend((3..7).map(|q| Gate::cnot(0, q)));
+        by_hand.extend([cswap(1, 4, 6), cswap(1, 3, 5)].concat());
+        by_hand.extend(Gate::swap(0, 2));
+        by_hand.extend([
+            Gate::h(0),
+            Gate::cphase(0, 1, -PI / 2.0),
+            Gate::h(1),
+            Gate::cphase(0, 2, -PI / 4.0),
+            Gate::cphase(1, 2, -PI / 2.0),
+            Gate::h(2),
+        ]);
+
+        let (imported, expected) = (final_state(&body), final_state(&by_hand));
+        for (i, (a, b)) in imported.amplitudes().iter().zip(expected.amplitudes()).enumerate() {
+            assert!((a - b).norm() < 1e-12, "amplitude {}: {} != {}", i, a, b);
+        }
+    }
+
+    #[test]
+    fn shor15_gives_back_the_order_of_7() {
+        let circuit = from_qasm2(SHOR15).unwrap();
+        let histogram = circuit.histogram(SimulatorKind::StateVector, &NoiseModel::ideal(), 4000, &mut seeded_rng(1)).unwrap();
+        assert_eq!(histogram.keys().copied().collect::<Vec<_>>(), [0, 2, 4, 6]);
+        assert!(histogram.values().all(|&shots| (850..1150).contains(&shots)), "{:?}", histogram);
+        let analysis = analyze_histogram(&histogram, 3, &BigUint::from(7u32), &BigUint::from(15u32), DEFAULT_MAX_MULTIPLE);
+        assert_eq!(analysis.period, Some(BigUint::from(4u32)));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            ("h q[0];\nfoo q[1];", 6, "unknown gate foo"),
+            ("h q[2];", 5, "index 2 is out of range for q[2]"),
+            ("cx q[0], r[1];", 5, "unknown quantum register r"),
+            ("measure q[0] -> c[5];", 5, "index 5 is out of range for c[2]"),
+            ("rz(pi/) q[0];", 5, "expected an expression"),
+            ("rz(2*pi q[0];", 5, "expected ,, found q"),
+            ("rz(theta) q[0];", 5, "unknown parameter theta"),
+            ("rz(.) q[0];", 5, "invalid number ."),
+            ("rz(pi $ 2) q[0];", 5, "unexpected character $"),
+            ("cx q[0];", 5, "gate cx acts on 2 qubits, not 1"),
+            ("rz q[0];", 5, "gate rz takes 1 parameters, not 0"),
+            ("\n\ncx q[0], q[0];", 7, "the same qubit twice"),
+        ];
+        for (body, line, message) in cases {
+            let (found_line, found) = parse_error(body);
+            assert!(found.contains(message), "{:?}: {:?} does not mention {:?}", body, found, message);
+            assert_eq!(found_line, line, "{:?}: {}", body, found);
+        }
+    }
+
+    #[test]
+    fn refuses_other_versions_and_includes() {
+        assert!(matches!(from_qasm2("OPENQASM 3.0;\nqreg q[1];"), Err(QasmError::Parse { line: 1, .. })));
+        let include = from_qasm2("OPENQASM 2.0;\ninclude \"other.inc\";");
+        assert!(matches!(include, Err(QasmError::Parse { line: 2, ref message }) if message.contains("other.inc")));
+    }
+}
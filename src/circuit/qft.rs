@@ -0,0 +1,212 @@
+use super::Circuit;
+use crate::quantum::{Gate, SimError, Simulator, StateVector};
+use num_complex::Complex64;
+use std::f64::consts::{PI, TAU};
+
+// Quantum Fourier Transform over a register, qubits[0] the least significant:
+// |x> -> 1/√M Σ_y e^(2πi xy/M) |y>, M = 2^m.
+//
+// The exact circuit has a controlled rotation by π/2^d between every pair of
+// qubits at distance d. Coppersmith's approximate QFT keeps only those with
+// d <= cutoff, which takes the gate count from O(m^2) to O(m cutoff). On a basis state
+// every output qubit is then off by a phase below π/2^cutoff, so the fidelity is at least
+// cos^(2m)(π/2^(cutoff+1)) ≈ 1 - m π^2 / 4^(cutoff+1), and for any input the error in
+// operator norm is below m π/2^cutoff. The cutoff needs to be log2(m) plus a few: at m = 8
+// the worst basis state gets 0.59 with cutoff 2, 0.91 with 3 and 0.998 with 5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Qft {
+    // Largest qubit distance that still gets a rotation (None = exact QFT)
+    pub cutoff: Option<usize>,
+    // Reverse the qubit order at the end. Without the swaps the output
+    // register is read with its bits reversed, which saves m/2 swaps.
+    pub skip_swaps: bool,
+}
+
+impl Qft {
+    pub fn exact() -> Self {
+        Qft::default()
+    }
+
+    pub fn approximate(cutoff: usize) -> Self {
+        Qft { cutoff: Some(cutoff), skip_swaps: false }
+    }
+
+    pub fn gates(&self, qubits: &[usize]) -> Vec<Gate> {
+        let m = qubits.len();
+        let mut gates = Vec::new();
+        for j in (0..m).rev() {
+            gates.push(Gate::h(qubits[j]));
+            for k in (0..j).rev() {
+                let distance = j - k;
+                if self.cutoff.is_some_and(|cutoff| distance > cutoff) {
+                    continue;
+                }
+                let Some(theta) = rotation_angle(distance) else { continue };
+                gates.push(Gate::cphase(qubits[k], qubits[j], theta));
+            }
+        }
+        if !self.skip_swaps {
+            for i in 0..m / 2 {
+                gates.extend(Gate::swap(qubits[i], qubits[m - 1 - i]));
+            }
+        }
+        gates
+    }
+
+    // The inverse transform, the same gates reversed and conjugated
+    pub fn inverse_gates(&self, qubits: &[usize]) -> Vec<Gate> {
+        self.gates(qubits).iter().rev().map(Gate::inverse).collect()
+    }
+
+    // QFT on qubits 0..m of an m-qubit circuit
+    pub fn circuit(&self, m: usize) -> Circuit {
+        let mut circuit = Circuit::new(m, 0);
+        circuit.extend(self.gates(&(0..m).collect::<Vec<_>>()));
+        circuit
+    }
+
+    pub fn inverse_circuit(&self, m: usize) -> Circuit {
+        let mut circuit = Circuit::new(m, 0);
+        circuit.extend(self.inverse_gates(&(0..m).collect::<Vec<_>>()));
+        circuit
+    }
+}
+
+// Largest qubit distance whose rotation π/2^d is still resolved in f64 next to the
+// π-sized angles it sums with. Past it the "exact" QFT drops rotations too.
+pub(crate) const MAX_ROTATION_DISTANCE: usize = f64::MANTISSA_DIGITS as usize;
+
+// The QFT's rotation between qubits at distance d, π/2^d (None past MAX_ROTATION_DISTANCE)
+pub(crate) fn rotation_angle(distance: usize) -> Option<f64> {
+    (distance <= MAX_ROTATION_DISTANCE).then(|| PI * 0.5f64.powi(distance as i32))
+}
+
+// The exact discrete Fourier transform of a state vector
+// (unitary normalization, e^(+2πi xy/M) forward, as the QFT), by radix-2 FFT
+pub fn dft(amplitudes: &[Complex64], inverse: bool) -> Vec<Complex64> {
+    let len = amplitudes.len();
+    assert!(len.is_power_of_two(), "DFT length must be a power of two");
+    let bits = len.trailing_zeros();
+    // Bit-reversed copy, then butterflies
+    let mut out: Vec<Complex64> = (0..len)
+        .map(|i| amplitudes[if bits == 0 { 0 } else { i.reverse_bits() >> (usize::BITS - bits) }])
+        .collect();
+    let sign = if inverse { -1.0 } else { 1.0 };
+    let mut size = 2;
+    while size <= len {
+        let step = Complex64::from_polar(1.0, sign * TAU / size as f64);
+        for start in (0..len).step_by(size) {
+            let mut w = Complex64::new(1.0, 0.0);
+            for k in 0..size / 2 {
+                let even = out[start + k];
+                let odd = out[start + k + size / 2] * w;
+                out[start + k] = even + odd;
+                out[start + k + size / 2] = even - odd;
+                w *= step;
+            }
+        }
+        size *= 2;
+    }
+    let norm = 1.0 / (len as f64).sqrt();
+    out.iter_mut().for_each(|a| *a *= norm);
+    out
+}
+
+// |<a|b>|^2 for normalized states
+pub fn fidelity(a: &[Complex64], b: &[Complex64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x.conj() * y).sum::<Complex64>().norm_sqr()
+}
+
+// Run `qft` on `state` and compare with the exact DFT of it.
+// 1 (up to rounding) for the exact QFT, a bit below for the approximate one.
+pub fn qft_fidelity(qft: &Qft, state: &StateVector) -> Result<f64, SimError> {
+    let mut sim = StateVector::from_amplitudes(state.amplitudes().to_vec())?;
+    for gate in qft.gates(&(0..sim.num_qubits()).collect::<Vec<_>>()) {
+        sim.apply(&gate);
+    }
+    let expected = dft(state.amplitudes(), false);
+    Ok(fidelity(&expected, sim.amplitudes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+    use rand::Rng;
+
+    fn random_state(m: usize, rng: &mut impl Rng) -> Vec<Complex64> {
+        let amplitudes: Vec<Complex64> = (0..1 << m).map(|_| Complex64::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))).collect();
+        let norm = amplitudes.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+        amplitudes.iter().map(|a| a / norm).collect()
+    }
+
+    // The DFT written out as the sum, O(M^2)
+    fn naive_dft(amplitudes: &[Complex64]) -> Vec<Complex64> {
+        let len = amplitudes.len();
+        (0..len)
+            .map(|y| amplitudes.iter().enumerate().map(|(x, a)| a * Complex64::from_polar(1.0, TAU * (x * y % len) as f64 / len as f64)).sum::<Complex64>() / (len as f64).sqrt())
+            .collect()
+    }
+
+    #[test]
+    fn dft_matches_the_sum_and_inverts() {
+        let mut rng = seeded_rng(12);
+        for m in 0..=6 {
+            let state = random_state(m, &mut rng);
+            let forward = dft(&state, false);
+            for (a, b) in forward.iter().zip(naive_dft(&state)) {
+                assert!((a - b).norm() < 1e-12);
+            }
+            for (a, b) in dft(&forward, true).iter().zip(&state) {
+                assert!((a - b).norm() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_circuit_is_the_dft() {
+        let mut rng = seeded_rng(13);
+        for m in 1..=7 {
+            for _ in 0..3 {
+                let state = random_state(m, &mut rng);
+                let mut sim = StateVector::from_amplitudes(state.clone()).unwrap();
+                Qft::exact().circuit(m).run(&mut sim, &mut rng);
+                for (a, b) in sim.amplitudes().iter().zip(dft(&state, false)) {
+                    assert!((a - b).norm() < 1e-12, "m = {}: {} != {}", m, a, b);
+                }
+                Qft::exact().inverse_circuit(m).run(&mut sim, &mut rng);
+                for (a, b) in sim.amplitudes().iter().zip(&state) {
+                    assert!((a - b).norm() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn skipping_swaps_reverses_the_output() {
+        let state = random_state(5, &mut seeded_rng(14));
+        let mut sim = StateVector::from_amplitudes(state.clone()).unwrap();
+        for gate in (Qft { skip_swaps: true, ..Qft::exact() }).gates(&[0, 1, 2, 3, 4]) {
+            sim.apply(&gate);
+        }
+        let expected = dft(&state, false);
+        for (i, a) in sim.amplitudes().iter().enumerate() {
+            assert!((a - expected[i.reverse_bits() >> (usize::BITS - 5)]).norm() < 1e-12);
+        }
+    }
+
+    // The figures in the comment on `Qft`
+    #[test]
+    fn approximate_fidelity_on_the_worst_basis_state() {
+        let m = 8;
+        let worst = |qft: &Qft| (0..1 << m).map(|x| qft_fidelity(qft, &StateVector::basis(m, x).unwrap()).unwrap()).fold(1.0, f64::min);
+        assert!((worst(&Qft::exact()) - 1.0).abs() < 1e-12);
+        for (cutoff, expected) in [(2, 0.59), (3, 0.91), (5, 0.998)] {
+            let fidelity = worst(&Qft::approximate(cutoff));
+            assert!((fidelity - expected).abs() < 0.005, "cutoff {}: {}", cutoff, fidelity);
+            // And the bound the comment gives
+            assert!(fidelity >= (PI / (1u64 << (cutoff + 1)) as f64).cos().powi(2 * m as i32) - 1e-12);
+        }
+        assert_eq!(Qft::approximate(2).gates(&(0..m).collect::<Vec<_>>()).len(), m + (2 * m - 3) + 3 * (m / 2));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::budget::CancelToken;
+use crate::period::Backend;
+use crate::quantum::{NoiseModel, SimulatorKind};
+use num_bigint::BigUint;
+use std::time::Duration;
+
+// Knobs for a single `factor` call
+#[derive(Debug, Clone)]
+pub struct Config {
+    // How many random values of 'a' to try before giving up (None = never give up)
+    pub max_attempts: Option<u64>,
+    // Largest period `find_period_classical` will search for (None = n * n)
+    pub period_limit: Option<BigUint>,
+    // Wall-clock limit for the whole call (None = no limit)
+    pub timeout: Option<Duration>,
+    // Checked inside the loop and inside period finding
+    pub cancel: Option<CancelToken>,
+    // Seed for the ChaCha generator `factor` uses (None = pick a fresh one)
+    pub seed: Option<u64>,
+    // Period finder `factor` and `factorize` use
+    pub backend: Backend,
+    // Factored multiple of every order mod N, such as λ(N), for the exact backend
+    pub order_multiple: Vec<(BigUint, u32)>,
+    // Errors injected into the circuits of the quantum and semiclassical backends
+    pub noise: NoiseModel,
+    // What those backends simulate the circuits on
+    pub simulator: SimulatorKind,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_attempts: Some(100),
+            period_limit: None,
+            timeout: None,
+            cancel: None,
+            seed: None,
+            backend: Backend::default(),
+            order_multiple: Vec::new(),
+            noise: NoiseModel::ideal(),
+            simulator: SimulatorKind::default(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::budget::{Interrupt, Progress};
+use num_bigint::BigUint;
+use std::error::Error;
+use std::fmt;
+
+// Everything that can stop `factor` from producing a split of N
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FactorError {
+    // N is below 2, there is nothing to factor
+    TooSmall(BigUint),
+    // N is prime, Shor's reduction would never find a non-trivial factor
+    Prime(BigUint),
+    // Every allowed base 'a' was tried without success
+    AttemptsExhausted(Progress),
+    // Every attempt failed because period finding ran past its limit
+    PeriodLimitExceeded { a: BigUint, limit: BigUint },
+    // The deadline passed or the run was cancelled
+    Interrupted(Interrupt, Progress),
+    // The period finder cannot work with this input at all
+    Backend(String),
+}
+
+impl FactorError {
+    // The partial-progress report, for errors that carry one
+    pub fn progress(&self) -> Option<&Progress> {
+        match self {
+            FactorError::AttemptsExhausted(progress) | FactorError::Interrupted(_, progress) => Some(progress),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn progress_mut(&mut self) -> Option<&mut Progress> {
+        match self {
+            FactorError::AttemptsExhausted(progress) | FactorError::Interrupted(_, progress) => Some(progress),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FactorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FactorError::TooSmall(n) => write!(f, "{} is too small to factor, N must be greater than 1", n),
+            FactorError::Prime(n) => write!(f, "{} is prime", n),
+            FactorError::AttemptsExhausted(progress) => {
+                write!(f, "no factor found after trying {} values of 'a'", progress.attempts)
+            }
+            FactorError::PeriodLimitExceeded { a, limit } => {
+                write!(f, "period finding exceeded limit {} (last a = {})", limit, a)
+            }
+            FactorError::Interrupted(Interrupt::Deadline, progress) => {
+                write!(f, "deadline reached after {:?} and {} values of 'a'", progress.elapsed, progress.attempts)
+            }
+            FactorError::Interrupted(Interrupt::Cancelled, progress) => {
+                write!(f, "cancelled after {:?} and {} values of 'a'", progress.elapsed, progress.attempts)
+            }
+            FactorError::Backend(msg) => write!(f, "period finder failed: {}", msg),
+        }
+    }
+}
+
+impl Error for FactorError {}
@@ -0,0 +1,177 @@
+use crate::error::FactorError;
+use crate::postprocess::PhaseAnalysis;
+use crate::quantum::SimulatorKind;
+use num_bigint::BigUint;
+
+// One step of `shors_algorithm`, in the order they happen
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    // N = base^exponent, found before any base 'a' is tried
+    PerfectPower { base: BigUint, exponent: u32 },
+    // A new random base was picked
+    TryingBase { attempt: u64, a: BigUint },
+    // gcd(a, n) > 1, the base itself is a factor
+    GcdFactor { a: BigUint, factor: BigUint },
+    // Period finding for 'a' is about to start, `backend` is the finder's name
+    FindingPeriod { a: BigUint, backend: &'static str },
+    // A quantum backend measured a phase and post-processed it
+    PhaseMeasured { a: BigUint, analysis: PhaseAnalysis },
+    // A quantum backend ran its circuit(s) for 'a'. `truncation_error` is the largest
+    // weight an approximate simulator (MPS) dropped in one run, 0 for exact ones.
+    CircuitSimulated { a: BigUint, simulator: SimulatorKind, qubits: usize, truncation_error: f64 },
+    // Period finding failed (gcd(a, n) > 1 or the search limit was hit)
+    PeriodNotFound { a: BigUint, backend: &'static str, error: Option<FactorError> },
+    PeriodFound { a: BigUint, r: BigUint },
+    // r is odd, r / 2 is useless
+    OddPeriod { a: BigUint, r: BigUint },
+    // a^(r/2) ≡ -1 (mod n), gcds would be trivial
+    MinusOne { a: BigUint, r: BigUint },
+    // A non-trivial factor came out of gcd(a^(r/2) ± 1, n)
+    FactorFound { a: BigUint, factor: BigUint },
+    // Both gcds were 1 or n
+    TrivialFactors { a: BigUint, r: BigUint },
+}
+
+// Anything that wants to hear about the steps of `shors_algorithm`
+pub trait Observer {
+    fn on_event(&mut self, event: &Event);
+}
+
+// Closures work as observers, handy for counting or collecting events
+impl<F: FnMut(&Event)> Observer for F {
+    fn on_event(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+// Ignores everything
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentObserver;
+
+impl Observer for SilentObserver {
+    fn on_event(&mut self, _event: &Event) {}
+}
+
+// Prints each step to stdout, the way the program always has
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleObserver;
+
+impl Observer for ConsoleObserver {
+    fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::PerfectPower { base, exponent } => println!("N is a perfect power: {}^{}", base, exponent),
+            Event::TryingBase { a, .. } => println!("Trying a = {}", a),
+            Event::GcdFactor { factor, .. } => println!("Found factor (GCD): {}", factor),
+            Event::FindingPeriod { backend: "classical", .. } => println!("Finding period classically (this is the slow part)..."),
+            Event::FindingPeriod { backend, .. } => println!("Finding period with the {} backend...", backend),
+            Event::PeriodNotFound { a, backend, error } => {
+                if let Some(FactorError::PeriodLimitExceeded { .. }) = error {
+                    println!("Period finding exceeded limit for a = {}", a);
+                }
+                match *backend {
+                    "classical" => println!("Could not find period classically for a = {}. Trying another 'a'.", a),
+                    backend => println!("Could not find period with the {} backend for a = {}. Trying another 'a'.", backend, a),
+                }
+            }
+            Event::PhaseMeasured { analysis, .. } => match &analysis.result {
+                Ok(r) => println!("Measured y = {} (of 2^{}): period candidate r = {}", analysis.y, analysis.t, r),
+                Err(why) => println!("Measured y = {} (of 2^{}): {}", analysis.y, analysis.t, why),
+            },
+            Event::CircuitSimulated { simulator, truncation_error, .. } if *truncation_error > 0.0 => {
+                println!("{} truncation dropped {:.3e} of the state's weight", simulator, truncation_error)
+            }
+            Event::CircuitSimulated { .. } => {}
+            Event::PeriodFound { r, .. } => println!("Found period r = {}", r),
+            Event::OddPeriod { .. } => println!("Period 'r' is odd. Trying another 'a'."),
+            Event::MinusOne { .. } => println!("a^(r/2) % n == -1 (mod n). Trying another 'a'."),
+            Event::FactorFound { factor, .. } => println!("Found factor (Shor's): {}", factor),
+            Event::TrivialFactors { .. } => println!("Found trivial factors. Trying another 'a'."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::factor_observed;
+    use crate::period::Backend;
+    use crate::shor::{Factorization, Method};
+
+    fn run(n: u64, config: &Config) -> (Factorization, Vec<Event>) {
+        let mut events = Vec::new();
+        let found = factor_observed(&BigUint::from(n), config, &mut |event: &Event| events.push(event.clone())).unwrap();
+        (found, events)
+    }
+
+    // Walk `events` through the order documented on `Event`, one attempt at a time
+    fn assert_documented_order(found: &Factorization, events: &[Event]) {
+        let mut events = events.iter().peekable();
+        for attempt in 1..=found.attempts {
+            let a = match events.next() {
+                Some(Event::TryingBase { attempt: k, a }) if *k == attempt => a.clone(),
+                other => panic!("attempt {} starts with {:?}", attempt, other),
+            };
+            let last = attempt == found.attempts;
+            match events.next() {
+                Some(Event::GcdFactor { a: b, factor }) => {
+                    assert!(last && *b == a && *factor == found.p);
+                    continue;
+                }
+                Some(Event::FindingPeriod { a: b, .. }) => assert_eq!(*b, a),
+                other => panic!("base {} goes on with {:?}", a, other),
+            }
+            while let Some(Event::PhaseMeasured { a: b, .. } | Event::CircuitSimulated { a: b, .. }) = events.peek() {
+                assert_eq!(*b, a);
+                events.next();
+            }
+            let r = match events.next() {
+                Some(Event::PeriodNotFound { a: b, .. }) => {
+                    assert!(!last && *b == a);
+                    continue;
+                }
+                Some(Event::PeriodFound { a: b, r }) if *b == a => r.clone(),
+                other => panic!("period finding for {} ends with {:?}", a, other),
+            };
+            match events.next() {
+                Some(Event::OddPeriod { a: b, r: s } | Event::MinusOne { a: b, r: s } | Event::TrivialFactors { a: b, r: s }) => assert!(!last && *b == a && *s == r),
+                Some(Event::FactorFound { a: b, .. }) => {
+                    assert!(last && *b == a);
+                    assert_eq!(found.method, Method::Period { a: a.clone(), r });
+                    // Both gcds can be non-trivial, the second is the one returned
+                    if let Some(Event::FactorFound { a: b, .. }) = events.peek() {
+                        assert_eq!(*b, a);
+                        events.next();
+                    }
+                }
+                other => panic!("period {} of {} is followed by {:?}", r, a, other),
+            }
+        }
+        assert_eq!(events.next(), None);
+    }
+
+    #[test]
+    fn events_come_in_the_documented_order() {
+        for n in [15, 21, 33, 35, 91, 1001] {
+            for seed in 0..30 {
+                let (found, events) = run(n, &Config { seed: Some(seed), ..Config::default() });
+                assert_documented_order(&found, &events);
+            }
+        }
+        for backend in [Backend::Quantum, Backend::SemiClassical] {
+            for seed in 0..5 {
+                let (found, events) = run(21, &Config { seed: Some(seed), backend, ..Config::default() });
+                assert_documented_order(&found, &events);
+                // A quantum finder reports its circuit before the period
+                let finding = events.iter().filter(|event| matches!(event, Event::FindingPeriod { .. })).count();
+                assert!(events.iter().filter(|event| matches!(event, Event::CircuitSimulated { .. })).count() >= finding);
+            }
+        }
+    }
+
+    #[test]
+    fn shortcuts_skip_the_bases() {
+        assert_eq!(run(3 * 3 * 3, &Config::default()).1, vec![Event::PerfectPower { base: BigUint::from(3u32), exponent: 3 }]);
+        assert_eq!(run(30, &Config::default()).1, Vec::new());
+    }
+}
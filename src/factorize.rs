@@ -0,0 +1,158 @@
+use crate::budget::Budget;
+use crate::config::Config;
+use crate::error::FactorError;
+use crate::events::Observer;
+use crate::period::PeriodFinder;
+use crate::primality::is_prime;
+use crate::shor::{split, Method};
+use num_bigint::BigUint;
+use num_traits::One;
+use rand::Rng;
+use std::fmt;
+
+// n as a product of primes, sorted ascending, each with its multiplicity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimeFactorization {
+    pub n: BigUint,
+    pub factors: Vec<(BigUint, u32)>,
+    // Seed of the generator that picked the bases, when `factorize` created it
+    pub seed: Option<u64>,
+}
+
+impl PrimeFactorization {
+    // Multiply everything back together, should always give n
+    pub fn product(&self) -> BigUint {
+        self.factors.iter().fold(BigUint::one(), |acc, (p, k)| acc * p.pow(*k))
+    }
+}
+
+impl fmt::Display for PrimeFactorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (p, k)) in self.factors.iter().enumerate() {
+            if i > 0 {
+                write!(f, " * ")?;
+            }
+            if *k == 1 {
+                write!(f, "{}", p)?;
+            } else {
+                write!(f, "{}^{}", p, k)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Keep splitting with `shors_algorithm` until only primes are left.
+// A prime n is not an error here, it simply factors as itself.
+// The timeout covers the whole factorization, the attempt budget each split.
+pub fn factorize<R: Rng + ?Sized>(
+    n: &BigUint,
+    config: &Config,
+    finder: &dyn PeriodFinder,
+    rng: &mut R,
+    observer: &mut dyn Observer,
+) -> Result<PrimeFactorization, FactorError> {
+    if n <= &BigUint::one() {
+        return Err(FactorError::TooSmall(n.clone()));
+    }
+
+    // Parts still to split, each with how many times it divides n
+    let budget = Budget::start(config);
+    let mut primes = Vec::new();
+    let mut pending = vec![(n.clone(), 1u32)];
+    while let Some((part, multiplicity)) = pending.pop() {
+        // Primes end the recursion, only composite parts get split
+        if is_prime(&part) {
+            primes.push((part, multiplicity));
+            continue;
+        }
+        let found = match split(&part, config, &budget, finder, rng, observer) {
+            Ok(found) => found,
+            Err(mut err) => {
+                // Report what the earlier splits already achieved
+                if let Some(progress) = err.progress_mut() {
+                    progress.factors = group_primes(primes);
+                    progress.remaining = pending;
+                    progress.remaining.push((part, multiplicity));
+                }
+                return Err(err);
+            }
+        };
+        if let Method::PerfectPower { exponent } = found.method {
+            pending.push((found.p, multiplicity * exponent));
+        } else {
+            pending.push((found.p, multiplicity));
+            pending.push((found.q, multiplicity));
+        }
+    }
+
+    Ok(PrimeFactorization { n: n.clone(), factors: group_primes(primes), seed: None })
+}
+
+// Sort primes and merge repeats, adding up their multiplicities
+fn group_primes(mut primes: Vec<(BigUint, u32)>) -> Vec<(BigUint, u32)> {
+    primes.sort();
+    let mut factors: Vec<(BigUint, u32)> = Vec::new();
+    for (p, m) in primes {
+        match factors.last_mut() {
+            Some((last, k)) if *last == p => *k += m,
+            _ => factors.push((p, m)),
+        }
+    }
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SilentObserver;
+    use crate::period::BabyStepGiantStep;
+    use crate::seeded_rng;
+
+    fn prime_factors(n: u64, seed: u64) -> Result<PrimeFactorization, FactorError> {
+        factorize(&BigUint::from(n), &Config::default(), &BabyStepGiantStep::default(), &mut seeded_rng(seed), &mut SilentObserver)
+    }
+
+    fn multiset(pairs: &[(u64, u32)]) -> Vec<(BigUint, u32)> {
+        pairs.iter().map(|&(p, k)| (BigUint::from(p), k)).collect()
+    }
+
+    #[test]
+    fn finds_every_prime_with_its_multiplicity() {
+        let cases: [(u64, &[(u64, u32)]); 8] = [
+            (2, &[(2, 1)]),
+            (97, &[(97, 1)]),
+            (15, &[(3, 1), (5, 1)]),
+            (360, &[(2, 3), (3, 2), (5, 1)]),
+            (3u64.pow(10), &[(3, 10)]),
+            (3 * 5 * 7 * 11 * 13, &[(3, 1), (5, 1), (7, 1), (11, 1), (13, 1)]),
+            (7u64.pow(4) * 11u64.pow(2) * 13, &[(7, 4), (11, 2), (13, 1)]),
+            (101 * 101 * 1_000_003, &[(101, 2), (1_000_003, 1)]),
+        ];
+        for (n, expected) in cases {
+            for seed in 0..5 {
+                let found = prime_factors(n, seed).unwrap();
+                assert_eq!(found.factors, multiset(expected), "{} with seed {}", n, seed);
+                assert_eq!(found.product(), BigUint::from(n));
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_nothing_to_factor() {
+        for n in [0, 1] {
+            assert_eq!(prime_factors(n, 0), Err(FactorError::TooSmall(BigUint::from(n))));
+        }
+    }
+
+    #[test]
+    fn displays_as_a_product() {
+        assert_eq!(prime_factors(360, 0).unwrap().to_string(), "2^3 * 3^2 * 5");
+        assert_eq!(prime_factors(97, 0).unwrap().to_string(), "97");
+    }
+
+    #[test]
+    fn repeats_are_merged() {
+        assert_eq!(group_primes(multiset(&[(5, 1), (3, 2), (5, 2), (2, 1)])), multiset(&[(2, 1), (3, 2), (5, 3)]));
+    }
+}
@@ -0,0 +1,122 @@
+// Shor's algorithm for integer factorization. The period-finding step is a
+// pluggable `PeriodFinder`: classical searches (the default), or the quantum
+// order-finding circuit, full or semi-classical, run on a simulator.
+// `factor` is the entry point, the building blocks are public for callers that
+// want to drive the steps themselves.
+
+pub mod arithmetic;
+mod budget;
+pub mod circuit;
+mod config;
+mod error;
+mod events;
+mod factorize;
+pub mod math;
+mod noise_report;
+pub mod period;
+pub mod postprocess;
+pub mod primality;
+pub mod quantum;
+pub mod resources;
+mod sampling;
+mod shor;
+
+pub use budget::{Budget, CancelToken, Interrupt, Progress};
+pub use config::Config;
+pub use error::FactorError;
+pub use events::{ConsoleObserver, Event, Observer, SilentObserver};
+pub use factorize::PrimeFactorization;
+pub use noise_report::{noise_report, NoiseReport, NoiseReportRow};
+pub use period::{find_period_classical, BabyStepGiantStep, Backend, ExactOrder, PeriodContext, PeriodFinder, QuantumOrderFinding, SemiClassicalOrderFinding};
+pub use sampling::{ideal_probability, sample_order_finding, SampleReport, SampleRow, THEORY_FLOOR, THEORY_MAX_BITS};
+pub use shor::{factor_from_period, shors_algorithm, Factorization, Method};
+
+use num_bigint::BigUint;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+// The generator behind every run of `factor` and `factorize`.
+// The same seed always picks the same bases.
+pub fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+// The seed from `config`, or a fresh random one
+fn resolve_seed(config: &Config) -> u64 {
+    config.seed.unwrap_or_else(|| thread_rng().r#gen())
+}
+
+// Record the seed in the progress report of a failed run
+fn with_seed(mut err: FactorError, seed: u64) -> FactorError {
+    if let Some(progress) = err.progress_mut() {
+        progress.seed = Some(seed);
+    }
+    err
+}
+
+// Find one non-trivial split n = p * q
+pub fn factor(n: &BigUint, config: &Config) -> Result<Factorization, FactorError> {
+    factor_observed(n, config, &mut SilentObserver)
+}
+
+// Same as `factor`, reporting every step to `observer`
+pub fn factor_observed(n: &BigUint, config: &Config, observer: &mut dyn Observer) -> Result<Factorization, FactorError> {
+    let seed = resolve_seed(config);
+    let mut rng = seeded_rng(seed);
+    match shors_algorithm(n, config, config.backend.finder(config).as_ref(), &mut rng, observer) {
+        Ok(found) => Ok(Factorization { seed: Some(seed), ..found }),
+        Err(err) => Err(with_seed(err, seed)),
+    }
+}
+
+// Split n all the way down to its prime factors
+pub fn factorize(n: &BigUint, config: &Config) -> Result<PrimeFactorization, FactorError> {
+    factorize_observed(n, config, &mut SilentObserver)
+}
+
+// Same as `factorize`, reporting the steps of every split to `observer`
+pub fn factorize_observed(n: &BigUint, config: &Config, observer: &mut dyn Observer) -> Result<PrimeFactorization, FactorError> {
+    let seed = resolve_seed(config);
+    let mut rng = seeded_rng(seed);
+    match factorize::factorize(n, config, config.backend.finder(config).as_ref(), &mut rng, observer) {
+        Ok(found) => Ok(PrimeFactorization { seed: Some(seed), ..found }),
+        Err(err) => Err(with_seed(err, seed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::NoiseModel;
+
+    fn events(n: u64, config: &Config) -> (Result<PrimeFactorization, FactorError>, Vec<Event>) {
+        let mut events = Vec::new();
+        let found = factorize_observed(&BigUint::from(n), config, &mut |event: &Event| events.push(event.clone()));
+        (found, events)
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let noisy = Config { backend: Backend::SemiClassical, noise: "depolarizing=0.002".parse::<NoiseModel>().unwrap(), ..Config::default() };
+        for config in [Config::default(), Config { backend: Backend::Quantum, ..Config::default() }, noisy] {
+            let config = Config { seed: Some(11), ..config };
+            let (first, first_events) = events(3 * 5 * 7 * 11, &config);
+            let (second, second_events) = events(3 * 5 * 7 * 11, &config);
+            assert_eq!(first.unwrap(), second.unwrap());
+            assert_eq!(first_events, second_events);
+            assert!(first_events.iter().any(|event| matches!(event, Event::TryingBase { .. })));
+            assert_eq!(factor(&BigUint::from(91u32), &config), factor(&BigUint::from(91u32), &config));
+        }
+    }
+
+    #[test]
+    fn seed_is_reported() {
+        let config = Config { seed: Some(42), ..Config::default() };
+        assert_eq!(factor(&BigUint::from(91u32), &config).unwrap().seed, Some(42));
+        assert_eq!(factorize(&BigUint::from(91u32), &config).unwrap().seed, Some(42));
+        // A fresh seed, reported so the run can be repeated
+        let found = factor(&BigUint::from(91u32), &Config::default()).unwrap();
+        let again = factor(&BigUint::from(91u32), &Config { seed: found.seed, ..Config::default() }).unwrap();
+        assert_eq!(found, again);
+    }
+}
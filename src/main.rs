@@ -1,160 +1,415 @@
-use num_bigint::{BigUint, RandBigInt, ToBigUint};
+use num_bigint::BigUint;
 use num_integer::Integer;
-use num_traits::{One, Zero, CheckedSub}; // Import CheckedSub
-use rand::thread_rng;
-use rand::Rng; // Import Rng
+use num_traits::ToPrimitive;
+use shors::circuit::{
+    from_qasm2, modexp_circuit, order_finding_circuit, semiclassical_order_finding_circuit, to_qasm3, Circuit, Optimizer, Oracle, Pass, Qft,
+};
+use shors::period::carmichael_lambda_factors;
+use shors::postprocess::{analyze_histogram, DEFAULT_MAX_MULTIPLE};
+use shors::primality::is_prime;
+use shors::resources::{estimate_resources, physical_report, Assumptions, SurfaceCode};
+use shors::{
+    factor_from_period, factor_observed, factorize_observed, noise_report, sample_order_finding, seeded_rng, Config, ConsoleObserver, FactorError, Method, Progress,
+};
+use std::env;
+use std::fs;
 use std::io;
-use std::time::Instant; // Import Instant
+use std::time::{Duration, Instant}; // Import Instant
 
-// Function to compute the greatest common divisor (GCD)
-fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
-    a.gcd(b)
+const USAGE: &str = "Usage: shorsAlgorithm [--full] [--max-bases COUNT] [--timeout SECONDS] [--seed SEED] [--backend NAME]
+       [--order-multiple FACTORS] [--known-factors FACTORS] [--noise MODEL] [--simulator NAME] [--noise-report SCALES [--runs COUNT]]
+       [--qasm3 CIRCUIT [--base A] [--oracle NAME]] [--optimize CIRCUIT [--passes PASSES] [--drop-below ANGLE]]
+       [--qasm2 FILE [--base A] [--shots COUNT]] [--sample CIRCUIT [--base A] [--oracle NAME] [--shots COUNT] [--export FILE]]
+       [--resources BITS [--assume ASSUMPTIONS] [--surface-code PARAMETERS]] [N]
+FACTORS is a product of prime powers such as 2^4*3*5 (commas work too)
+MODEL is a list like depolarizing=0.001,cx:depolarizing=0.01,readout=0.02
+(channels: depolarizing, amplitude-damping, phase-damping), SCALES a list like 0,0.5,1,2
+NAME for --simulator is statevector, density or mps[:BOND]
+CIRCUIT is qft, modexp, order-finding or semiclassical (only the last two for --sample), --oracle permutation or beauregard
+FILE for --export ends in .csv or .json
+PASSES is a list of cancel, merge, drop and commute (all by default), ANGLE in radians
+ASSUMPTIONS is a list like exponent-bits=3072,exp-window=4,aqft-cutoff=exact
+PARAMETERS is a list like physical-error=0.001,cycle-time-us=1,reaction-time-us=10,factories=20";
+
+// What to do instead of factoring N once, see USAGE
+enum Mode {
+    Factor,
+    // Success rate of the noise model at each of these scales
+    NoiseReport(Vec<f64>),
+    // Print this circuit for N as OpenQASM 3
+    Qasm3(String),
+    // Optimize this circuit for N and report the gate counts
+    Optimize(String),
+    // Run this OpenQASM 2 file and read its outcomes as phases
+    Qasm2(String),
+    // Sample this order-finding circuit for N
+    Sample(String),
+    // Estimate the resources for a modulus of this many bits, no N needed
+    Resources(u64),
+}
+
+// Command line options, see USAGE
+struct Args {
+    mode: Mode,
+    // Factor all the way down to primes instead of stopping at one split
+    full: bool,
+    config: Config,
+    // Prime factors of N, turned into λ(N) for the exact backend once N is known
+    known_factors: Option<Vec<(BigUint, u32)>>,
+    // Runs per scale for the noise report
+    runs: u64,
+    // Base 'a' of the circuits (None = the smallest one coprime to N)
+    base: Option<u64>,
+    oracle: Oracle,
+    optimizer: Optimizer,
+    shots: usize,
+    // Where to write the sampled histogram
+    export: Option<String>,
+    assumptions: Assumptions,
+    // The machine the estimates run on
+    surface_code: SurfaceCode,
+    n: Option<String>,
 }
 
-// Function for modular exponentiation (base^exp % modulus)
-fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
-    base.modpow(exponent, modulus)
+// Parse a product of prime powers like 2^4*3*5 or 61,79
+fn parse_factored(s: &str) -> Result<Vec<(BigUint, u32)>, String> {
+    s.split(['*', ','])
+        .map(|item| {
+            let (p, e) = item.trim().split_once('^').unwrap_or((item.trim(), "1"));
+            let p = BigUint::parse_bytes(p.as_bytes(), 10).ok_or_else(|| format!("Invalid factor {}", item))?;
+            let e = e.parse().ok().filter(|&e| e > 0).ok_or_else(|| format!("Invalid exponent in {}", item))?;
+            Ok((p, e))
+        })
+        .collect()
 }
 
-// Classical period finding function (find smallest r > 0 such that a^r % n == 1)
-// This is the part that a quantum computer speeds up significantly.
-fn find_period_classical(a: &BigUint, n: &BigUint) -> Option<BigUint> {
-    if gcd(a, n) != BigUint::one() {
-        // 'a' shares a factor with 'n', this case is handled before calling find_period
-        return None;
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        mode: Mode::Factor,
+        full: false,
+        config: Config::default(),
+        known_factors: None,
+        runs: 20,
+        base: None,
+        oracle: Oracle::default(),
+        optimizer: Optimizer::default(),
+        shots: 1024,
+        export: None,
+        assumptions: Assumptions::default(),
+        surface_code: SurfaceCode::default(),
+        n: None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        // The value after `arg`, described as `what` when it is missing
+        let mut value = |what: &str| iter.next().ok_or_else(|| format!("{} needs {}", arg, what));
+        let mut mode = None;
+        match arg.as_str() {
+            "--full" => args.full = true,
+            "--max-bases" => {
+                let value = value("a count")?;
+                let max: u64 = value.parse().map_err(|_| format!("Invalid base count {}", value))?;
+                // 0 means no limit at all
+                args.config.max_attempts = if max == 0 { None } else { Some(max) };
+            }
+            "--timeout" => {
+                let value = value("a number of seconds")?;
+                let secs: f64 = value.parse().map_err(|_| format!("Invalid timeout {}", value))?;
+                let timeout = Duration::try_from_secs_f64(secs).map_err(|_| format!("Invalid timeout {}", value))?;
+                args.config.timeout = Some(timeout);
+            }
+            "--seed" => {
+                let value = value("a number")?;
+                args.config.seed = Some(value.parse().map_err(|_| format!("Invalid seed {}", value))?);
+            }
+            "--backend" => args.config.backend = value("a name")?.parse()?,
+            "--order-multiple" => args.config.order_multiple = parse_factored(&value("factors")?)?,
+            "--known-factors" => args.known_factors = Some(parse_factored(&value("factors")?)?),
+            "--noise" => args.config.noise = value("a model")?.parse()?,
+            "--simulator" => args.config.simulator = value("a name")?.parse()?,
+            "--noise-report" => {
+                let scales = value("scale factors")?
+                    .split(',')
+                    .map(|x| x.trim().parse::<f64>().ok().filter(|x| *x >= 0.0).ok_or_else(|| format!("Invalid scale {}", x)))
+                    .collect::<Result<_, _>>()?;
+                mode = Some(Mode::NoiseReport(scales));
+            }
+            "--runs" => {
+                let value = value("a count")?;
+                args.runs = value.parse().map_err(|_| format!("Invalid run count {}", value))?;
+            }
+            "--qasm3" => mode = Some(Mode::Qasm3(value("a circuit name")?)),
+            "--optimize" => mode = Some(Mode::Optimize(value("a circuit name")?)),
+            "--passes" => args.optimizer.passes = value("a list of passes")?.split(',').map(|p| p.trim().parse()).collect::<Result<Vec<Pass>, _>>()?,
+            "--drop-below" => {
+                let value = value("an angle")?;
+                args.optimizer.min_angle = value.parse().ok().filter(|a: &f64| *a >= 0.0).ok_or_else(|| format!("Invalid angle {}", value))?;
+            }
+            "--qasm2" => mode = Some(Mode::Qasm2(value("a file")?)),
+            "--shots" => {
+                let value = value("a count")?;
+                args.shots = value.parse().ok().filter(|&s| s > 0).ok_or_else(|| format!("Invalid shot count {}", value))?;
+            }
+            "--sample" => mode = Some(Mode::Sample(value("a circuit name")?)),
+            "--export" => args.export = Some(value("a file")?),
+            "--resources" => {
+                let value = value("a bit length")?;
+                mode = Some(Mode::Resources(value.parse().ok().filter(|&b| b >= 2).ok_or_else(|| format!("Invalid bit length {}", value))?));
+            }
+            "--assume" => args.assumptions = value("a list of assumptions")?.parse()?,
+            "--surface-code" => args.surface_code = value("a list of parameters")?.parse()?,
+            "--base" => {
+                let value = value("a number")?;
+                args.base = Some(value.parse().map_err(|_| format!("Invalid base {}", value))?);
+            }
+            "--oracle" => args.oracle = value("a name")?.parse()?,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            _ => args.n = Some(arg),
+        }
+        if let Some(mode) = mode {
+            if !matches!(args.mode, Mode::Factor) {
+                return Err("Only one of --noise-report, --qasm3, --optimize, --qasm2, --sample and --resources can be given".to_string());
+            }
+            args.mode = mode;
+        }
     }
+    Ok(args)
+}
 
-    let one = BigUint::one();
-    let mut r = BigUint::one();
-    let mut x = a % n;
+// The circuit called `what` for N, with the base it was built for
+fn build_circuit(what: &str, n: &BigUint, base: Option<u64>, oracle: Oracle) -> Result<(Circuit, u64), String> {
+    let n = n.to_u64().filter(|&n| n > 2).ok_or_else(|| format!("Cannot build circuits for N = {}", n))?;
+    let a = match base {
+        Some(a) if a < 2 || a >= n || a.gcd(&n) != 1 => return Err(format!("The base must be coprime to {} and between 2 and {}", n, n - 1)),
+        Some(a) => a,
+        None => (2..n).find(|a| a.gcd(&n) == 1).unwrap_or(2),
+    };
+    let qft = Qft::exact();
+    let circuit = match what {
+        // On the 2m qubits of the phase register
+        "qft" => qft.circuit(2 * (u64::BITS - n.leading_zeros()) as usize),
+        "modexp" => modexp_circuit(a, n, &qft, oracle).map_err(|e| e.to_string())?.circuit,
+        "order-finding" => order_finding_circuit(a, n, &qft, oracle).map_err(|e| e.to_string())?.circuit,
+        "semiclassical" => semiclassical_order_finding_circuit(a, n, &qft, oracle).map_err(|e| e.to_string())?.circuit,
+        _ => return Err(format!("Unknown circuit {} (available: qft, modexp, order-finding, semiclassical)", what)),
+    };
+    Ok((circuit, a))
+}
 
-    // Limit the search for practical reasons in this classical simulation
-    let limit = n * n; // A reasonable upper bound heuristic, though not guaranteed
+// The circuit called `what` for N, as OpenQASM 3
+fn export_qasm3(what: &str, n: &BigUint, base: Option<u64>, oracle: Oracle) -> Result<String, String> {
+    let (circuit, a) = build_circuit(what, n, base, oracle)?;
+    let header = match what {
+        "qft" => format!("// qft circuit on {} qubits, the phase register for N = {}\n", circuit.num_qubits, n),
+        _ => format!("// {} circuit for a = {}, N = {} ({} oracle)\n", what, a, n, oracle),
+    };
+    to_qasm3(&circuit).map(|qasm| header + &qasm).map_err(|e| e.to_string())
+}
+
+// Run the OpenQASM 2 program in `path` and, given a base, post-process its
+// outcomes as phases of a mod N the way `shors_algorithm` does
+fn run_qasm2(path: &str, n: &BigUint, base: Option<u64>, config: &Config, shots: usize) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let circuit = from_qasm2(&source).map_err(|e| format!("{}: {}", path, e))?;
+    let seed = config.seed.unwrap_or_else(rand::random);
+    println!("{}: {} qubits, {} classical bits, {} gates", path, circuit.num_qubits, circuit.num_clbits, circuit.gate_count());
+    println!("Seed: {} (replay with --seed {})", seed, seed);
+    let histogram = circuit.histogram(config.simulator, &config.noise, shots, &mut seeded_rng(seed)).map_err(|e| e.to_string())?;
+    let t = circuit.num_clbits;
 
-    while x != one {
-        x = (&x * a) % n;
-        r += &one;
-        if r > limit {
-            // Period finding took too long, might be very large or 'a' was unlucky
-            println!("Period finding exceeded limit for a = {}", a);
-            return None;
+    let Some(a) = base.map(BigUint::from) else {
+        for (y, count) in &histogram {
+            println!("  {:0t$b}  {:>8} shots ({:5.1}%)", y, count, 100.0 * *count as f64 / shots as f64, t = t);
         }
+        println!("Pass --base A to read the outcomes as phases of A mod {}", n);
+        return Ok(());
+    };
+    if a < BigUint::from(2u32) || &a >= n || a.gcd(n) != BigUint::from(1u32) {
+        return Err(format!("The base must be coprime to {} and between 2 and {}", n, n - 1u32));
     }
-    Some(r)
+    let analysis = analyze_histogram(&histogram, t, &a, n, DEFAULT_MAX_MULTIPLE);
+    print!("{}", analysis);
+    if let Some(r) = &analysis.period {
+        match factor_from_period(&a, r, n, &mut ConsoleObserver) {
+            Some(p) => println!("\nFactors found: {} and {}", p, n / &p),
+            None => println!("\nNo factor from a = {} and r = {}", a, r),
+        }
+    }
+    Ok(())
+}
+
+// Sample the order-finding circuit called `what` for N, print the report and, given
+// `export`, write it as CSV or JSON by the file's extension
+fn run_sample(what: &str, n: &BigUint, base: Option<u64>, oracle: Oracle, config: &Config, shots: usize, export: Option<&str>) -> Result<(), String> {
+    let semiclassical = match what {
+        "order-finding" => false,
+        "semiclassical" => true,
+        _ => return Err(format!("Unknown circuit {} (available: order-finding, semiclassical)", what)),
+    };
+    let format = match export {
+        Some(path) if path.ends_with(".csv") => Some((path, false)),
+        Some(path) if path.ends_with(".json") => Some((path, true)),
+        Some(path) => return Err(format!("Cannot tell the export format of {} (use .csv or .json)", path)),
+        None => None,
+    };
+    let small = n.to_u64().filter(|&n| n > 2).ok_or_else(|| format!("Cannot build circuits for N = {}", n))?;
+    let a = match base {
+        Some(a) if a < 2 || a >= small || a.gcd(&small) != 1 => return Err(format!("The base must be coprime to {} and between 2 and {}", n, n - 1u32)),
+        Some(a) => a,
+        None => (2..small).find(|a| a.gcd(&small) == 1).unwrap_or(2),
+    };
+    let report = sample_order_finding(n, &BigUint::from(a), semiclassical, oracle, config, shots).map_err(|e| e.to_string())?;
+    print!("{}", report);
+    if let Some((path, json)) = format {
+        let contents = if json { report.to_json() } else { report.to_csv() };
+        fs::write(path, contents).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+        println!("Histogram written to {}", path);
+    }
+    Ok(())
 }
 
-// Shor's algorithm implementation (using classical period finding)
-fn shors_algorithm(n: &BigUint) -> Option<(BigUint, BigUint)> {
-    if n.is_even() {
-        return Some((2u32.to_biguint().unwrap(), n / 2u32));
+// Show what a run that gave up had achieved
+fn print_progress(progress: &Progress) {
+    println!("Bases tried for the last split: {}", progress.attempts);
+    for (a, r) in &progress.periods {
+        println!("  a = {} has period r = {}, no factor", a, r);
     }
-    if n <= &BigUint::one() {
-        println!("Number must be greater than 1.");
-        return None;
+    let list = |parts: &[(BigUint, u32)]| {
+        parts.iter().map(|(p, k)| if *k == 1 { p.to_string() } else { format!("{}^{}", p, k) }).collect::<Vec<_>>().join(" * ")
+    };
+    if !progress.factors.is_empty() {
+        println!("Prime factors found so far: {}", list(&progress.factors));
     }
-    // Check if n is prime (using a simple primality test for demonstration)
-    // A more robust primality test (like Miller-Rabin) should be used for larger numbers.
-    // This basic check is omitted for brevity, assuming n is composite.
-
-    let one = BigUint::one();
-    let two = 2u32.to_biguint().unwrap();
-    let mut rng = thread_rng();
-
-    loop {
-        // 1. Pick a random number 'a' such that 1 < a < n
-        let a = rng.gen_biguint_range(&two, n);
-        println!("Trying a = {}", a);
-
-        // 2. Compute gcd(a, n)
-        let common_divisor = gcd(&a, n);
-        if common_divisor > one {
-            println!("Found factor (GCD): {}", common_divisor);
-            return Some((common_divisor.clone(), n / common_divisor));
-        }
+    println!("Still to factor: {}", list(&progress.remaining));
+}
 
-        // 3. Find the period 'r' of a^x mod n
-        // *** This is where the Quantum Fourier Transform would be used on a quantum computer ***
-        println!("Finding period classically (this is the slow part)...");
-        let r_opt = find_period_classical(&a, n);
+// N from the command line, or failing that from stdin
+fn read_n(args: &Args) -> Result<BigUint, String> {
+    let input = args.n.clone().unwrap_or_else(|| {
+        println!("Enter the number (N) to factor:");
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        input
+    });
+    BigUint::parse_bytes(input.trim().as_bytes(), 10).ok_or_else(|| "Invalid number input.".to_string())
+}
 
-        if r_opt.is_none() {
-            println!("Could not find period classically for a = {}. Trying another 'a'.", a);
-            continue; // Try a different 'a'
-        }
-        let r = r_opt.unwrap();
-        println!("Found period r = {}", r);
+// Logical estimates for a modulus of `bits` bits, then what they take on the surface code
+fn print_resources(bits: u64, args: &Args) {
+    let report = estimate_resources(bits, &args.assumptions);
+    print!("{}", report);
+    println!();
+    print!("{}", physical_report(&report, &args.surface_code));
+}
 
-        // 4. Check if 'r' is even
-        if r.is_odd() {
-            println!("Period 'r' is odd. Trying another 'a'.");
-            continue;
+// Run the optimizer on the circuit called `what` for N and print what it removed
+fn run_optimize(what: &str, n: &BigUint, args: &Args) -> Result<(), String> {
+    let (circuit, a) = build_circuit(what, n, args.base, args.oracle)?;
+    let passes: Vec<String> = args.optimizer.passes.iter().map(|p| p.to_string()).collect();
+    match what {
+        "qft" => println!("Optimizing the qft circuit on {} qubits, passes {}", circuit.num_qubits, passes.join(", ")),
+        _ => println!("Optimizing the {} circuit for a = {}, N = {} ({} oracle), passes {}", what, a, n, args.oracle, passes.join(", ")),
+    }
+    let start_time = Instant::now();
+    let (_, report) = args.optimizer.optimize(&circuit);
+    print!("{}", report);
+    println!("Optimization took: {:?}", start_time.elapsed());
+    Ok(())
+}
+
+// The config for factoring N: the known factors checked and turned into λ(N), and
+// the seed picked here so it is printed before the run, even one that never ends
+fn factoring_config(n: &BigUint, args: &Args) -> Result<Config, String> {
+    if let Some(known) = &args.known_factors {
+        let product = known.iter().fold(BigUint::from(1u32), |acc, (p, e)| acc * p.pow(*e));
+        if product != *n {
+            return Err(format!("The known factors multiply to {}, not {}.", product, n));
+        }
+        if let Some((p, _)) = known.iter().find(|(p, _)| !is_prime(p)) {
+            return Err(format!("Known factor {} is not prime.", p));
         }
+    }
+    let mut config = args.config.clone();
+    let seed = *config.seed.get_or_insert_with(rand::random);
+    println!("Attempting to factor N = {}", n);
+    println!("Seed: {} (replay with --seed {})", seed, seed);
+    if let Some(known) = &args.known_factors {
+        config.order_multiple = carmichael_lambda_factors(known).map_err(|err| format!("Could not compute λ(N) from the known factors: {}", err))?;
+    }
+    Ok(config)
+}
 
-        // 5. Check if a^(r/2) % n == n - 1 (or a^(r/2) == -1 mod n)
-        let r_half = &r / &two;
-        let term = modpow(&a, &r_half, n);
+// Factor N once, or all the way down to primes with `full`, and report how it went
+fn run_factor(n: &BigUint, config: &Config, full: bool) {
+    // Start timing
+    let start_time = Instant::now();
 
-        let n_minus_1 = n - &one;
-        if term == n_minus_1 {
-            println!("a^(r/2) % n == -1 (mod n). Trying another 'a'.");
-            continue;
-        }
+    let result = if full {
+        factorize_observed(n, config, &mut ConsoleObserver).map(|f| {
+            println!("\nPrime factorization: {} = {}", f.n, f);
+        })
+    } else {
+        factor_observed(n, config, &mut ConsoleObserver).map(|f| {
+            if let Method::PerfectPower { exponent } = f.method {
+                println!("\nPerfect power: {} = {}^{}", f.n, f.p, exponent);
+                return;
+            }
+            println!("\nFactors found: {} and {}", f.p, f.q);
+            // Use references for multiplication within println!
+            println!("Verification: {} * {} = {}", f.p, f.q, &f.p * &f.q);
+        })
+    };
 
-        // 6. Compute factors
-        let factor1 = gcd(&(term.clone() + &one), n);
-        // Use checked_sub which is now in scope
-        let factor2 = gcd(&(term.checked_sub(&one).unwrap_or_else(|| n.clone() + term.clone() - &one)), n); // Handles potential underflow if term is 0 or 1
+    // Calculate duration
+    let duration = start_time.elapsed();
 
-        if factor1 != one && factor1 != *n {
-             println!("Found factor (Shor's): {}", factor1);
+    match result {
+        Ok(()) => {}
+        Err(FactorError::TooSmall(_)) => {
+            println!("\nPlease enter a composite number greater than 3.");
         }
-         if factor2 != one && factor2 != *n {
-             println!("Found factor (Shor's): {}", factor2);
-             return Some((factor2.clone(), n / factor2));
+        Err(err) => {
+            println!("\nFailed to find factors: {}", err);
+            if let Some(progress) = err.progress() {
+                print_progress(progress);
+            }
         }
+    }
+    // Print the duration
+    println!("Computation took: {:?}", duration);
+}
 
-        // If factors are trivial (1 or n), try another 'a'
-        println!("Found trivial factors. Trying another 'a'.");
+fn run(args: &Args) -> Result<(), String> {
+    match &args.mode {
+        Mode::Resources(bits) => print_resources(*bits, args),
+        Mode::Qasm3(what) => print!("{}", export_qasm3(what, &read_n(args)?, args.base, args.oracle)?),
+        Mode::Optimize(what) => run_optimize(what, &read_n(args)?, args)?,
+        Mode::Qasm2(path) => run_qasm2(path, &read_n(args)?, args.base, &args.config, args.shots)?,
+        Mode::Sample(what) => run_sample(what, &read_n(args)?, args.base, args.oracle, &args.config, args.shots, args.export.as_deref())?,
+        Mode::NoiseReport(scales) => {
+            let n = read_n(args)?;
+            let config = factoring_config(&n, args)?;
+            let report = noise_report(&n, &config, scales, args.runs).map_err(|err| format!("Noise report failed: {}", err))?;
+            print!("{}", report);
+        }
+        Mode::Factor => {
+            let n = read_n(args)?;
+            run_factor(&n, &factoring_config(&n, args)?, args.full);
+        }
     }
+    Ok(())
 }
 
 fn main() {
-    println!("Enter the number (N) to factor:");
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("Failed to read line");
-    let n_str = input.trim();
-
-    match BigUint::parse_bytes(n_str.as_bytes(), 10) {
-        Some(n) => {
-            if n < 4u32.to_biguint().unwrap() {
-                 println!("Please enter a composite number greater than 3.");
-                 return;
-            }
-            println!("Attempting to factor N = {}", n);
-
-            // Start timing
-            let start_time = Instant::now();
-
-            let result = shors_algorithm(&n);
-
-            // Calculate duration
-            let duration = start_time.elapsed();
-
-            match result {
-                Some((p, q)) => {
-                    println!("\nFactors found: {} and {}", p, q);
-                    // Use references for multiplication within println!
-                    println!("Verification: {} * {} = {}", p, q, &p * &q);
-                }
-                None => {
-                    println!("\nFailed to find factors. The number might be prime or the algorithm failed (e.g., period finding limit exceeded).");
-                }
-            }
-            // Print the duration
-            println!("Computation took: {:?}", duration);
-        }
-        None => {
-            println!("Invalid number input.");
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(msg) => {
+            println!("{}", msg);
+            println!("{}", USAGE);
+            return;
         }
+    };
+    if let Err(msg) = run(&args) {
+        println!("{}", msg);
     }
-}
\ No newline at end of file
+}
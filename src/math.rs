@@ -0,0 +1,73 @@
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+// Function to compute the greatest common divisor (GCD)
+pub fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    a.gcd(b)
+}
+
+// Function for modular exponentiation (base^exp % modulus)
+pub fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    base.modpow(exponent, modulus)
+}
+
+// Floor of the k-th root of n
+pub fn integer_root(n: &BigUint, k: u32) -> BigUint {
+    n.nth_root(k)
+}
+
+// Detect n = base^k with k >= 2, trying every exponent up to log2(n).
+// The largest exponent wins, so the base returned is as small as possible.
+pub fn perfect_power(n: &BigUint) -> Option<(BigUint, u32)> {
+    let max_k = n.bits().saturating_sub(1);
+    for k in (2..=max_k as u32).rev() {
+        let root = integer_root(n, k);
+        if root > BigUint::one() && root.pow(k) == *n {
+            return Some((root, k));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(n: impl Into<BigUint>) -> Option<(BigUint, u32)> {
+        perfect_power(&n.into())
+    }
+
+    #[test]
+    fn perfect_power_finds_the_smallest_base() {
+        for k in 2..=70 {
+            assert_eq!(power(BigUint::one() << k), Some((BigUint::from(2u32), k as u32)), "2^{}", k);
+        }
+        assert_eq!(power(243u32), Some((BigUint::from(3u32), 5)));
+        // 6^6 is also 36^3 and 216^2
+        assert_eq!(power(46656u32), Some((BigUint::from(6u32), 6)));
+        // A 1270-bit power of the Mersenne prime 2^127 - 1
+        let p = (BigUint::one() << 127u32) - 1u32;
+        assert_eq!(power(p.pow(10)), Some((p, 10)));
+    }
+
+    #[test]
+    fn perfect_power_rejects_the_rest() {
+        for n in [0u32, 1, 2, 3, 6, 12, 15, 242, 244] {
+            assert_eq!(power(n), None, "{}", n);
+        }
+        let two_64 = BigUint::one() << 64u32;
+        assert_eq!(power(&two_64 + 1u32), None);
+        assert_eq!(power(&two_64 - 1u32), None);
+        let p = (BigUint::one() << 127u32) - 1u32;
+        assert_eq!(power(p.pow(3) + 1u32), None);
+        assert_eq!(power(p.pow(3) * 2u32), None);
+    }
+
+    #[test]
+    fn integer_root_floors() {
+        assert_eq!(integer_root(&BigUint::from(26u32), 3), BigUint::from(2u32));
+        assert_eq!(integer_root(&BigUint::from(27u32), 3), BigUint::from(3u32));
+        assert_eq!(integer_root(&((BigUint::one() << 64u32) - 1u32), 2), BigUint::from(u32::MAX));
+    }
+}
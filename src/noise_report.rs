@@ -0,0 +1,179 @@
+use crate::budget::Budget;
+use crate::config::Config;
+use crate::error::FactorError;
+use crate::events::Event;
+use crate::period::{find_period_classical, Backend};
+use crate::quantum::NoiseModel;
+use crate::shor::{shors_algorithm, Method};
+use crate::{resolve_seed, seeded_rng};
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use std::fmt;
+
+// How `shors_algorithm` fared at one noise strength
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseReportRow {
+    // Factor applied to every rate of the base model
+    pub scale: f64,
+    pub runs: u64,
+    // Runs that split N within the attempt budget
+    pub factored: u64,
+    // ... of which through a period (not a lucky gcd)
+    pub by_period: u64,
+    // Bases tried over all runs
+    pub attempts: u64,
+    // Phases measured over all runs, and how many landed on a peak of the true order r
+    // (within 1/2Q of some s/r). Small-multiple post-processing often recovers r even
+    // from noise when N is tiny, the peaks are what shows the circuit still works.
+    pub measurements: u64,
+    pub on_peak: u64,
+}
+
+impl NoiseReportRow {
+    pub fn success_rate(&self) -> f64 {
+        ratio(self.factored, self.runs)
+    }
+
+    pub fn on_peak_rate(&self) -> f64 {
+        ratio(self.on_peak, self.measurements)
+    }
+}
+
+// y / 2^t is within 1 / 2^(t+1) of a multiple of 1 / r
+fn on_peak(y: &BigUint, t: usize, r: &BigUint) -> bool {
+    let q = BigUint::from(1u32) << t;
+    let rem = y * r % &q;
+    let distance = (&q - &rem).min(rem);
+    distance * 2u32 < *r
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 / whole as f64 }
+}
+
+// Success of `shors_algorithm` on one N as the noise of `model` grows
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseReport {
+    pub n: BigUint,
+    pub backend: Backend,
+    pub model: NoiseModel,
+    pub seed: u64,
+    pub rows: Vec<NoiseReportRow>,
+}
+
+// Run `shors_algorithm` on n `runs` times for each scale, with `config.noise`
+// scaled by it. Run i uses seed + i at every scale, so the rows differ only in
+// the noise. Failures to factor are counted; any other error ends the report.
+// The backend has to be one that runs a circuit, the classical ones would succeed
+// at every scale.
+pub fn noise_report(n: &BigUint, config: &Config, scales: &[f64], runs: u64) -> Result<NoiseReport, FactorError> {
+    if !config.backend.uses_noise() {
+        return Err(FactorError::Backend(format!("the {} backend ignores the noise model, use quantum or semiclassical", config.backend)));
+    }
+    let seed = resolve_seed(config);
+    let limit = n * n;
+    // True orders, found classically to judge the measured phases
+    let mut orders: HashMap<BigUint, Option<BigUint>> = HashMap::new();
+    let mut rows = Vec::with_capacity(scales.len());
+    for &scale in scales {
+        let config = Config { noise: config.noise.scaled(scale), ..config.clone() };
+        let finder = config.backend.finder(&config);
+        let mut row = NoiseReportRow { scale, runs, factored: 0, by_period: 0, attempts: 0, measurements: 0, on_peak: 0 };
+        for run in 0..runs {
+            let mut rng = seeded_rng(seed.wrapping_add(run));
+            let (mut measurements, mut peaks, mut attempts) = (0, 0, 0);
+            let mut count = |event: &Event| match event {
+                Event::TryingBase { .. } => attempts += 1,
+                Event::PhaseMeasured { a, analysis } => {
+                    measurements += 1;
+                    let order = orders
+                        .entry(a.clone())
+                        .or_insert_with(|| find_period_classical(a, n, &limit, &Budget::unlimited()).ok().flatten());
+                    peaks += order.as_ref().is_some_and(|r| on_peak(&analysis.y, analysis.t, r)) as u64;
+                }
+                _ => {}
+            };
+            match shors_algorithm(n, &config, finder.as_ref(), &mut rng, &mut count) {
+                Ok(found) => {
+                    row.factored += 1;
+                    row.by_period += matches!(found.method, Method::Period { .. }) as u64;
+                }
+                Err(FactorError::AttemptsExhausted(_) | FactorError::PeriodLimitExceeded { .. }) => {}
+                Err(err) => return Err(err),
+            }
+            row.attempts += attempts;
+            row.measurements += measurements;
+            row.on_peak += peaks;
+        }
+        rows.push(row);
+    }
+    Ok(NoiseReport { n: n.clone(), backend: config.backend, model: config.noise.clone(), seed, rows })
+}
+
+impl fmt::Display for NoiseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Noise report for N = {}, backend {}, seed {}", self.n, self.backend, self.seed)?;
+        writeln!(f, "Base model: {}", self.model)?;
+        writeln!(f, "{:>8}  {:>16}  {:>13}  {:>18}  {:>13}", "scale", "factored", "by period", "phases on peak", "bases per run")?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:>8}  {:>7}/{:<3} {:>3.0}%  {:>13}  {:>7}/{:<5} {:>3.0}%  {:>13.2}",
+                row.scale,
+                row.factored,
+                row.runs,
+                100.0 * row.success_rate(),
+                row.by_period,
+                row.on_peak,
+                row.measurements,
+                100.0 * row.on_peak_rate(),
+                ratio(row.attempts, row.runs),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(backend: Backend) -> Config {
+        Config { backend, noise: NoiseModel::depolarizing(0.01), seed: Some(5), max_attempts: Some(1), ..Config::default() }
+    }
+
+    #[test]
+    fn success_does_not_grow_with_the_noise() {
+        let report = noise_report(&BigUint::from(143u32), &config(Backend::SemiClassical), &[0.0, 1.0, 3.0, 10.0], 20).unwrap();
+        assert_eq!(report.rows.len(), 4);
+        for pair in report.rows.windows(2) {
+            assert!(pair[1].factored <= pair[0].factored, "{}", report);
+            assert!(pair[1].by_period <= pair[0].by_period, "{}", report);
+            assert!(pair[1].on_peak_rate() <= pair[0].on_peak_rate(), "{}", report);
+        }
+        let (ideal, noisiest) = (&report.rows[0], &report.rows[3]);
+        assert!(noisiest.factored < ideal.factored, "{}", report);
+        assert!(ideal.on_peak_rate() > 0.5 && noisiest.on_peak_rate() < 0.1, "{}", report);
+    }
+
+    #[test]
+    fn same_seed_same_report() {
+        let report = |seed| noise_report(&BigUint::from(35u32), &Config { seed: Some(seed), ..config(Backend::SemiClassical) }, &[0.0, 2.0], 10).unwrap();
+        assert_eq!(report(9), report(9));
+    }
+
+    #[test]
+    fn refuses_backends_without_noise() {
+        for backend in [Backend::Classical, Backend::Bsgs, Backend::Exact] {
+            assert!(matches!(noise_report(&BigUint::from(15u32), &config(backend), &[1.0], 1), Err(FactorError::Backend(_))));
+        }
+    }
+
+    #[test]
+    fn peaks_are_within_half_a_step() {
+        let (t, r) = (8, BigUint::from(3u32));
+        // 256 / 3 = 85.33: 85 is on the first peak, 84 and 86 are not
+        let peaks: Vec<u32> = (0..256u32).filter(|&y| on_peak(&BigUint::from(y), t, &r)).collect();
+        assert_eq!(peaks, [0, 85, 171]);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::budget::{Budget, Progress};
+use crate::config::Config;
+use crate::error::FactorError;
+use crate::events::Observer;
+use crate::math::gcd;
+use num_bigint::BigUint;
+use num_traits::One;
+use rand::RngCore;
+use std::fmt;
+use std::str::FromStr;
+
+mod bsgs;
+mod exact;
+mod quantum;
+mod semiclassical;
+
+pub use bsgs::BabyStepGiantStep;
+pub use exact::{carmichael_lambda_factors, order_from_multiple, ExactOrder};
+pub use quantum::QuantumOrderFinding;
+pub(crate) use quantum::check_size;
+pub use semiclassical::SemiClassicalOrderFinding;
+
+// What a period finder gets besides a and n
+pub struct PeriodContext<'a> {
+    // Give up (PeriodLimitExceeded) rather than report a period above this
+    pub limit: &'a BigUint,
+    // Check it regularly in long searches
+    pub budget: &'a Budget,
+    // For backends that sample, e.g. measurements.
+    // This is a separate stream from the one picking the bases,
+    // so every backend sees the same sequence of 'a'.
+    pub rng: &'a mut dyn RngCore,
+    // For backends with steps worth reporting, such as each measurement
+    pub observer: &'a mut dyn Observer,
+}
+
+// An order-finding backend: the smallest r > 0 with a^r ≡ 1 (mod n).
+// Ok(None) means this backend could not find it for this 'a', another 'a' may work.
+pub trait PeriodFinder {
+    fn name(&self) -> &'static str;
+
+    fn find_period(&self, a: &BigUint, n: &BigUint, ctx: &mut PeriodContext) -> Result<Option<BigUint>, FactorError>;
+}
+
+// The backends the command line can pick from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Classical,
+    Bsgs,
+    // Needs `Config::order_multiple`
+    Exact,
+    Quantum,
+    SemiClassical,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 5] = [Backend::Classical, Backend::Bsgs, Backend::Exact, Backend::Quantum, Backend::SemiClassical];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Classical => "classical",
+            Backend::Bsgs => "bsgs",
+            Backend::Exact => "exact",
+            Backend::Quantum => "quantum",
+            Backend::SemiClassical => "semiclassical",
+        }
+    }
+
+    // Whether it runs a circuit, so that `Config::noise` and `Config::simulator` matter
+    pub fn uses_noise(&self) -> bool {
+        matches!(self, Backend::Quantum | Backend::SemiClassical)
+    }
+
+    // A finder with this backend's default settings, plus whatever it needs from `config`
+    pub fn finder(&self, config: &Config) -> Box<dyn PeriodFinder> {
+        match self {
+            Backend::Classical => Box::new(Classical),
+            Backend::Bsgs => Box::new(BabyStepGiantStep::default()),
+            Backend::Exact => Box::new(ExactOrder::new(config.order_multiple.clone())),
+            Backend::Quantum => Box::new(QuantumOrderFinding { noise: config.noise.clone(), simulator: config.simulator, ..QuantumOrderFinding::default() }),
+            Backend::SemiClassical => Box::new(SemiClassicalOrderFinding { noise: config.noise.clone(), simulator: config.simulator, ..SemiClassicalOrderFinding::default() }),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Backend::ALL.iter().copied().find(|b| b.name() == s).ok_or_else(|| {
+            let names: Vec<_> = Backend::ALL.iter().map(|b| b.name()).collect();
+            format!("Unknown backend {} (available: {})", s, names.join(", "))
+        })
+    }
+}
+
+// The naive walk x = x * a mod n, O(r)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Classical;
+
+impl PeriodFinder for Classical {
+    fn name(&self) -> &'static str {
+        "classical"
+    }
+
+    fn find_period(&self, a: &BigUint, n: &BigUint, ctx: &mut PeriodContext) -> Result<Option<BigUint>, FactorError> {
+        find_period_classical(a, n, ctx.limit, ctx.budget)
+    }
+}
+
+// How many multiplications `find_period_classical` does between budget checks
+const BUDGET_CHECK_INTERVAL: u32 = 1 << 12;
+
+// Classical period finding function (find smallest r > 0 such that a^r % n == 1)
+// This is the part that a quantum computer speeds up significantly.
+pub fn find_period_classical(a: &BigUint, n: &BigUint, limit: &BigUint, budget: &Budget) -> Result<Option<BigUint>, FactorError> {
+    if gcd(a, n) != BigUint::one() {
+        // 'a' shares a factor with 'n', this case is handled before calling find_period
+        return Ok(None);
+    }
+
+    let one = BigUint::one();
+    let mut r = BigUint::one();
+    let mut x = a % n;
+    let mut since_check = 0u32;
+
+    while x != one {
+        x = (&x * a) % n;
+        r += &one;
+        since_check += 1;
+        if since_check == BUDGET_CHECK_INTERVAL {
+            since_check = 0;
+            budget.check().map_err(|reason| FactorError::Interrupted(reason, Progress::default()))?;
+        }
+        if &r > limit {
+            // Period finding took too long, might be very large or 'a' was unlucky
+            return Err(FactorError::PeriodLimitExceeded { a: a.clone(), limit: limit.clone() });
+        }
+    }
+    Ok(Some(r))
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::events::SilentObserver;
+    use crate::seeded_rng;
+
+    // What `finder` says the order of a mod n is, searching up to `limit`
+    pub fn find_below(finder: &dyn PeriodFinder, a: u64, n: u64, limit: u64, seed: u64) -> Result<Option<BigUint>, FactorError> {
+        let (limit, budget) = (BigUint::from(limit), Budget::unlimited());
+        let mut ctx = PeriodContext { limit: &limit, budget: &budget, rng: &mut seeded_rng(seed), observer: &mut SilentObserver };
+        finder.find_period(&BigUint::from(a), &BigUint::from(n), &mut ctx)
+    }
+
+    pub fn find(finder: &dyn PeriodFinder, a: u64, n: u64, seed: u64) -> Result<Option<BigUint>, FactorError> {
+        find_below(finder, a, n, n * n, seed)
+    }
+
+    // `finder` against `find_period_classical` for every a from 2 to n - 1
+    pub fn assert_agrees_with_classical(finder: &dyn PeriodFinder, n: u64, seed: u64) {
+        for a in 2..n {
+            let expected = find_period_classical(&BigUint::from(a), &BigUint::from(n), &BigUint::from(n * n), &Budget::unlimited()).unwrap();
+            assert_eq!(find(finder, a, n, seed + a).unwrap(), expected, "{} backend, order of {} mod {}", finder.name(), a, n);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::{PeriodContext, PeriodFinder};
+use crate::budget::{Budget, Progress};
+use crate::error::FactorError;
+use crate::math::gcd;
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+use std::collections::HashMap;
+
+// Default cap on the baby-step table, about 100 MB at u64 keys
+pub const DEFAULT_MAX_TABLE: usize = 1 << 22;
+
+// How many steps run between budget checks
+const BUDGET_CHECK_INTERVAL: u64 = 1 << 12;
+
+// Baby-step giant-step order finding, O(√r) time and memory.
+//
+// The baby steps a^0 .. a^(m-1) go into a hash table, then the giant steps
+// a^m, a^2m, ... are looked up in it. The first hit a^(im) = a^j gives
+// r = im - j exactly. The order divides λ(n) < n, so m = ⌈√n⌉ always suffices.
+//
+// When ⌈√n⌉ baby steps would not fit in `max_table`, the table is capped and the
+// giant steps simply keep going: O(max_table) memory, O(r / max_table) time.
+#[derive(Debug, Clone, Copy)]
+pub struct BabyStepGiantStep {
+    pub max_table: usize,
+}
+
+impl Default for BabyStepGiantStep {
+    fn default() -> Self {
+        BabyStepGiantStep { max_table: DEFAULT_MAX_TABLE }
+    }
+}
+
+impl PeriodFinder for BabyStepGiantStep {
+    fn name(&self) -> &'static str {
+        "bsgs"
+    }
+
+    fn find_period(&self, a: &BigUint, n: &BigUint, ctx: &mut PeriodContext) -> Result<Option<BigUint>, FactorError> {
+        if gcd(a, n) != BigUint::one() {
+            return Ok(None);
+        }
+        // r <= λ(n) <= n - 1, no need to look further (or past the caller's limit)
+        let bound = (n - 1u32).min(ctx.limit.clone());
+        let table_size = (bound.sqrt() + 1u32).to_usize().unwrap_or(usize::MAX).min(self.max_table.max(1));
+
+        let found = match (a.to_u64(), n.to_u64()) {
+            (Some(a), Some(n)) => order_u64(a, n, &bound, table_size, ctx.budget)?.map(BigUint::from),
+            _ => order_big(a, n, &bound, table_size, ctx.budget)?,
+        };
+        match found {
+            Some(r) => Ok(Some(r)),
+            None => Err(FactorError::PeriodLimitExceeded { a: a.clone(), limit: bound }),
+        }
+    }
+}
+
+fn interrupted(budget: &Budget) -> Result<(), FactorError> {
+    budget.check().map_err(|reason| FactorError::Interrupted(reason, Progress::default()))
+}
+
+// Both halves for n < 2^64, with u128 products
+fn order_u64(a: u64, n: u64, bound: &BigUint, m: usize, budget: &Budget) -> Result<Option<u64>, FactorError> {
+    let bound = bound.to_u64().unwrap_or(u64::MAX);
+    let mul = |x: u64, y: u64| ((x as u128 * y as u128) % n as u128) as u64;
+
+    // Baby steps, a small order shows up right here
+    let mut table = HashMap::with_capacity(m);
+    let mut x = 1 % n;
+    for j in 0..m as u64 {
+        if j > 0 && x == 1 {
+            return Ok(Some(j));
+        }
+        table.insert(x, j);
+        x = mul(x, a);
+    }
+    // x is now a^m
+    let step = x;
+    let m = m as u64;
+
+    // Giant steps
+    let mut y = step;
+    let mut i = 1u64;
+    loop {
+        if let Some(&j) = table.get(&y) {
+            let r = i * m - j;
+            return Ok((r <= bound).then_some(r));
+        }
+        if i.saturating_mul(m).saturating_sub(m) > bound {
+            return Ok(None);
+        }
+        if i.is_multiple_of(BUDGET_CHECK_INTERVAL) {
+            interrupted(budget)?;
+        }
+        y = mul(y, step);
+        i += 1;
+    }
+}
+
+// Same walk on BigUint for larger n
+fn order_big(a: &BigUint, n: &BigUint, bound: &BigUint, m: usize, budget: &Budget) -> Result<Option<BigUint>, FactorError> {
+    let one = BigUint::one();
+
+    let mut table: HashMap<BigUint, u64> = HashMap::with_capacity(m);
+    let mut x = &one % n;
+    for j in 0..m as u64 {
+        if j > 0 && x == one {
+            return Ok(Some(BigUint::from(j)));
+        }
+        if j > 0 && j.is_multiple_of(BUDGET_CHECK_INTERVAL) {
+            interrupted(budget)?;
+        }
+        let next = (&x * a) % n;
+        table.insert(x, j);
+        x = next;
+    }
+    let step = x;
+    let m_big = BigUint::from(m);
+
+    let mut y = step.clone();
+    let mut i = 1u64;
+    loop {
+        let im = &m_big * i;
+        if let Some(&j) = table.get(&y) {
+            let r = im - j;
+            return Ok((&r <= bound).then_some(r));
+        }
+        if im - &m_big > *bound {
+            return Ok(None);
+        }
+        if i.is_multiple_of(BUDGET_CHECK_INTERVAL) {
+            interrupted(budget)?;
+        }
+        y = (&y * &step) % n;
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::period::testing::{assert_agrees_with_classical, find, find_below};
+
+    #[test]
+    fn agrees_with_classical() {
+        for n in [15, 21, 35, 91, 143, 1001] {
+            assert_agrees_with_classical(&BabyStepGiantStep::default(), n, 0);
+        }
+    }
+
+    #[test]
+    fn capped_table_keeps_stepping() {
+        // A table far below √n only costs time, the giant steps still land on r
+        for max_table in [0, 1, 2, 5] {
+            for n in [15, 143, 1001] {
+                assert_agrees_with_classical(&BabyStepGiantStep { max_table }, n, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn big_moduli_take_the_biguint_walk() {
+        // 2^64 ≡ -1 mod 2^64 + 1, so 2 has order 128
+        let n = (BigUint::one() << 64u32) + 1u32;
+        let (limit, budget) = (n.clone(), Budget::unlimited());
+        for max_table in [4, DEFAULT_MAX_TABLE] {
+            let mut ctx = PeriodContext { limit: &limit, budget: &budget, rng: &mut crate::seeded_rng(0), observer: &mut crate::events::SilentObserver };
+            assert_eq!(BabyStepGiantStep { max_table }.find_period(&BigUint::from(2u32), &n, &mut ctx).unwrap(), Some(BigUint::from(128u32)));
+        }
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        // The order of 7 mod 15 is 4
+        assert_eq!(find_below(&BabyStepGiantStep::default(), 7, 15, 4, 0).unwrap(), Some(BigUint::from(4u32)));
+        for max_table in [1, DEFAULT_MAX_TABLE] {
+            match find_below(&BabyStepGiantStep { max_table }, 7, 15, 3, 0) {
+                Err(FactorError::PeriodLimitExceeded { a, limit }) => assert_eq!((a, limit), (BigUint::from(7u32), BigUint::from(3u32))),
+                other => panic!("expected PeriodLimitExceeded, got {:?}", other),
+            }
+        }
+        assert_eq!(find(&BabyStepGiantStep::default(), 6, 15, 0).unwrap(), None);
+    }
+}
@@ -0,0 +1,161 @@
+use super::{BabyStepGiantStep, PeriodContext, PeriodFinder};
+use crate::config::Config;
+use crate::error::FactorError;
+use crate::events::SilentObserver;
+use crate::factorize::factorize;
+use crate::math::{gcd, modpow};
+use crate::seeded_rng;
+use num_bigint::BigUint;
+use num_traits::One;
+
+// Exact order finding from a known multiple M of the order, given factored
+// as M = ∏ p^e. φ(N) and λ(N) are such multiples for every base.
+//
+// Each prime is stripped from M for as long as a^(M/p) ≡ 1 still holds,
+// what is left is the order. That is a handful of `modpow` calls per prime
+// factor of M, so it is fast, deterministic, and good for checking the other backends.
+#[derive(Debug, Clone)]
+pub struct ExactOrder {
+    pub multiple: Vec<(BigUint, u32)>,
+}
+
+impl ExactOrder {
+    pub fn new(multiple: Vec<(BigUint, u32)>) -> Self {
+        ExactOrder { multiple }
+    }
+
+    // From the prime factorization of N itself, through λ(N)
+    pub fn from_prime_factors(n_factors: &[(BigUint, u32)]) -> Result<Self, FactorError> {
+        Ok(ExactOrder::new(carmichael_lambda_factors(n_factors)?))
+    }
+}
+
+impl PeriodFinder for ExactOrder {
+    fn name(&self) -> &'static str {
+        "exact"
+    }
+
+    fn find_period(&self, a: &BigUint, n: &BigUint, ctx: &mut PeriodContext) -> Result<Option<BigUint>, FactorError> {
+        if self.multiple.is_empty() {
+            return Err(FactorError::Backend("the exact backend needs a factored multiple of the order".to_string()));
+        }
+        if gcd(a, n) != BigUint::one() {
+            return Ok(None);
+        }
+        match order_from_multiple(a, n, &self.multiple) {
+            Some(r) if &r > ctx.limit => Err(FactorError::PeriodLimitExceeded { a: a.clone(), limit: ctx.limit.clone() }),
+            Some(r) => Ok(Some(r)),
+            None => Err(FactorError::Backend(format!("{} is not a multiple of the order of {} mod {}", product(&self.multiple), a, n))),
+        }
+    }
+}
+
+// The order of a mod n, given a factored multiple of it.
+// None when a^M is not 1, i.e. M is not a multiple of the order after all.
+pub fn order_from_multiple(a: &BigUint, n: &BigUint, multiple: &[(BigUint, u32)]) -> Option<BigUint> {
+    let one = BigUint::one() % n;
+    let mut order = product(multiple);
+    if modpow(a, &order, n) != one {
+        return None;
+    }
+    for (p, e) in multiple {
+        for _ in 0..*e {
+            let candidate = &order / p;
+            if modpow(a, &candidate, n) != one {
+                break;
+            }
+            order = candidate;
+        }
+    }
+    Some(order)
+}
+
+// λ(N) in factored form, from the factorization of N.
+// λ(p^k) = p^(k-1) (p - 1), except λ(2^k) = 2^(k-2) for k >= 3, and λ(N) is their lcm.
+// Every k must be at least 1.
+pub fn carmichael_lambda_factors(n_factors: &[(BigUint, u32)]) -> Result<Vec<(BigUint, u32)>, FactorError> {
+    let two = BigUint::from(2u32);
+    let mut lambda: Vec<(BigUint, u32)> = Vec::new();
+    let mut include = |p: BigUint, e: u32| {
+        if e == 0 {
+            return;
+        }
+        // lcm: keep the highest power of each prime
+        match lambda.iter_mut().find(|(q, _)| *q == p) {
+            Some((_, k)) => *k = (*k).max(e),
+            None => lambda.push((p, e)),
+        }
+    };
+
+    for (p, k) in n_factors {
+        if *k == 0 || *p < two {
+            return Err(FactorError::Backend(format!("{}^{} is not a prime power", p, k)));
+        }
+        if *p == two {
+            include(two.clone(), if *k >= 3 { k - 2 } else { k - 1 });
+            continue;
+        }
+        include(p.clone(), k - 1);
+        // p - 1 is even and smaller than p, factor it with the crate's own driver
+        let p_minus_1 = p - 1u32;
+        if p_minus_1 > BigUint::one() {
+            let config = Config::default();
+            let found = factorize(&p_minus_1, &config, &BabyStepGiantStep::default(), &mut seeded_rng(0), &mut SilentObserver)?;
+            for (q, e) in found.factors {
+                include(q, e);
+            }
+        }
+    }
+    lambda.sort();
+    Ok(lambda)
+}
+
+fn product(factors: &[(BigUint, u32)]) -> BigUint {
+    factors.iter().fold(BigUint::one(), |acc, (p, e)| acc * p.pow(*e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::period::testing::{assert_agrees_with_classical, find, find_below};
+
+    fn factors(pairs: &[(u32, u32)]) -> Vec<(BigUint, u32)> {
+        pairs.iter().map(|&(p, e)| (BigUint::from(p), e)).collect()
+    }
+
+    #[test]
+    fn agrees_with_classical() {
+        let cases: [(u64, &[(u32, u32)]); 6] = [(15, &[(3, 1), (5, 1)]), (21, &[(3, 1), (7, 1)]), (35, &[(5, 1), (7, 1)]), (45, &[(3, 2), (5, 1)]), (64, &[(2, 6)]), (1001, &[(7, 1), (11, 1), (13, 1)])];
+        for (n, n_factors) in cases {
+            let finder = ExactOrder::from_prime_factors(&factors(n_factors)).unwrap();
+            assert_agrees_with_classical(&finder, n, 0);
+        }
+    }
+
+    #[test]
+    fn carmichael_lambda() {
+        // λ(15) = lcm(2, 4), λ(64) = 2^4, λ(45) = lcm(6, 4)
+        assert_eq!(carmichael_lambda_factors(&factors(&[(3, 1), (5, 1)])).unwrap(), factors(&[(2, 2)]));
+        assert_eq!(carmichael_lambda_factors(&factors(&[(2, 6)])).unwrap(), factors(&[(2, 4)]));
+        assert_eq!(carmichael_lambda_factors(&factors(&[(3, 2), (5, 1)])).unwrap(), factors(&[(2, 2), (3, 1)]));
+    }
+
+    #[test]
+    fn refuses_bad_multiples() {
+        for bad in [&[(3, 0), (5, 1)][..], &[(1, 1)], &[(0, 2)]] {
+            assert!(matches!(ExactOrder::from_prime_factors(&factors(bad)), Err(FactorError::Backend(_))));
+        }
+        // 3 is not a multiple of the order 4 of 7 mod 15, and neither is 5^0
+        for multiple in [factors(&[(3, 1)]), factors(&[(5, 0)]), Vec::new()] {
+            assert!(matches!(find(&ExactOrder::new(multiple), 7, 15, 0), Err(FactorError::Backend(_))));
+        }
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let finder = ExactOrder::new(factors(&[(2, 2)]));
+        assert_eq!(find_below(&finder, 7, 15, 4, 0).unwrap(), Some(BigUint::from(4u32)));
+        assert!(matches!(find_below(&finder, 7, 15, 3, 0), Err(FactorError::PeriodLimitExceeded { .. })));
+        assert_eq!(find(&finder, 5, 15, 0).unwrap(), None);
+    }
+}
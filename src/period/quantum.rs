@@ -0,0 +1,158 @@
+use super::{PeriodContext, PeriodFinder};
+use crate::budget::Progress;
+use crate::circuit::{order_finding_circuit, Oracle, Qft};
+use crate::error::FactorError;
+use crate::math::gcd;
+use crate::events::Event;
+use crate::postprocess::{analyze_measurement, PeriodCombiner, DEFAULT_MAX_MULTIPLE};
+use crate::quantum::{NoiseModel, SimulatorKind};
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+use rand::RngCore;
+use std::cell::Cell;
+
+// Default largest state vector, 2^24 amplitudes is 256 MiB
+pub const DEFAULT_MAX_QUBITS: usize = 24;
+
+// Order finding on the simulated quantum computer: the full circuit of
+// `order_finding_circuit` (2m phase qubits, m work qubits) runs on a
+// state vector (or density matrix), and the measured phases go through continued fractions.
+// 3m qubits limit this to small n: 15, 21 and 35 need 12, 15 and 18.
+// Runs that only find a divisor of r are combined through their LCM.
+#[derive(Debug, Clone)]
+pub struct QuantumOrderFinding {
+    pub max_qubits: usize,
+    // Phase measurements to try per base before giving up on it
+    pub measurements: usize,
+    pub qft: Qft,
+    // How the modular multiplications are built
+    pub oracle: Oracle,
+    // Errors injected while the circuit runs
+    pub noise: NoiseModel,
+    // A density matrix gives the exact noisy distribution in one run, at twice the qubits' memory
+    pub simulator: SimulatorKind,
+}
+
+impl Default for QuantumOrderFinding {
+    fn default() -> Self {
+        QuantumOrderFinding { max_qubits: DEFAULT_MAX_QUBITS, measurements: 8, qft: Qft::exact(), oracle: Oracle::Permutation, noise: NoiseModel::ideal(), simulator: SimulatorKind::StateVector }
+    }
+}
+
+impl PeriodFinder for QuantumOrderFinding {
+    fn name(&self) -> &'static str {
+        "quantum"
+    }
+
+    fn find_period(&self, a: &BigUint, n: &BigUint, ctx: &mut PeriodContext) -> Result<Option<BigUint>, FactorError> {
+        if gcd(a, n) != BigUint::one() {
+            return Ok(None);
+        }
+        let (Some(a_small), Some(n_small)) = (a.to_u64(), n.to_u64()) else {
+            return Err(FactorError::Backend(format!("{} is too large to simulate", n)));
+        };
+        let qubits = 3 * n.bits() as usize + self.oracle.ancillas(n_small);
+        check_size(n, qubits, self.simulator, self.max_qubits)?;
+
+        let of = order_finding_circuit(a_small, n_small, &self.qft, self.oracle).map_err(|e| FactorError::Backend(e.to_string()))?;
+        let t = of.phase_bits();
+        let new_state = |qubits| self.simulator.create(qubits).map_err(|e| FactorError::Backend(e.to_string()));
+        if !self.noise.is_ideal() && self.simulator != SimulatorKind::DensityMatrix {
+            // Every noisy run of a pure state is a different trajectory, so each measurement reruns the circuit
+            let worst = Cell::new(0.0f64);
+            let found = measure_period(a, n, t, self.measurements, ctx, |rng| {
+                let mut state = new_state(of.circuit.num_qubits)?;
+                let bits = of.circuit.run_noisy(state.as_mut(), &self.noise, rng);
+                worst.set(worst.get().max(state.truncation_error()));
+                Ok(bits.iter().enumerate().fold(0u64, |y, (k, &b)| if b { y | 1 << k } else { y }))
+            });
+            ctx.observer.on_event(&Event::CircuitSimulated { a: a.clone(), simulator: self.simulator, qubits, truncation_error: worst.get() });
+            return found;
+        }
+
+        // Prepare the state once (noise included on a density matrix), the final measurement is sampled from it
+        let (body, measured) = of.circuit.split_final_measurements();
+        let mut state = new_state(body.num_qubits)?;
+        body.run_noisy(state.as_mut(), &self.noise, ctx.rng);
+        let truncation_error = state.truncation_error();
+        ctx.observer.on_event(&Event::CircuitSimulated { a: a.clone(), simulator: self.simulator, qubits, truncation_error });
+        let phase_qubits: Vec<usize> = measured.iter().map(|&(q, _)| q).collect();
+        let mut shots = state.sample_qubits(&phase_qubits, self.measurements, ctx.rng).into_iter();
+
+        measure_period(a, n, t, self.measurements, ctx, |rng| {
+            let y = shots.next().unwrap_or(0);
+            // Readout errors flip each recorded bit on its own
+            Ok((0..t).fold(0u64, |acc, k| if self.noise.read(y >> k & 1 == 1, rng) { acc | 1 << k } else { acc }))
+        })
+    }
+}
+
+// Refuse circuits whose dense state would not fit in `max_qubits` qubits' worth of amplitudes
+pub(crate) fn check_size(n: &BigUint, qubits: usize, simulator: SimulatorKind, max_qubits: usize) -> Result<(), FactorError> {
+    match simulator.memory_qubits(qubits) {
+        Some(size) if size > max_qubits => Err(FactorError::Backend(format!(
+            "{} needs {} qubits ({} on the {} simulator), the simulator allows {}",
+            n, qubits, size, simulator, max_qubits
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// Take up to `measurements` phase measurements from `measure` and post-process
+// each one, combining them when none gives r alone
+pub(crate) fn measure_period<F>(
+    a: &BigUint,
+    n: &BigUint,
+    t: usize,
+    measurements: usize,
+    ctx: &mut PeriodContext,
+    mut measure: F,
+) -> Result<Option<BigUint>, FactorError>
+where
+    F: FnMut(&mut dyn RngCore) -> Result<u64, FactorError>,
+{
+    let mut combiner = PeriodCombiner::new(a, n);
+    for _ in 0..measurements {
+        ctx.budget.check().map_err(|reason| FactorError::Interrupted(reason, Progress::default()))?;
+        let y = measure(&mut *ctx.rng)?;
+        let analysis = analyze_measurement(&BigUint::from(y), t, a, n, DEFAULT_MAX_MULTIPLE);
+        ctx.observer.on_event(&Event::PhaseMeasured { a: a.clone(), analysis: analysis.clone() });
+        let found = analysis.result.clone().ok().or_else(|| combiner.add(&analysis));
+        if let Some(r) = found {
+            if &r > ctx.limit {
+                return Err(FactorError::PeriodLimitExceeded { a: a.clone(), limit: ctx.limit.clone() });
+            }
+            return Ok(Some(r));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::period::testing::{assert_agrees_with_classical, find};
+
+    #[test]
+    fn finds_the_order() {
+        let finder = QuantumOrderFinding::default();
+        assert_eq!(find(&finder, 7, 15, 1).unwrap(), Some(BigUint::from(4u32)));
+        assert_eq!(find(&finder, 2, 21, 1).unwrap(), Some(BigUint::from(6u32)));
+        assert_eq!(find(&finder, 6, 21, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn agrees_with_classical() {
+        let finder = QuantumOrderFinding::default();
+        for n in [15, 21] {
+            assert_agrees_with_classical(&finder, n, 100);
+        }
+    }
+
+    #[test]
+    fn refuses_circuits_above_the_limit() {
+        let finder = QuantumOrderFinding { max_qubits: 14, ..QuantumOrderFinding::default() };
+        assert!(find(&finder, 7, 15, 1).is_ok());
+        assert!(matches!(find(&finder, 2, 21, 1), Err(FactorError::Backend(_))));
+    }
+}
@@ -0,0 +1,94 @@
+use super::quantum::{check_size, measure_period};
+use super::{PeriodContext, PeriodFinder};
+use crate::circuit::{semiclassical_order_finding_circuit, Oracle, Qft};
+use crate::error::FactorError;
+use crate::events::Event;
+use crate::math::gcd;
+use crate::quantum::{NoiseModel, SimulatorKind};
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+use std::cell::Cell;
+
+// Default largest state vector for the semi-classical circuit (m + 1 qubits)
+pub const DEFAULT_MAX_QUBITS: usize = 24;
+
+// Order finding with the semi-classical circuit of `semiclassical_order_finding_circuit`:
+// one recycled control qubit, mid-circuit measurements and classically controlled
+// phase corrections. m + 1 qubits instead of 3m, so the same memory reaches much
+// larger n than the `quantum` backend. The state depends on the mid-circuit
+// outcomes, so every measurement of y is a fresh run of the whole circuit.
+#[derive(Debug, Clone)]
+pub struct SemiClassicalOrderFinding {
+    pub max_qubits: usize,
+    // Runs (each giving one y) to try per base before giving up on it
+    pub measurements: usize,
+    // The cutoff drops far phase corrections; the Beauregard oracle also builds its adders from it
+    pub qft: Qft,
+    // How the modular multiplications are built
+    pub oracle: Oracle,
+    // Errors injected while the circuit runs
+    pub noise: NoiseModel,
+    // A density matrix applies the noise exactly, only the mid-circuit outcomes stay random
+    pub simulator: SimulatorKind,
+}
+
+impl Default for SemiClassicalOrderFinding {
+    fn default() -> Self {
+        SemiClassicalOrderFinding { max_qubits: DEFAULT_MAX_QUBITS, measurements: 8, qft: Qft::exact(), oracle: Oracle::Permutation, noise: NoiseModel::ideal(), simulator: SimulatorKind::StateVector }
+    }
+}
+
+impl PeriodFinder for SemiClassicalOrderFinding {
+    fn name(&self) -> &'static str {
+        "semiclassical"
+    }
+
+    fn find_period(&self, a: &BigUint, n: &BigUint, ctx: &mut PeriodContext) -> Result<Option<BigUint>, FactorError> {
+        if gcd(a, n) != BigUint::one() {
+            return Ok(None);
+        }
+        let (Some(a_small), Some(n_small)) = (a.to_u64(), n.to_u64()) else {
+            return Err(FactorError::Backend(format!("{} is too large to simulate", n)));
+        };
+        let qubits = n.bits() as usize + 1 + self.oracle.ancillas(n_small);
+        check_size(n, qubits, self.simulator, self.max_qubits)?;
+
+        let of = semiclassical_order_finding_circuit(a_small, n_small, &self.qft, self.oracle).map_err(|e| FactorError::Backend(e.to_string()))?;
+        let worst = Cell::new(0.0f64);
+        let found = measure_period(a, n, of.phase_bits(), self.measurements, ctx, |rng| {
+            let mut state = self.simulator.create(of.circuit.num_qubits).map_err(|e| FactorError::Backend(e.to_string()))?;
+            let bits = of.circuit.run_noisy(state.as_mut(), &self.noise, rng);
+            worst.set(worst.get().max(state.truncation_error()));
+            Ok(bits.iter().enumerate().fold(0u64, |y, (k, &b)| if b { y | 1 << k } else { y }))
+        });
+        ctx.observer.on_event(&Event::CircuitSimulated { a: a.clone(), simulator: self.simulator, qubits, truncation_error: worst.get() });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::period::testing::{assert_agrees_with_classical, find};
+
+    #[test]
+    fn finds_the_order() {
+        let finder = SemiClassicalOrderFinding::default();
+        assert_eq!(find(&finder, 7, 15, 1).unwrap(), Some(BigUint::from(4u32)));
+        assert_eq!(find(&finder, 2, 21, 1).unwrap(), Some(BigUint::from(6u32)));
+    }
+
+    #[test]
+    fn agrees_with_classical() {
+        let finder = SemiClassicalOrderFinding::default();
+        for n in [15, 21, 33] {
+            assert_agrees_with_classical(&finder, n, 200);
+        }
+    }
+
+    #[test]
+    fn beauregard_oracle_finds_the_order() {
+        let finder = SemiClassicalOrderFinding { oracle: Oracle::Beauregard, ..SemiClassicalOrderFinding::default() };
+        assert_eq!(find(&finder, 7, 15, 1).unwrap(), Some(BigUint::from(4u32)));
+    }
+}
@@ -0,0 +1,352 @@
+// Classical post-processing of the phase measured by order finding.
+//
+// A run measures y out of Q = 2^t with y / Q ≈ s / r for a random s. The
+// continued fraction of y / Q finds s / r in lowest terms, so its denominator
+// is r itself only when gcd(s, r) = 1; otherwise it is a divisor of r, which
+// small multiples or the LCM over several runs recover.
+
+use crate::math::modpow;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Denominators are also tried times 2, 3, ... up to this
+pub const DEFAULT_MAX_MULTIPLE: u64 = 8;
+
+// Why a measurement gave no period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MeasurementFailure {
+    // y = 0 is s = 0, it says nothing about r
+    ZeroPhase,
+    // No convergent has a denominator between 2 and n - 1
+    NoConvergent,
+    // y / Q is within 1/2Q of c / d, so d divides r, but s shares a factor
+    // with r that is too large for the small multiples of d to make up
+    SharedFactor { denominator: BigUint },
+    // y was not close to any s / r, an off-peak outcome
+    NotAPeriod,
+}
+
+impl fmt::Display for MeasurementFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeasurementFailure::ZeroPhase => write!(f, "y = 0 carries no information"),
+            MeasurementFailure::NoConvergent => write!(f, "no convergent with a denominator below N"),
+            MeasurementFailure::SharedFactor { denominator } => {
+                write!(f, "the numerator shares a factor with r, only the divisor {} of r was found", denominator)
+            }
+            MeasurementFailure::NotAPeriod => write!(f, "no convergent or small multiple is a period"),
+        }
+    }
+}
+
+// Everything learned from one measurement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseAnalysis {
+    pub y: BigUint,
+    // Bits of the phase register, Q = 2^t
+    pub t: usize,
+    // Convergents (c, d) of y / Q with 0 < d < n
+    pub convergents: Vec<(BigUint, BigUint)>,
+    // The denominator known to divide r (from a convergent within 1/2Q of y / Q)
+    pub divisor: Option<BigUint>,
+    pub result: Result<BigUint, MeasurementFailure>,
+}
+
+// Continued fraction terms of y / q
+pub fn continued_fraction(y: &BigUint, q: &BigUint) -> Vec<BigUint> {
+    let (mut num, mut den) = (y.clone(), q.clone());
+    let mut terms = Vec::new();
+    while !den.is_zero() {
+        let (quotient, rem) = num.div_rem(&den);
+        terms.push(quotient);
+        num = den;
+        den = rem;
+    }
+    terms
+}
+
+// The convergents p/q of y / q, as (p, q) pairs in order
+pub fn convergents(y: &BigUint, q: &BigUint) -> Vec<(BigUint, BigUint)> {
+    let (mut p_prev, mut p) = (BigUint::zero(), BigUint::one());
+    let (mut q_prev, mut q_cur) = (BigUint::one(), BigUint::zero());
+    let mut out = Vec::new();
+    for term in continued_fraction(y, q) {
+        let p_next = &term * &p + &p_prev;
+        let q_next = &term * &q_cur + &q_prev;
+        p_prev = std::mem::replace(&mut p, p_next);
+        q_prev = std::mem::replace(&mut q_cur, q_next);
+        out.push((p.clone(), q_cur.clone()));
+    }
+    out
+}
+
+// Analyse one measurement y of a t-bit phase register for the order of a mod n.
+// Each convergent denominator d < n is tested, and so are d * k for k up to `max_multiple`;
+// the smallest that satisfies a^r ≡ 1 is the period.
+pub fn analyze_measurement(y: &BigUint, t: usize, a: &BigUint, n: &BigUint, max_multiple: u64) -> PhaseAnalysis {
+    let q = BigUint::one() << t;
+    let one = BigUint::one();
+    let convergents: Vec<_> = convergents(y, &q).into_iter().filter(|(_, d)| !d.is_zero() && d < n).collect();
+    let mut analysis = PhaseAnalysis { y: y.clone(), t, convergents, divisor: None, result: Err(MeasurementFailure::NotAPeriod) };
+
+    if y.is_zero() {
+        analysis.result = Err(MeasurementFailure::ZeroPhase);
+        return analysis;
+    }
+    // |y/Q - c/d| < 1/2Q  <=>  |y d - c Q| * 2 < d
+    analysis.divisor = analysis
+        .convergents
+        .iter()
+        .rev()
+        .find(|(c, d)| {
+            let (yd, cq) = (y * d, c * &q);
+            let diff = if yd > cq { yd - cq } else { cq - yd };
+            diff * 2u32 < *d
+        })
+        .map(|(_, d)| d.clone());
+
+    let mut candidates: Vec<BigUint> = Vec::new();
+    for k in 1..=max_multiple.max(1) {
+        candidates.extend(analysis.convergents.iter().map(|(_, d)| d * k).filter(|r| r > &one));
+    }
+    candidates.sort();
+    candidates.dedup();
+    analysis.result = match candidates.into_iter().find(|r| modpow(a, r, n) == one) {
+        Some(r) => Ok(reduce_period(a, n, r, max_multiple)),
+        None if analysis.convergents.iter().all(|(_, d)| d <= &one) => Err(MeasurementFailure::NoConvergent),
+        None => match &analysis.divisor {
+            Some(d) if d > &one => Err(MeasurementFailure::SharedFactor { denominator: d.clone() }),
+            _ => Err(MeasurementFailure::NotAPeriod),
+        },
+    };
+    analysis
+}
+
+// A multiple r of the order with small cofactors, divided down by them
+// for as long as a^r ≡ 1 still holds
+fn reduce_period(a: &BigUint, n: &BigUint, mut r: BigUint, max_multiple: u64) -> BigUint {
+    for k in 2..=max_multiple {
+        while (&r % k).is_zero() && modpow(a, &(&r / k), n).is_one() {
+            r /= k;
+        }
+    }
+    r
+}
+
+// Period from a measurement y of a t-bit phase register, with the default small multiples
+pub fn period_from_measurement(y: &BigUint, t: usize, a: &BigUint, n: &BigUint) -> Option<BigUint> {
+    analyze_measurement(y, t, a, n, DEFAULT_MAX_MULTIPLE).result.ok()
+}
+
+// Every outcome of a histogram of phase measurements, post-processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramAnalysis {
+    pub shots: usize,
+    // Each outcome's analysis and number of shots, most frequent first
+    pub outcomes: Vec<(PhaseAnalysis, usize)>,
+    // The period the most shots give on their own (the smallest on a tie), or failing
+    // that the one the divisors of the outcomes combine to
+    pub period: Option<BigUint>,
+    // Shots whose outcome alone gives `period`
+    pub period_shots: usize,
+}
+
+// Analyse every outcome y of `histogram` (y -> shots) as a t-bit phase for the order of a mod n
+pub fn analyze_histogram(histogram: &BTreeMap<u64, usize>, t: usize, a: &BigUint, n: &BigUint, max_multiple: u64) -> HistogramAnalysis {
+    let mut outcomes: Vec<(PhaseAnalysis, usize)> =
+        histogram.iter().map(|(&y, &shots)| (analyze_measurement(&BigUint::from(y), t, a, n, max_multiple), shots)).collect();
+    outcomes.sort_by_key(|&(_, shots)| std::cmp::Reverse(shots));
+
+    let mut votes: BTreeMap<&BigUint, usize> = BTreeMap::new();
+    for (analysis, shots) in &outcomes {
+        if let Ok(r) = &analysis.result {
+            *votes.entry(r).or_insert(0) += shots;
+        }
+    }
+    let (period, period_shots) = match votes.iter().max_by(|x, y| x.1.cmp(y.1).then(y.0.cmp(x.0))) {
+        Some((r, &shots)) => (Some((*r).clone()), shots),
+        None => {
+            let mut combiner = PeriodCombiner::new(a, n);
+            (outcomes.iter().find_map(|(analysis, _)| combiner.add(analysis)), 0)
+        }
+    };
+    HistogramAnalysis { shots: histogram.values().sum(), outcomes, period, period_shots }
+}
+
+impl HistogramAnalysis {
+    // Share of all the shots that `shots` is
+    pub fn fraction(&self, shots: usize) -> f64 {
+        if self.shots == 0 { 0.0 } else { shots as f64 / self.shots as f64 }
+    }
+}
+
+// One line per outcome, most frequent first, then the period they give
+impl fmt::Display for HistogramAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (outcome, shots) in &self.outcomes {
+            let result = match &outcome.result {
+                Ok(r) => format!("r = {}", r),
+                Err(why) => why.to_string(),
+            };
+            let percent = 100.0 * self.fraction(*shots);
+            writeln!(f, "  y = {:<6} {:0t$b}  {:>8} shots ({:5.1}%)  {}", outcome.y, outcome.y, shots, percent, result, t = outcome.t)?;
+        }
+        match &self.period {
+            Some(r) => writeln!(f, "Period r = {} ({:.1}% of the shots give it on their own)", r, 100.0 * self.fraction(self.period_shots)),
+            None => writeln!(f, "No outcome gives the period"),
+        }
+    }
+}
+
+// Combines runs that each found only a divisor of r: r is a multiple of
+// every such divisor, and their LCM reaches r after a few runs.
+#[derive(Debug, Clone)]
+pub struct PeriodCombiner {
+    a: BigUint,
+    n: BigUint,
+    lcm: BigUint,
+    pub runs: usize,
+}
+
+impl PeriodCombiner {
+    pub fn new(a: &BigUint, n: &BigUint) -> Self {
+        PeriodCombiner { a: a.clone(), n: n.clone(), lcm: BigUint::one(), runs: 0 }
+    }
+
+    // LCM of the divisors seen so far
+    pub fn lcm(&self) -> &BigUint {
+        &self.lcm
+    }
+
+    // Add a run. Some(r) once the LCM is a period.
+    pub fn add(&mut self, analysis: &PhaseAnalysis) -> Option<BigUint> {
+        self.runs += 1;
+        if let Ok(r) = &analysis.result {
+            self.lcm = self.lcm.lcm(r);
+        } else if let Some(d) = &analysis.divisor {
+            self.lcm = self.lcm.lcm(d);
+        }
+        if self.lcm > BigUint::one() && self.lcm < self.n && modpow(&self.a, &self.lcm, &self.n) == BigUint::one() {
+            Some(reduce_period(&self.a, &self.n, self.lcm.clone(), DEFAULT_MAX_MULTIPLE))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn order(a: u64, n: u64) -> u64 {
+        (1..n).find(|&r| modpow(&big(a), &big(r), &big(n)).is_one()).unwrap()
+    }
+
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+
+    // The outcome closest to the peak s / r of a t-bit register
+    fn peak(s: u64, r: u64, t: usize) -> u64 {
+        ((s << t) as f64 / r as f64).round() as u64
+    }
+
+    #[test]
+    fn continued_fraction_and_convergents() {
+        // 415 / 93 = [4; 2, 6, 7]
+        assert_eq!(continued_fraction(&big(415), &big(93)), [4u64, 2, 6, 7].map(big));
+        let expected = [(4u64, 1u64), (9, 2), (58, 13), (415, 93)].map(|(p, q)| (big(p), big(q)));
+        assert_eq!(convergents(&big(415), &big(93)), expected);
+    }
+
+    #[test]
+    fn convergents_recover_r() {
+        // Nielsen & Chuang's example: 1536 / 2048 = 3 / 4 for 7 mod 15
+        assert_eq!(analyze_measurement(&big(1536), 11, &big(7), &big(15), 1).result, Ok(big(4)));
+        // Every peak s / r with gcd(s, r) = 1 gives r from the convergents alone
+        for (a, n) in [(7u64, 15u64), (2, 21), (2, 323), (3, 391), (5, 1007)] {
+            let (r, t) = (order(a, n), 2 * (64 - n.leading_zeros()) as usize);
+            for s in (1..r).filter(|&s| gcd(s, r) == 1) {
+                let y = big(peak(s, r, t));
+                let analysis = analyze_measurement(&y, t, &big(a), &big(n), 1);
+                assert_eq!(analysis.result, Ok(big(r)), "y = {} (s = {}) for {} mod {}", y, s, a, n);
+                assert_eq!(analysis.divisor, Some(big(r)));
+            }
+        }
+    }
+
+    #[test]
+    fn small_multiples_make_up_a_shared_factor() {
+        // 2 mod 21 has r = 6; s = 2 lands on 1 / 3, whose denominator only divides r
+        let (a, n, t) = (big(2), big(21), 10);
+        let y = big(peak(2, 6, t));
+        let alone = analyze_measurement(&y, t, &a, &n, 1);
+        assert_eq!(alone.result, Err(MeasurementFailure::SharedFactor { denominator: big(3) }));
+        assert_eq!(alone.divisor, Some(big(3)));
+        assert_eq!(analyze_measurement(&y, t, &a, &n, DEFAULT_MAX_MULTIPLE).result, Ok(big(6)));
+        assert_eq!(period_from_measurement(&y, t, &a, &n), Some(big(6)));
+    }
+
+    #[test]
+    fn reduces_a_multiple_of_r() {
+        // 7 mod 15 has r = 4; 1 / 2 gives the candidates 2, 4, 6, 8, the first period is 4
+        assert_eq!(analyze_measurement(&big(128), 8, &big(7), &big(15), DEFAULT_MAX_MULTIPLE).result, Ok(big(4)));
+        // 4 mod 15 has r = 2; 1 / 4 gives 4, which is divided down to 2
+        assert_eq!(analyze_measurement(&big(64), 8, &big(4), &big(15), DEFAULT_MAX_MULTIPLE).result, Ok(big(2)));
+    }
+
+    #[test]
+    fn failure_reasons() {
+        let (a, n) = (big(2), big(21));
+        assert_eq!(analyze_measurement(&big(0), 10, &a, &n, DEFAULT_MAX_MULTIPLE).result, Err(MeasurementFailure::ZeroPhase));
+        // 1023 / 1024 = [0; 1, 1023]: only the denominators 1 and 1024 (the multiples
+        // of 1 would try every small r)
+        assert_eq!(analyze_measurement(&big(1023), 10, &a, &n, 1).result, Err(MeasurementFailure::NoConvergent));
+        // Between the peaks 1 / 6 and 2 / 6, its convergents 1 / 3 and 1 / 4 are neither
+        // close enough nor periods
+        let off_peak = analyze_measurement(&big(257), 10, &a, &n, 1);
+        assert_eq!(off_peak.result, Err(MeasurementFailure::NotAPeriod));
+        assert_eq!(off_peak.divisor, None);
+    }
+
+    #[test]
+    fn combiner_takes_the_lcm_of_divisors() {
+        // 2 mod 21: s = 2 gives the divisor 3 and s = 3 the divisor 2, together r = 6
+        let (a, n, t) = (big(2), big(21), 10);
+        let mut combiner = PeriodCombiner::new(&a, &n);
+        assert_eq!(combiner.add(&analyze_measurement(&big(peak(2, 6, t)), t, &a, &n, 1)), None);
+        assert_eq!(combiner.lcm(), &big(3));
+        assert_eq!(combiner.add(&analyze_measurement(&big(0), t, &a, &n, 1)), None);
+        assert_eq!(combiner.add(&analyze_measurement(&big(peak(3, 6, t)), t, &a, &n, 1)), Some(big(6)));
+        assert_eq!(combiner.lcm(), &big(6));
+        assert_eq!(combiner.runs, 3);
+    }
+
+    #[test]
+    fn histogram_picks_the_most_common_period() {
+        // 7 mod 15, t = 8: the peaks 64, 128 and 192 all give 4
+        let histogram: BTreeMap<u64, usize> = [(0, 10), (64, 4), (128, 5), (192, 3)].into();
+        let analysis = analyze_histogram(&histogram, 8, &big(7), &big(15), DEFAULT_MAX_MULTIPLE);
+        assert_eq!(analysis.shots, 22);
+        assert_eq!(analysis.period, Some(big(4)));
+        assert_eq!(analysis.period_shots, 12);
+        assert_eq!(analysis.outcomes[0].1, 10);
+        assert_eq!(analysis.outcomes[0].0.result, Err(MeasurementFailure::ZeroPhase));
+        assert!((analysis.fraction(analysis.period_shots) - 12.0 / 22.0).abs() < 1e-12);
+
+        let lines: Vec<String> = analysis.to_string().lines().map(str::to_string).collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "  y = 0      00000000        10 shots ( 45.5%)  y = 0 carries no information");
+        assert_eq!(lines[1], "  y = 128    10000000         5 shots ( 22.7%)  r = 4");
+        assert_eq!(lines[4], "Period r = 4 (54.5% of the shots give it on their own)");
+        let empty = analyze_histogram(&BTreeMap::new(), 8, &big(7), &big(15), DEFAULT_MAX_MULTIPLE);
+        assert_eq!((empty.fraction(0), empty.to_string()), (0.0, "No outcome gives the period\n".to_string()));
+    }
+}
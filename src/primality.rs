@@ -0,0 +1,265 @@
+use crate::math::modpow;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+// Primes used for quick trial division before the probabilistic tests
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+// Enough Miller-Rabin bases to be deterministic for n < 3.3 * 10^24
+pub const DETERMINISTIC_BASES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+// Primality test used everywhere in the crate (Baillie-PSW).
+// No composite passing it is known, and it is exact below 2^64.
+pub fn is_prime(n: &BigUint) -> bool {
+    baillie_psw(n)
+}
+
+// Strong probable prime test to a single base (one Miller-Rabin round).
+// n must be odd and greater than 2.
+pub fn is_strong_probable_prime(n: &BigUint, base: &BigUint) -> bool {
+    let one = BigUint::one();
+    let n_minus_1 = n - &one;
+    let s = n_minus_1.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_1 >> s;
+
+    let mut x = modpow(&(base % n), &d, n);
+    if x.is_zero() || x == one || x == n_minus_1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = (&x * &x) % n;
+        if x == n_minus_1 {
+            return true;
+        }
+    }
+    false
+}
+
+// Miller-Rabin with the given bases. Composite for sure when it returns false.
+pub fn miller_rabin(n: &BigUint, bases: &[u32]) -> bool {
+    if let Some(answer) = small_prime_check(n) {
+        return answer;
+    }
+    bases.iter().all(|&b| is_strong_probable_prime(n, &BigUint::from(b)))
+}
+
+// Baillie-PSW: a base 2 strong probable prime test followed by a strong Lucas test
+pub fn baillie_psw(n: &BigUint) -> bool {
+    if let Some(answer) = small_prime_check(n) {
+        return answer;
+    }
+    is_strong_probable_prime(n, &BigUint::from(2u32)) && is_strong_lucas_probable_prime(n)
+}
+
+// Strong Lucas probable prime test with Selfridge's parameters
+// (D the first of 5, -7, 9, -11, ... with (D/n) = -1, P = 1, Q = (1 - D) / 4).
+// n must be odd and greater than 2.
+pub fn is_strong_lucas_probable_prime(n: &BigUint) -> bool {
+    // For a perfect square (D/n) is never -1, the search below would not end
+    let root = n.sqrt();
+    if &root * &root == *n {
+        return false;
+    }
+
+    // Find D, tracked as a magnitude and a sign
+    let mut d_abs = 5u64;
+    let mut negative = false;
+    loop {
+        let d_mod_n = signed_mod(d_abs, negative, n);
+        match jacobi(&d_mod_n, n) {
+            -1 => break,
+            0 if BigUint::from(d_abs) != *n => return false,
+            _ => {}
+        }
+        d_abs += 2;
+        negative = !negative;
+    }
+
+    // Q = (1 - D) / 4
+    let (q_abs, q_negative) = if negative { ((1 + d_abs) / 4, false) } else { ((d_abs - 1) / 4, true) };
+    let d = signed_mod(d_abs, negative, n);
+    let q = signed_mod(q_abs, q_negative, n);
+
+    // n + 1 = k * 2^s with k odd
+    let n_plus_1 = n + 1u32;
+    let s = n_plus_1.trailing_zeros().unwrap_or(0);
+    let k = &n_plus_1 >> s;
+
+    // U_k, V_k and Q^k mod n by walking the bits of k (P = 1)
+    let mut u = BigUint::one();
+    let mut v = BigUint::one();
+    let mut qk = q.clone();
+    for i in (0..k.bits() - 1).rev() {
+        // Double: U_2m = U_m V_m, V_2m = V_m^2 - 2 Q^m
+        u = (&u * &v) % n;
+        v = sub_mod(&((&v * &v) % n), &((&qk << 1u32) % n), n);
+        qk = (&qk * &qk) % n;
+        if k.bit(i) {
+            // Step: U_m+1 = (U_m + V_m) / 2, V_m+1 = (D U_m + V_m) / 2
+            let new_u = half_mod(&((&u + &v) % n), n);
+            let new_v = half_mod(&((&d * &u + &v) % n), n);
+            u = new_u;
+            v = new_v;
+            qk = (&qk * &q) % n;
+        }
+    }
+
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..s {
+        v = sub_mod(&((&v * &v) % n), &((&qk << 1u32) % n), n);
+        if v.is_zero() {
+            return true;
+        }
+        qk = (&qk * &qk) % n;
+    }
+    false
+}
+
+// Jacobi symbol (a/n) for odd n
+pub fn jacobi(a: &BigUint, n: &BigUint) -> i32 {
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+    while !a.is_zero() {
+        let twos = a.trailing_zeros().unwrap_or(0);
+        a >>= twos;
+        let n_mod_8 = (&n % 8u32).to_u32().unwrap_or(0);
+        if twos % 2 == 1 && (n_mod_8 == 3 || n_mod_8 == 5) {
+            result = -result;
+        }
+        if (&a % 4u32) == BigUint::from(3u32) && n_mod_8 % 4 == 3 {
+            result = -result;
+        }
+        std::mem::swap(&mut a, &mut n);
+        a %= &n;
+    }
+    if n.is_one() { result } else { 0 }
+}
+
+// Answers for n < 2, even n and n with a small prime factor, None otherwise
+fn small_prime_check(n: &BigUint) -> Option<bool> {
+    if n <= &BigUint::one() {
+        return Some(false);
+    }
+    for &p in SMALL_PRIMES.iter() {
+        if *n == BigUint::from(p) {
+            return Some(true);
+        }
+        if (n % p).is_zero() {
+            return Some(false);
+        }
+    }
+    // No factor below 100 and n < 100^2 means n is prime
+    if n < &BigUint::from(10_000u32) {
+        return Some(true);
+    }
+    None
+}
+
+// value (negated when `negative`) reduced into 0..n
+fn signed_mod(value: u64, negative: bool, n: &BigUint) -> BigUint {
+    let r = BigUint::from(value) % n;
+    if negative && !r.is_zero() { n - r } else { r }
+}
+
+fn sub_mod(a: &BigUint, b: &BigUint, n: &BigUint) -> BigUint {
+    if a >= b { a - b } else { n - (b - a) }
+}
+
+// x / 2 mod n for odd n
+fn half_mod(x: &BigUint, n: &BigUint) -> BigUint {
+    if x.is_even() { x >> 1u32 } else { (x + n) >> 1u32 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn mersenne(p: u32) -> BigUint {
+        (BigUint::one() << p) - 1u32
+    }
+
+    #[test]
+    fn agrees_with_a_sieve() {
+        let limit = 3000;
+        let mut sieve = vec![true; limit];
+        sieve[0] = false;
+        sieve[1] = false;
+        for i in 2..limit {
+            if sieve[i] {
+                (2 * i..limit).step_by(i).for_each(|j| sieve[j] = false);
+            }
+        }
+        for (n, &prime) in sieve.iter().enumerate() {
+            let n = big(n as u64);
+            assert_eq!(is_prime(&n), prime, "is_prime({})", n);
+            assert_eq!(miller_rabin(&n, &DETERMINISTIC_BASES), prime, "miller_rabin({})", n);
+        }
+    }
+
+    #[test]
+    fn carmichael_numbers_are_composite() {
+        for n in [561u64, 1105, 1729, 41041, 825265] {
+            let n = big(n);
+            // They fool Fermat's test to every coprime base
+            assert_eq!(modpow(&big(2), &(&n - 1u32), &n), BigUint::one());
+            assert!(!is_prime(&n), "{}", n);
+            assert!(!miller_rabin(&n, &DETERMINISTIC_BASES), "{}", n);
+        }
+    }
+
+    #[test]
+    fn strong_pseudoprimes_to_base_2_are_composite() {
+        for n in [2047u64, 3277, 4033, 4681, 8321, 3215031751] {
+            let n = big(n);
+            assert!(is_strong_probable_prime(&n, &big(2)), "{} is a strong pseudoprime to base 2", n);
+            assert!(!is_prime(&n), "{}", n);
+            assert!(!miller_rabin(&n, &DETERMINISTIC_BASES), "{}", n);
+        }
+        // 3215031751 = 151 * 751 * 28351 also passes bases 3, 5 and 7
+        assert!(miller_rabin(&big(3215031751), &[2, 3, 5, 7]));
+    }
+
+    #[test]
+    fn strong_lucas_pseudoprimes_are_composite() {
+        for n in [5459u64, 5777, 10877, 16109, 18971] {
+            let n = big(n);
+            assert!(is_strong_lucas_probable_prime(&n), "{} is a strong Lucas pseudoprime", n);
+            assert!(!is_prime(&n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn large_primes_and_composites() {
+        for p in [61, 89, 107, 127, 521] {
+            assert!(is_prime(&mersenne(p)), "2^{} - 1 is prime", p);
+        }
+        assert!(is_prime(&big(18446744073709551557)), "largest prime below 2^64");
+        // 2^67 - 1 = 193707721 * 761838257287 (Cole)
+        assert!(!is_prime(&mersenne(67)));
+        assert!(!is_prime(&(mersenne(89) * mersenne(127))));
+        assert!(!is_prime(&(mersenne(61) * mersenne(61))));
+    }
+
+    #[test]
+    fn jacobi_matches_euler_on_primes() {
+        for p in [3u64, 5, 7, 11, 13, 101] {
+            for a in 0..p {
+                let euler = modpow(&big(a), &big((p - 1) / 2), &big(p));
+                let expected = if a == 0 { 0 } else if euler == BigUint::one() { 1 } else { -1 };
+                assert_eq!(jacobi(&big(a), &big(p)), expected, "({} / {})", a, p);
+            }
+        }
+        // (2 / 15) = (2 / 3)(2 / 5) = 1 although 2 is not a square mod 15
+        assert_eq!(jacobi(&big(2), &big(15)), 1);
+    }
+}
@@ -0,0 +1,216 @@
+// Quantum circuit simulation: the quantum half of Shor's algorithm.
+//
+// Gates are single-qubit operations with any number of controls (`Gate`),
+// and every simulator implements `Simulator`, so circuits do not care
+// which one they run on.
+
+use rand::{Rng, RngCore};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+mod density;
+mod gate;
+mod linalg;
+mod mps;
+mod noise;
+mod state;
+
+pub use density::DensityMatrix;
+pub use gate::{Gate, GateKind, Matrix2};
+pub use mps::{Mps, DEFAULT_MAX_BOND};
+pub use noise::{Channel, NoiseModel, ORACLE_NOISE};
+pub use state::StateVector;
+
+// Hard limit on simulated qubits, 2^34 amplitudes is 256 GiB
+pub const MAX_QUBITS: usize = 34;
+
+// A quantum state that gates can be applied to and qubits measured on
+pub trait Simulator {
+    fn num_qubits(&self) -> usize;
+
+    fn apply(&mut self, gate: &Gate);
+
+    // Probability that measuring `qubit` now gives 1
+    fn probability_one(&self, qubit: usize) -> f64;
+
+    // Probabilities of the values of `qubits` (bit k = qubits[k]), summed over all other qubits
+    fn marginal_probabilities(&self, qubits: &[usize]) -> Vec<f64>;
+
+    // `shots` values of `qubits` drawn from the current state, which is left as it is
+    fn sample_qubits(&self, qubits: &[usize], shots: usize, rng: &mut dyn RngCore) -> Vec<u64> {
+        let distribution = self.marginal_probabilities(qubits);
+        (0..shots).map(|_| sample_index(&distribution, rng.r#gen()) as u64).collect()
+    }
+
+    // Probability weight approximations have dropped so far (0 for exact simulators)
+    fn truncation_error(&self) -> f64 {
+        0.0
+    }
+
+    // Projective measurement in the computational basis, the state collapses
+    fn measure(&mut self, qubit: usize, rng: &mut dyn RngCore) -> bool;
+
+    // Permute the basis states of `register` (bit k of the value is register[k])
+    // by the bijection `f`, only where every control is |1>.
+    // This is how oracles such as modular multiplication run without a gate-level circuit.
+    fn apply_permutation(&mut self, controls: &[usize], register: &[usize], f: &dyn Fn(u64) -> u64);
+
+    // The channel with Kraus operators `kraus` (Σ K†K = 1) on `qubit`.
+    // A pure state cannot hold the mixture, so it picks one K_i with probability
+    // ||K_i ψ||^2 (one Monte Carlo trajectory); a mixed state applies all of them.
+    fn apply_kraus(&mut self, qubit: usize, kraus: &[Matrix2], rng: &mut dyn RngCore);
+
+    // Bring `qubit` back to |0> (measure, then flip if it was 1)
+    fn reset(&mut self, qubit: usize, rng: &mut dyn RngCore) {
+        if self.measure(qubit, rng) {
+            self.apply(&Gate::x(qubit));
+        }
+    }
+}
+
+// Index drawn from `distribution` with the uniform variate `u` in [0, 1)
+pub(crate) fn sample_index(distribution: &[f64], u: f64) -> usize {
+    let total: f64 = distribution.iter().sum();
+    let mut x = u * total;
+    for (i, p) in distribution.iter().enumerate() {
+        x -= p;
+        if x < 0.0 {
+            return i;
+        }
+    }
+    distribution.iter().rposition(|&p| p > 0.0).unwrap_or(0)
+}
+
+// Which simulator the quantum backends run their circuits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimulatorKind {
+    // Pure states, noise as random trajectories
+    #[default]
+    StateVector,
+    // Mixed states, noise applied exactly, twice the qubits' worth of memory
+    DensityMatrix,
+    // Matrix product states with bonds capped at `max_bond`, approximate past it
+    Mps { max_bond: usize },
+}
+
+impl SimulatorKind {
+    pub const ALL: [SimulatorKind; 3] =
+        [SimulatorKind::StateVector, SimulatorKind::DensityMatrix, SimulatorKind::Mps { max_bond: DEFAULT_MAX_BOND }];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimulatorKind::StateVector => "statevector",
+            SimulatorKind::DensityMatrix => "density",
+            SimulatorKind::Mps { .. } => "mps",
+        }
+    }
+
+    // The state vector size (in qubits) simulating `num_qubits` qubits takes.
+    // None for an MPS, whose memory depends on the entanglement instead.
+    pub fn memory_qubits(&self, num_qubits: usize) -> Option<usize> {
+        match self {
+            SimulatorKind::StateVector => Some(num_qubits),
+            SimulatorKind::DensityMatrix => Some(2 * num_qubits),
+            SimulatorKind::Mps { .. } => None,
+        }
+    }
+
+    // |0...0> on a new simulator of this kind
+    pub fn create(&self, num_qubits: usize) -> Result<Box<dyn Simulator>, SimError> {
+        Ok(match self {
+            SimulatorKind::StateVector => Box::new(StateVector::new(num_qubits)?),
+            SimulatorKind::DensityMatrix => Box::new(DensityMatrix::new(num_qubits)?),
+            SimulatorKind::Mps { max_bond } => Box::new(Mps::new(num_qubits, *max_bond)?),
+        })
+    }
+}
+
+impl fmt::Display for SimulatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulatorKind::Mps { max_bond } => write!(f, "mps:{}", max_bond),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+impl FromStr for SimulatorKind {
+    type Err = String;
+
+    // A name, or mps:BOND for an MPS with another bond cap
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(bond) = s.strip_prefix("mps:") {
+            let max_bond = bond.parse().ok().filter(|&b| b > 0).ok_or_else(|| format!("Invalid bond dimension {}", bond))?;
+            return Ok(SimulatorKind::Mps { max_bond });
+        }
+        SimulatorKind::ALL.iter().copied().find(|k| k.name() == s).ok_or_else(|| {
+            let names: Vec<_> = SimulatorKind::ALL.iter().map(|k| k.name()).collect();
+            format!("Unknown simulator {} (available: {})", s, names.join(", "))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimError {
+    TooManyQubits { requested: usize, max: usize },
+    OutOfMemory { qubits: usize, bytes: u128 },
+    InvalidState(String),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::TooManyQubits { requested, max } => write!(f, "{} qubits requested, at most {} can be simulated", requested, max),
+            SimError::OutOfMemory { qubits, bytes } => write!(f, "not enough memory for {} qubits ({} bytes)", qubits, bytes),
+            SimError::InvalidState(msg) => write!(f, "invalid state: {}", msg),
+        }
+    }
+}
+
+impl Error for SimError {}
+
+// Circuits the tests of the simulators cross-check them on
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{Gate, GateKind};
+    use crate::circuit::{Circuit, Instruction};
+    use rand::seq::SliceRandom;
+    use rand::{Rng, RngCore};
+    use std::f64::consts::PI;
+
+    // `length` random unitary instructions: every gate kind with up to two controls
+    // anywhere in the register, and now and then a controlled multiplication mod 7
+    // on three qubits (which runs as `apply_permutation`)
+    pub fn random_circuit(num_qubits: usize, length: usize, rng: &mut dyn RngCore) -> Circuit {
+        let mut circuit = Circuit::new(num_qubits, 0);
+        let mut qubits: Vec<usize> = (0..num_qubits).collect();
+        for _ in 0..length {
+            qubits.shuffle(rng);
+            if num_qubits >= 4 && rng.gen_bool(0.1) {
+                let (controls, register) = (qubits[..1].to_vec(), qubits[1..4].to_vec());
+                circuit.instructions.push(Instruction::ModMul { controls, register, a: rng.gen_range(2..7), n: 7 });
+                continue;
+            }
+            let [theta, phi, lambda]: [f64; 3] = std::array::from_fn(|_| rng.gen_range(-PI..PI));
+            let kind = match rng.gen_range(0..13) {
+                0 => GateKind::H,
+                1 => GateKind::X,
+                2 => GateKind::Y,
+                3 => GateKind::Z,
+                4 => GateKind::S,
+                5 => GateKind::Sdg,
+                6 => GateKind::T,
+                7 => GateKind::Tdg,
+                8 => GateKind::Phase(theta),
+                9 => GateKind::Rx(theta),
+                10 => GateKind::Ry(theta),
+                11 => GateKind::Rz(theta),
+                _ => GateKind::U(theta, phi, lambda),
+            };
+            let controls = rng.gen_range(0..3).min(num_qubits - 1);
+            circuit.push(qubits[1..=controls].iter().fold(Gate::new(kind, qubits[0]), |gate, &c| gate.controlled(c)));
+        }
+        circuit
+    }
+}
@@ -0,0 +1,241 @@
+use super::state::extract_bits;
+use super::{Gate, Matrix2, SimError, Simulator, StateVector, MAX_QUBITS};
+use num_complex::Complex64;
+use rand::{Rng, RngCore};
+
+// A mixed state of n qubits as its 2^n x 2^n density matrix ρ, which noise
+// channels act on exactly instead of one random trajectory at a time.
+//
+// ρ is stored vectorized as a 2n-qubit `StateVector`, entry ρ[row][col] at index
+// row | col << n. A gate U on qubit q is then U on qubit q and conj(U) on qubit
+// q + n, so the state-vector kernels do the work. 4^n entries: 12 qubits take 256 MiB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMatrix {
+    num_qubits: usize,
+    vectorized: StateVector,
+}
+
+impl DensityMatrix {
+    // |0...0><0...0| on `num_qubits` qubits
+    pub fn new(num_qubits: usize) -> Result<Self, SimError> {
+        if 2 * num_qubits > MAX_QUBITS {
+            return Err(SimError::TooManyQubits { requested: num_qubits, max: MAX_QUBITS / 2 });
+        }
+        Ok(DensityMatrix { num_qubits, vectorized: StateVector::new(2 * num_qubits)? })
+    }
+
+    // The pure state |ψ><ψ|
+    pub fn from_state(state: &StateVector) -> Result<Self, SimError> {
+        let n = state.num_qubits();
+        let mut rho = DensityMatrix::new(n)?;
+        let amplitudes = state.amplitudes();
+        for (index, entry) in rho.vectorized.amplitudes_mut().iter_mut().enumerate() {
+            let (row, col) = (index & ((1 << n) - 1), index >> n);
+            *entry = amplitudes[row] * amplitudes[col].conj();
+        }
+        Ok(rho)
+    }
+
+    // Memory the matrix of `num_qubits` qubits takes
+    pub fn memory_bytes(num_qubits: usize) -> u128 {
+        StateVector::memory_bytes(2 * num_qubits)
+    }
+
+    // ρ[row][col]
+    pub fn entry(&self, row: usize, col: usize) -> Complex64 {
+        self.vectorized.amplitudes()[row | col << self.num_qubits]
+    }
+
+    pub fn trace(&self) -> f64 {
+        (0..1usize << self.num_qubits).map(|i| self.entry(i, i).re).sum()
+    }
+
+    // Tr ρ^2: 1 for a pure state, down to 2^-n for the maximally mixed one
+    pub fn purity(&self) -> f64 {
+        // ρ is Hermitian, so Tr ρ^2 = Σ |ρ_ij|^2
+        self.vectorized.norm_sqr() / (self.trace() * self.trace())
+    }
+
+    // Exact probability of every basis state, the diagonal of ρ
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.marginal_probabilities(&(0..self.num_qubits).collect::<Vec<_>>())
+    }
+
+    // The reduced state of `keep` (qubit keep[k] becomes qubit k), all other qubits traced out
+    pub fn partial_trace(&self, keep: &[usize]) -> Result<DensityMatrix, SimError> {
+        for &q in keep {
+            self.check_qubit(q);
+        }
+        let n = self.num_qubits;
+        let m = keep.len();
+        let keep_mask = keep.iter().fold(0usize, |mask, &q| mask | 1 << q);
+        let mut reduced = DensityMatrix::new(m)?;
+        let entries = reduced.vectorized.amplitudes_mut();
+        entries[0] = Complex64::new(0.0, 0.0);
+        for (index, &value) in self.vectorized.amplitudes().iter().enumerate() {
+            let (row, col) = (index & ((1 << n) - 1), index >> n);
+            // Only the diagonal of the traced-out qubits contributes
+            if (row ^ col) & !keep_mask == 0 {
+                entries[extract_bits(row, keep) as usize | (extract_bits(col, keep) as usize) << m] += value;
+            }
+        }
+        Ok(reduced)
+    }
+
+    fn check_qubit(&self, qubit: usize) {
+        assert!(qubit < self.num_qubits, "qubit {} out of range for {} qubits", qubit, self.num_qubits);
+    }
+
+    // B -> Σ K B K† on every 2x2 block of ρ for `qubit`, with S[r + 2c][r' + 2c'] = Σ K[r][r'] conj(K[c][c'])
+    fn apply_superoperator(&mut self, qubit: usize, s: &[[Complex64; 4]; 4]) {
+        let row_bit = 1usize << qubit;
+        let col_bit = 1usize << (qubit + self.num_qubits);
+        let entries = self.vectorized.amplitudes_mut();
+        let offsets = [0, row_bit, col_bit, row_bit | col_bit];
+        for base in 0..entries.len() {
+            if base & (row_bit | col_bit) != 0 {
+                continue;
+            }
+            let block = offsets.map(|o| entries[base | o]);
+            for (k, o) in offsets.iter().enumerate() {
+                entries[base | o] = (0..4).map(|j| s[k][j] * block[j]).sum();
+            }
+        }
+    }
+}
+
+fn conj(m: &Matrix2) -> Matrix2 {
+    m.map(|row| row.map(|e| e.conj()))
+}
+
+impl Simulator for DensityMatrix {
+    fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    fn apply(&mut self, gate: &Gate) {
+        for q in gate.qubits() {
+            self.check_qubit(q);
+        }
+        // U ρ U†: U on the row index, conj(U) on the column index
+        self.vectorized.apply(gate);
+        let control_mask = gate.controls.iter().fold(0usize, |mask, &c| mask | 1 << (c + self.num_qubits));
+        self.vectorized.apply_matrix(gate.target + self.num_qubits, control_mask, &conj(&gate.kind.matrix()));
+    }
+
+    fn apply_permutation(&mut self, controls: &[usize], register: &[usize], f: &dyn Fn(u64) -> u64) {
+        let n = self.num_qubits;
+        self.vectorized.apply_permutation(controls, register, f);
+        let shift = |qubits: &[usize]| qubits.iter().map(|q| q + n).collect::<Vec<_>>();
+        self.vectorized.apply_permutation(&shift(controls), &shift(register), f);
+    }
+
+    // Exact: every Kraus operator at once, ρ -> Σ K ρ K†
+    fn apply_kraus(&mut self, qubit: usize, kraus: &[Matrix2], _rng: &mut dyn RngCore) {
+        self.check_qubit(qubit);
+        let zero = Complex64::new(0.0, 0.0);
+        let mut s = [[zero; 4]; 4];
+        for k in kraus {
+            for (r, c, r2, c2) in (0..16).map(|i| (i & 1, (i >> 1) & 1, (i >> 2) & 1, i >> 3)) {
+                s[r + 2 * c][r2 + 2 * c2] += k[r][r2] * k[c][c2].conj();
+            }
+        }
+        self.apply_superoperator(qubit, &s);
+    }
+
+    fn probability_one(&self, qubit: usize) -> f64 {
+        self.check_qubit(qubit);
+        self.marginal_probabilities(&[qubit])[1]
+    }
+
+    // The outcome is random, the state left behind is the exact post-measurement ρ
+    fn measure(&mut self, qubit: usize, rng: &mut dyn RngCore) -> bool {
+        let p1 = self.probability_one(qubit);
+        let outcome = rng.r#gen::<f64>() < p1;
+        let scale = 1.0 / if outcome { p1 } else { 1.0 - p1 };
+        let n = self.num_qubits;
+        let want = if outcome { 1 } else { 0 };
+        for (index, entry) in self.vectorized.amplitudes_mut().iter_mut().enumerate() {
+            let (row, col) = ((index >> qubit) & 1, (index >> (qubit + n)) & 1);
+            if row == want && col == want {
+                *entry *= scale;
+            } else {
+                *entry = Complex64::new(0.0, 0.0);
+            }
+        }
+        outcome
+    }
+
+    fn marginal_probabilities(&self, qubits: &[usize]) -> Vec<f64> {
+        let mut marginal = vec![0.0; 1 << qubits.len()];
+        for i in 0..1usize << self.num_qubits {
+            marginal[extract_bits(i, qubits) as usize] += self.entry(i, i).re;
+        }
+        let trace: f64 = marginal.iter().sum();
+        marginal.iter().map(|p| p / trace).collect()
+    }
+
+    // Deterministic: the channel |0><0|, |0><1| leaves |0> whatever was there
+    fn reset(&mut self, qubit: usize, rng: &mut dyn RngCore) {
+        let (zero, one) = (Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0));
+        self.apply_kraus(qubit, &[[[one, zero], [zero, zero]], [[zero, one], [zero, zero]]], rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::testing::random_circuit;
+    use crate::seeded_rng;
+
+    #[test]
+    fn matches_the_state_vector_on_random_circuits() {
+        for seed in 0..10 {
+            let mut rng = seeded_rng(seed);
+            let circuit = random_circuit(4, 60, &mut rng);
+            let mut pure = StateVector::new(4).unwrap();
+            let mut mixed = DensityMatrix::new(4).unwrap();
+            circuit.run(&mut pure, &mut rng);
+            circuit.run(&mut mixed, &mut rng);
+
+            // ρ = |ψ><ψ|
+            let psi = pure.amplitudes();
+            for row in 0..16 {
+                for col in 0..16 {
+                    let expected = psi[row] * psi[col].conj();
+                    assert!((mixed.entry(row, col) - expected).norm() < 1e-10, "seed {}: ρ[{}][{}] = {}, expected {}", seed, row, col, mixed.entry(row, col), expected);
+                }
+            }
+            assert!((mixed.trace() - 1.0).abs() < 1e-10);
+            assert!((mixed.purity() - 1.0).abs() < 1e-10);
+            for (p, q) in mixed.marginal_probabilities(&[2, 0]).iter().zip(pure.marginal_probabilities(&[2, 0])) {
+                assert!((p - q).abs() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn from_state_is_the_projector() {
+        let mut rng = seeded_rng(3);
+        let mut pure = StateVector::new(3).unwrap();
+        random_circuit(3, 30, &mut rng).run(&mut pure, &mut rng);
+        let rho = DensityMatrix::from_state(&pure).unwrap();
+        let psi = pure.amplitudes();
+        for row in 0..8 {
+            for col in 0..8 {
+                assert!((rho.entry(row, col) - psi[row] * psi[col].conj()).norm() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn half_a_bell_pair_is_maximally_mixed() {
+        let mut rho = DensityMatrix::new(2).unwrap();
+        rho.apply(&Gate::h(0));
+        rho.apply(&Gate::cnot(0, 1));
+        let half = rho.partial_trace(&[1]).unwrap();
+        assert!((half.entry(0, 0).re - 0.5).abs() < 1e-12 && (half.entry(1, 1).re - 0.5).abs() < 1e-12);
+        assert!(half.entry(0, 1).norm() < 1e-12);
+        assert!((half.purity() - 0.5).abs() < 1e-12);
+    }
+}
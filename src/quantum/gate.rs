@@ -0,0 +1,177 @@
+use num_complex::Complex64;
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4};
+
+// A 2x2 complex matrix, row major: [[m00, m01], [m10, m11]]
+pub type Matrix2 = [[Complex64; 2]; 2];
+
+// The single-qubit operation a gate applies to its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GateKind {
+    H,
+    X,
+    Y,
+    Z,
+    S,
+    Sdg,
+    T,
+    Tdg,
+    // diag(1, e^(iθ))
+    Phase(f64),
+    Rx(f64),
+    Ry(f64),
+    Rz(f64),
+    // The general single-qubit gate U(θ, φ, λ) of OpenQASM
+    U(f64, f64, f64),
+}
+
+impl GateKind {
+    pub fn matrix(&self) -> Matrix2 {
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let zero = c(0.0, 0.0);
+        let one = c(1.0, 0.0);
+        let phase = |theta: f64| Complex64::from_polar(1.0, theta);
+        match *self {
+            GateKind::H => [[c(FRAC_1_SQRT_2, 0.0), c(FRAC_1_SQRT_2, 0.0)], [c(FRAC_1_SQRT_2, 0.0), c(-FRAC_1_SQRT_2, 0.0)]],
+            GateKind::X => [[zero, one], [one, zero]],
+            GateKind::Y => [[zero, c(0.0, -1.0)], [c(0.0, 1.0), zero]],
+            GateKind::Z => [[one, zero], [zero, -one]],
+            GateKind::S => [[one, zero], [zero, phase(FRAC_PI_2)]],
+            GateKind::Sdg => [[one, zero], [zero, phase(-FRAC_PI_2)]],
+            GateKind::T => [[one, zero], [zero, phase(FRAC_PI_4)]],
+            GateKind::Tdg => [[one, zero], [zero, phase(-FRAC_PI_4)]],
+            GateKind::Phase(theta) => [[one, zero], [zero, phase(theta)]],
+            GateKind::Rx(theta) => {
+                let (s, co) = (theta / 2.0).sin_cos();
+                [[c(co, 0.0), c(0.0, -s)], [c(0.0, -s), c(co, 0.0)]]
+            }
+            GateKind::Ry(theta) => {
+                let (s, co) = (theta / 2.0).sin_cos();
+                [[c(co, 0.0), c(-s, 0.0)], [c(s, 0.0), c(co, 0.0)]]
+            }
+            GateKind::Rz(theta) => [[phase(-theta / 2.0), zero], [zero, phase(theta / 2.0)]],
+            GateKind::U(theta, phi, lambda) => {
+                let (s, co) = (theta / 2.0).sin_cos();
+                [
+                    [c(co, 0.0), -phase(lambda) * s],
+                    [phase(phi) * s, phase(phi + lambda) * co],
+                ]
+            }
+        }
+    }
+
+    // The kind that undoes this one
+    pub fn inverse(&self) -> GateKind {
+        match *self {
+            GateKind::S => GateKind::Sdg,
+            GateKind::Sdg => GateKind::S,
+            GateKind::T => GateKind::Tdg,
+            GateKind::Tdg => GateKind::T,
+            GateKind::Phase(theta) => GateKind::Phase(-theta),
+            GateKind::Rx(theta) => GateKind::Rx(-theta),
+            GateKind::Ry(theta) => GateKind::Ry(-theta),
+            GateKind::Rz(theta) => GateKind::Rz(-theta),
+            GateKind::U(theta, phi, lambda) => GateKind::U(-theta, -lambda, -phi),
+            self_inverse => self_inverse,
+        }
+    }
+
+    // Lower-case name as in OpenQASM ("h", "sdg", "p", "rz", ...)
+    pub fn name(&self) -> &'static str {
+        match self {
+            GateKind::H => "h",
+            GateKind::X => "x",
+            GateKind::Y => "y",
+            GateKind::Z => "z",
+            GateKind::S => "s",
+            GateKind::Sdg => "sdg",
+            GateKind::T => "t",
+            GateKind::Tdg => "tdg",
+            GateKind::Phase(_) => "p",
+            GateKind::Rx(_) => "rx",
+            GateKind::Ry(_) => "ry",
+            GateKind::Rz(_) => "rz",
+            GateKind::U(..) => "u",
+        }
+    }
+
+    // Diagonal in the computational basis (commutes with controls and other diagonals)
+    pub fn is_diagonal(&self) -> bool {
+        matches!(self, GateKind::Z | GateKind::S | GateKind::Sdg | GateKind::T | GateKind::Tdg | GateKind::Phase(_) | GateKind::Rz(_))
+    }
+}
+
+// A single-qubit operation on `target`, applied only when every control is |1>.
+// CNOT, Toffoli and controlled phases are all this with one or two controls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gate {
+    pub kind: GateKind,
+    pub target: usize,
+    pub controls: Vec<usize>,
+}
+
+impl Gate {
+    pub fn new(kind: GateKind, target: usize) -> Self {
+        Gate { kind, target, controls: Vec::new() }
+    }
+
+    pub fn h(target: usize) -> Self {
+        Gate::new(GateKind::H, target)
+    }
+
+    pub fn x(target: usize) -> Self {
+        Gate::new(GateKind::X, target)
+    }
+
+    pub fn z(target: usize) -> Self {
+        Gate::new(GateKind::Z, target)
+    }
+
+    pub fn s(target: usize) -> Self {
+        Gate::new(GateKind::S, target)
+    }
+
+    pub fn t(target: usize) -> Self {
+        Gate::new(GateKind::T, target)
+    }
+
+    pub fn phase(target: usize, theta: f64) -> Self {
+        Gate::new(GateKind::Phase(theta), target)
+    }
+
+    pub fn cnot(control: usize, target: usize) -> Self {
+        Gate::new(GateKind::X, target).controlled(control)
+    }
+
+    pub fn cphase(control: usize, target: usize, theta: f64) -> Self {
+        Gate::phase(target, theta).controlled(control)
+    }
+
+    pub fn toffoli(control1: usize, control2: usize, target: usize) -> Self {
+        Gate::x(target).controlled(control1).controlled(control2)
+    }
+
+    // Swap as three CNOTs
+    pub fn swap(a: usize, b: usize) -> [Gate; 3] {
+        [Gate::cnot(a, b), Gate::cnot(b, a), Gate::cnot(a, b)]
+    }
+
+    // The same gate with one more control
+    pub fn controlled(mut self, control: usize) -> Self {
+        self.controls.push(control);
+        self
+    }
+
+    pub fn inverse(&self) -> Self {
+        Gate { kind: self.kind.inverse(), target: self.target, controls: self.controls.clone() }
+    }
+
+    // The kind's name with a "c" per control: "cx", "ccx", "cp", ...
+    pub fn name(&self) -> String {
+        format!("{}{}", "c".repeat(self.controls.len()), self.kind.name())
+    }
+
+    // Every qubit the gate touches, controls first
+    pub fn qubits(&self) -> impl Iterator<Item = usize> + '_ {
+        self.controls.iter().copied().chain(std::iter::once(self.target))
+    }
+}
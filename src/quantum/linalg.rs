@@ -0,0 +1,343 @@
+// Dense complex matrices, and the QR and SVD the MPS simulator works with.
+// Small and self-contained: Householder QR and bidiagonalization, then the
+// Golub-Kahan implicit-shift QR iteration on the real bidiagonal.
+
+use num_complex::Complex64;
+
+const ZERO: Complex64 = Complex64::new(0.0, 0.0);
+const ONE: Complex64 = Complex64::new(1.0, 0.0);
+// Superdiagonal entries this small relative to their neighbours count as zero
+const EPSILON: f64 = 1e-15;
+// QR steps per singular value before giving up on convergence
+const MAX_ITERATIONS: usize = 75;
+
+// Row-major rows x cols matrix
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<Complex64>,
+}
+
+impl CMatrix {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        CMatrix { rows, cols, data: vec![ZERO; rows * cols] }
+    }
+
+    // Conjugate transpose
+    pub fn adjoint(&self) -> CMatrix {
+        let mut t = CMatrix::zeros(self.cols, self.rows);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                t[(j, i)] = self[(i, j)].conj();
+            }
+        }
+        t
+    }
+
+    pub fn matmul(&self, other: &CMatrix) -> CMatrix {
+        assert_eq!(self.cols, other.rows, "matrix shapes do not match");
+        let mut out = CMatrix::zeros(self.rows, other.cols);
+        for i in 0..self.rows {
+            let row = &mut out.data[i * other.cols..(i + 1) * other.cols];
+            for k in 0..self.cols {
+                let a = self[(i, k)];
+                if a == ZERO {
+                    continue;
+                }
+                for (o, b) in row.iter_mut().zip(&other.data[k * other.cols..(k + 1) * other.cols]) {
+                    *o += a * b;
+                }
+            }
+        }
+        out
+    }
+
+}
+
+impl std::ops::Index<(usize, usize)> for CMatrix {
+    type Output = Complex64;
+
+    fn index(&self, (i, j): (usize, usize)) -> &Complex64 {
+        &self.data[i * self.cols + j]
+    }
+}
+
+impl std::ops::IndexMut<(usize, usize)> for CMatrix {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Complex64 {
+        &mut self.data[i * self.cols + j]
+    }
+}
+
+// A = U diag(s) Vh with k = min(rows, cols) singular values, largest first
+#[derive(Debug, Clone)]
+pub(crate) struct Svd {
+    pub u: CMatrix,
+    pub s: Vec<f64>,
+    pub vh: CMatrix,
+}
+
+pub(crate) fn svd(a: &CMatrix) -> Svd {
+    if a.rows < a.cols {
+        // A† = U S Vh  =>  A = Vh† S U†
+        let Svd { u, s, vh } = svd(&a.adjoint());
+        return Svd { u: vh.adjoint(), s, vh: u.adjoint() };
+    }
+    // A = Q R when tall, then R = U B V† with B bidiagonal, made real by phases,
+    // and B = U2 S V2†. U and V are kept transposed (ut, vt) so that every
+    // rotation and reflection works on contiguous rows.
+    let (q, r) = if a.rows > a.cols { let (q, r) = qr(a); (Some(q), r) } else { (None, a.clone()) };
+    let n = r.cols;
+    let (mut ut, mut b, mut vt) = (identity(n), r, identity(n));
+    for k in 0..n {
+        if let Some(h) = reflector(&(k..n).map(|i| b[(i, k)]).collect::<Vec<_>>()) {
+            reflect_rows(&mut b, &h, k);
+            reflect_rows(&mut ut, &conj(&h), k);
+        }
+        if k + 1 < n {
+            // Row k times (1 - 2 w w†) is ((1 - 2 conj(w) w^T) x)^T for the row as a column x
+            if let Some(h) = reflector(&b.data[k * n + k + 1..(k + 1) * n]) {
+                reflect_columns(&mut b, &conj(&h), k + 1);
+                reflect_rows(&mut vt, &h, k + 1);
+            }
+        }
+    }
+    let mut d = vec![0.0; n];
+    let mut e = vec![0.0; n];
+    let mut carry = ONE;
+    for k in 0..n {
+        let dk = b[(k, k)] * carry;
+        let phase = if dk.norm() > 0.0 { dk / dk.norm() } else { ONE };
+        scale_row(&mut ut, k, phase);
+        d[k] = dk.norm();
+        carry = ONE;
+        if k + 1 < n {
+            let ek = b[(k, k + 1)] * phase.conj();
+            let psi = if ek.norm() > 0.0 { ek.conj() / ek.norm() } else { ONE };
+            scale_row(&mut vt, k + 1, psi);
+            e[k] = ek.norm();
+            carry = psi;
+        }
+    }
+    bidiagonal_svd(&mut d, &mut e, &mut ut, &mut vt);
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&x, &y| d[y].total_cmp(&d[x]));
+    let mut u = CMatrix::zeros(n, n);
+    let mut vh = CMatrix::zeros(n, n);
+    for (k, &j) in order.iter().enumerate() {
+        for i in 0..n {
+            u[(i, k)] = ut[(j, i)];
+        }
+        for (x, y) in vh.data[k * n..(k + 1) * n].iter_mut().zip(&vt.data[j * n..(j + 1) * n]) {
+            *x = y.conj();
+        }
+    }
+    let s = order.iter().map(|&j| d[j]).collect();
+    Svd { u: match q { Some(q) => q.matmul(&u), None => u }, s, vh }
+}
+
+fn conj(v: &[Complex64]) -> Vec<Complex64> {
+    v.iter().map(|x| x.conj()).collect()
+}
+
+fn identity(n: usize) -> CMatrix {
+    let mut m = CMatrix::zeros(n, n);
+    for i in 0..n {
+        m[(i, i)] = ONE;
+    }
+    m
+}
+
+fn scale_row(m: &mut CMatrix, i: usize, factor: Complex64) {
+    for x in &mut m.data[i * m.cols..(i + 1) * m.cols] {
+        *x *= factor;
+    }
+}
+
+// Real rotation of rows j and k: (r_j, r_k) <- (c r_j + s r_k, c r_k - s r_j)
+fn rotate_rows(m: &mut CMatrix, j: usize, k: usize, c: f64, s: f64) {
+    let cols = m.cols;
+    let (lo, hi) = (j.min(k), j.max(k));
+    let (head, tail) = m.data.split_at_mut(hi * cols);
+    let (first, second) = (&mut head[lo * cols..(lo + 1) * cols], &mut tail[..cols]);
+    let (rj, rk) = if j < k { (first, second) } else { (second, first) };
+    for (x, y) in rj.iter_mut().zip(rk.iter_mut()) {
+        let (a, b) = (*x, *y);
+        *x = a * c + b * s;
+        *y = b * c - a * s;
+    }
+}
+
+// c, s, r with c y + s z = r and c z - s y = 0
+fn givens(y: f64, z: f64) -> (f64, f64, f64) {
+    let r = y.hypot(z);
+    if r == 0.0 { (1.0, 0.0, 0.0) } else { (y / r, z / r, r) }
+}
+
+// Golub-Kahan implicit-shift QR on the upper bidiagonal (d, e): on return d holds
+// the singular values (unsorted, non-negative), and the left and right rotations
+// have been applied to the rows of ut and vt
+fn bidiagonal_svd(d: &mut [f64], e: &mut [f64], ut: &mut CMatrix, vt: &mut CMatrix) {
+    let n = d.len();
+    let norm = d.iter().zip(e.iter()).map(|(x, y)| x.abs() + y.abs()).fold(0.0, f64::max);
+    let mut hi = n;
+    for _ in 0..MAX_ITERATIONS * n.max(1) {
+        for i in 0..n.saturating_sub(1) {
+            if e[i].abs() <= EPSILON * (d[i].abs() + d[i + 1].abs()) {
+                e[i] = 0.0;
+            }
+        }
+        // The trailing part already diagonal is done
+        while hi > 1 && e[hi - 2] == 0.0 {
+            hi -= 1;
+        }
+        if hi <= 1 {
+            break;
+        }
+        let mut lo = hi - 2;
+        while lo > 0 && e[lo - 1] != 0.0 {
+            lo -= 1;
+        }
+        let last = hi - 1;
+        // A zero on the diagonal: rotate its superdiagonal entry away along the row
+        if let Some(i) = (lo..last).find(|&i| d[i].abs() <= EPSILON * norm) {
+            d[i] = 0.0;
+            let mut f = e[i];
+            e[i] = 0.0;
+            for j in i + 1..=last {
+                let (c, s, r) = givens(d[j], f);
+                d[j] = r;
+                rotate_rows(ut, j, i, c, s);
+                if j < last {
+                    f = -s * e[j];
+                    e[j] *= c;
+                }
+            }
+            continue;
+        }
+        // Wilkinson shift from the trailing 2x2 of B^T B
+        let t11 = d[last - 1] * d[last - 1] + if last >= lo + 2 { e[last - 2] * e[last - 2] } else { 0.0 };
+        let t12 = d[last - 1] * e[last - 1];
+        let t22 = d[last] * d[last] + e[last - 1] * e[last - 1];
+        let delta = (t11 - t22) / 2.0;
+        let denominator = delta + delta.signum() * delta.hypot(t12);
+        let mu = if denominator == 0.0 { t22 } else { t22 - t12 * t12 / denominator };
+        let (mut y, mut z) = (d[lo] * d[lo] - mu, d[lo] * e[lo]);
+        for k in lo..last {
+            let (c, s, r) = givens(y, z);
+            if k > lo {
+                e[k - 1] = r;
+            }
+            let (dk, ek) = (d[k], e[k]);
+            d[k] = c * dk + s * ek;
+            e[k] = c * ek - s * dk;
+            let bulge = s * d[k + 1];
+            d[k + 1] *= c;
+            rotate_rows(vt, k, k + 1, c, s);
+
+            let (c, s, r) = givens(d[k], bulge);
+            d[k] = r;
+            let (ek, dk1) = (e[k], d[k + 1]);
+            e[k] = c * ek + s * dk1;
+            d[k + 1] = c * dk1 - s * ek;
+            rotate_rows(ut, k, k + 1, c, s);
+            if k + 1 < last {
+                z = s * e[k + 1];
+                e[k + 1] *= c;
+                y = e[k];
+            }
+        }
+    }
+    for (k, x) in d.iter_mut().enumerate() {
+        if *x < 0.0 {
+            *x = -*x;
+            scale_row(vt, k, -ONE);
+        }
+    }
+}
+
+// Householder vector v (unit) with (1 - 2 v v†) x along e0, None when x = 0
+fn reflector(x: &[Complex64]) -> Option<Vec<Complex64>> {
+    let norm = norm_sqr(x).sqrt();
+    if norm == 0.0 {
+        return None;
+    }
+    // v = x + e^(i arg x0) |x| e0 avoids cancellation
+    let mut v = x.to_vec();
+    let phase = if x[0].norm() > 0.0 { x[0] / x[0].norm() } else { ONE };
+    v[0] += phase * norm;
+    let v_norm = norm_sqr(&v).sqrt();
+    for y in v.iter_mut() {
+        *y /= v_norm;
+    }
+    Some(v)
+}
+
+// M <- (1 - 2 v v†) M on rows from.., v spanning them
+fn reflect_rows(m: &mut CMatrix, v: &[Complex64], from: usize) {
+    let cols = m.cols;
+    let mut dots = vec![ZERO; cols];
+    for (i, vi) in v.iter().enumerate() {
+        let row = &m.data[(from + i) * cols..(from + i + 1) * cols];
+        for (dot, x) in dots.iter_mut().zip(row) {
+            *dot += vi.conj() * x;
+        }
+    }
+    for (i, vi) in v.iter().enumerate() {
+        let row = &mut m.data[(from + i) * cols..(from + i + 1) * cols];
+        for (x, dot) in row.iter_mut().zip(&dots) {
+            *x -= *vi * dot * 2.0;
+        }
+    }
+}
+
+// M <- M (1 - 2 w w†) on columns from.., w spanning them
+fn reflect_columns(m: &mut CMatrix, w: &[Complex64], from: usize) {
+    let cols = m.cols;
+    for row in m.data.chunks_mut(cols) {
+        let part = &mut row[from..from + w.len()];
+        let dot: Complex64 = part.iter().zip(w).map(|(x, wi)| x * wi).sum();
+        for (x, wi) in part.iter_mut().zip(w) {
+            *x -= wi.conj() * dot * 2.0;
+        }
+    }
+}
+
+fn norm_sqr(column: &[Complex64]) -> f64 {
+    column.iter().map(|x| x.norm_sqr()).sum()
+}
+
+// Thin QR by Householder reflections, k = min(rows, cols): Q is rows x k with
+// orthonormal columns, R is k x cols upper triangular. Moving the MPS center only
+// needs this, not a full SVD.
+pub(crate) fn qr(a: &CMatrix) -> (CMatrix, CMatrix) {
+    let (m, n) = (a.rows, a.cols);
+    let k = m.min(n);
+    let mut r = a.clone();
+    let mut reflectors = Vec::with_capacity(k);
+    for c in 0..k {
+        let h = reflector(&(c..m).map(|i| r[(i, c)]).collect::<Vec<_>>());
+        if let Some(h) = &h {
+            reflect_rows(&mut r, h, c);
+        }
+        reflectors.push(h);
+    }
+    // Q = H_0 H_1 ... H_(k-1) applied to the first k columns of the identity
+    let mut q = CMatrix::zeros(m, k);
+    for i in 0..k {
+        q[(i, i)] = ONE;
+    }
+    for (c, h) in reflectors.iter().enumerate().rev() {
+        if let Some(h) = h {
+            reflect_rows(&mut q, h, c);
+        }
+    }
+    let mut upper = CMatrix::zeros(k, n);
+    for i in 0..k {
+        for j in i..n {
+            upper[(i, j)] = r[(i, j)];
+        }
+    }
+    (q, upper)
+}
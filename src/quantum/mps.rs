@@ -0,0 +1,563 @@
+use super::linalg::{qr, svd, CMatrix};
+use super::{Gate, Matrix2, SimError, Simulator};
+use num_complex::Complex64;
+use rand::{Rng, RngCore};
+
+// Bond dimension cap `SimulatorKind::Mps` uses unless told otherwise
+pub const DEFAULT_MAX_BOND: usize = 64;
+// Singular values below this fraction of the total weight are numerical noise
+const NEGLIGIBLE_WEIGHT: f64 = 1e-28;
+
+const ZERO: Complex64 = Complex64::new(0.0, 0.0);
+
+// One tensor of the chain, A[l][s][r] at (l * 2 + s) * right + r
+#[derive(Debug, Clone, PartialEq)]
+struct Site {
+    left: usize,
+    right: usize,
+    data: Vec<Complex64>,
+}
+
+impl Site {
+    fn at(&self, l: usize, s: usize, r: usize) -> Complex64 {
+        self.data[(l * 2 + s) * self.right + r]
+    }
+}
+
+// A pure state as a matrix product state: a chain of one tensor per qubit, joined
+// by bonds of dimension at most `max_bond`. Memory grows with the entanglement,
+// not with 2^n, so weakly entangled circuits such as the QFT reach far more qubits
+// than a `StateVector`.
+//
+// Gates act on a block of neighbouring sites: the qubits are first brought next to
+// each other by SWAPs (which stay, the qubit-to-site map keeps track), the block is
+// contracted, the gate applied, and the block split again by SVDs. Every split
+// keeps the `max_bond` largest singular values, and `truncation_error` is one minus
+// the product of the kept weights, an estimate of the infidelity.
+// The chain is kept in mixed-canonical form around `center`, which makes every
+// truncation optimal and local probabilities cheap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mps {
+    sites: Vec<Site>,
+    // Which qubit each site holds, and where each qubit is
+    qubit_at: Vec<usize>,
+    site_of: Vec<usize>,
+    // Sites left of it are left-orthonormal, sites right of it right-orthonormal
+    center: usize,
+    max_bond: usize,
+    truncation_error: f64,
+    largest_bond: usize,
+}
+
+// What to do to the vector of physical indices of a block, bit k - 1 - j of the index being site lo + j
+enum BlockOp<'a> {
+    Matrix { target: usize, control_mask: usize, matrix: &'a Matrix2 },
+    // Index p moves to map[p]
+    Permutation(Vec<usize>),
+}
+
+impl Mps {
+    // |0...0> on `num_qubits` qubits with bonds capped at `max_bond`
+    pub fn new(num_qubits: usize, max_bond: usize) -> Result<Self, SimError> {
+        if num_qubits == 0 || max_bond == 0 {
+            return Err(SimError::InvalidState("an MPS needs at least one qubit and bond dimension 1".to_string()));
+        }
+        let zero_site = Site { left: 1, right: 1, data: vec![Complex64::new(1.0, 0.0), ZERO] };
+        Ok(Mps {
+            sites: vec![zero_site; num_qubits],
+            qubit_at: (0..num_qubits).collect(),
+            site_of: (0..num_qubits).collect(),
+            center: 0,
+            max_bond,
+            truncation_error: 0.0,
+            largest_bond: 1,
+        })
+    }
+
+    pub fn max_bond(&self) -> usize {
+        self.max_bond
+    }
+
+    // Largest bond dimension reached so far
+    pub fn largest_bond(&self) -> usize {
+        self.largest_bond
+    }
+
+    // Bond dimensions between neighbouring sites
+    pub fn bonds(&self) -> Vec<usize> {
+        self.sites[..self.sites.len() - 1].iter().map(|s| s.right).collect()
+    }
+
+    // Numbers of amplitudes stored, against 2^n for a state vector
+    pub fn stored_amplitudes(&self) -> usize {
+        self.sites.iter().map(|s| s.data.len()).sum()
+    }
+
+    // Amplitude of the basis state |index> (qubit q = bit q), by contracting the chain
+    pub fn amplitude(&self, index: u64) -> Complex64 {
+        let mut row = vec![Complex64::new(1.0, 0.0)];
+        for (site, &q) in self.sites.iter().zip(&self.qubit_at) {
+            let s = ((index >> q) & 1) as usize;
+            let mut next = vec![ZERO; site.right];
+            for (l, &x) in row.iter().enumerate() {
+                for (r, y) in next.iter_mut().enumerate() {
+                    *y += x * site.at(l, s, r);
+                }
+            }
+            row = next;
+        }
+        row[0]
+    }
+
+    fn check_qubit(&self, qubit: usize) {
+        assert!(qubit < self.site_of.len(), "qubit {} out of range for {} qubits", qubit, self.site_of.len());
+    }
+
+    // Keep the largest singular values (all but noise, at most `cap`), record what
+    // was dropped, and return how many were kept with their rescaling factor
+    fn truncate(&mut self, s: &[f64], cap: usize) -> (usize, f64) {
+        let total: f64 = s.iter().map(|x| x * x).sum();
+        if total == 0.0 {
+            return (1, 1.0);
+        }
+        let significant = s.iter().take_while(|&&x| x * x > NEGLIGIBLE_WEIGHT * total).count().max(1);
+        let kept = significant.min(cap);
+        let kept_weight: f64 = s[..kept].iter().map(|x| x * x).sum();
+        // Fidelities of successive truncations multiply
+        self.truncation_error = 1.0 - (1.0 - self.truncation_error) * (kept_weight / total);
+        self.largest_bond = self.largest_bond.max(kept);
+        (kept, (total / kept_weight).sqrt())
+    }
+
+    // Move the orthogonality center to `target` by QR decompositions of the sites in between
+    fn move_center(&mut self, target: usize) {
+        while self.center < target {
+            let c = self.center;
+            let site = &self.sites[c];
+            let (left, right) = (site.left, site.right);
+            // A = Q R: Q stays, R moves into the next site
+            let (q, r) = qr(&CMatrix { rows: left * 2, cols: right, data: site.data.clone() });
+            let next = &self.sites[c + 1];
+            let product = r.matmul(&CMatrix { rows: next.left, cols: 2 * next.right, data: next.data.clone() });
+            self.sites[c + 1] = Site { left: r.rows, right: next.right, data: product.data };
+            self.sites[c] = Site { left, right: q.cols, data: q.data };
+            self.center += 1;
+        }
+        while self.center > target {
+            let c = self.center;
+            let site = &self.sites[c];
+            let (left, right) = (site.left, site.right);
+            // A† = Q R, so A = R† Q†: Q† stays, R† moves into the previous site
+            let (q, r) = qr(&CMatrix { rows: left, cols: 2 * right, data: site.data.clone() }.adjoint());
+            let prev = &self.sites[c - 1];
+            let product = CMatrix { rows: prev.left * 2, cols: prev.right, data: prev.data.clone() }.matmul(&r.adjoint());
+            self.sites[c - 1] = Site { left: prev.left, right: r.rows, data: product.data };
+            self.sites[c] = Site { left: q.cols, right, data: q.adjoint().data };
+            self.center -= 1;
+        }
+    }
+
+    // Swap the qubits on sites i and i + 1
+    fn swap_sites(&mut self, i: usize) {
+        self.apply_block(i, i + 1, &BlockOp::Permutation(vec![0, 2, 1, 3]));
+        self.qubit_at.swap(i, i + 1);
+        self.site_of[self.qubit_at[i]] = i;
+        self.site_of[self.qubit_at[i + 1]] = i + 1;
+    }
+
+    // Bring `qubits` onto neighbouring sites around their median, returning the block
+    fn gather(&mut self, qubits: &[usize]) -> (usize, usize) {
+        let mut sites: Vec<usize> = qubits.iter().map(|&q| self.site_of[q]).collect();
+        sites.sort_unstable();
+        sites.dedup();
+        let mid = sites.len() / 2;
+        let start = sites[mid] - mid;
+        // Outwards from the median, so no qubit has to cross another
+        for idx in (0..mid).rev() {
+            let q = self.qubit_at[sites[idx]];
+            while self.site_of[q] < start + idx {
+                self.swap_sites(self.site_of[q]);
+            }
+        }
+        for (idx, &site) in sites.iter().enumerate().skip(mid + 1) {
+            let q = self.qubit_at[site];
+            while self.site_of[q] > start + idx {
+                self.swap_sites(self.site_of[q] - 1);
+            }
+        }
+        (start, start + sites.len() - 1)
+    }
+
+    // Contract sites lo..=hi, apply `op`, and split them again, truncating to `max_bond`
+    fn apply_block(&mut self, lo: usize, hi: usize, op: &BlockOp) {
+        self.move_center(lo);
+        let k = hi - lo + 1;
+        let left = self.sites[lo].left;
+        let right = self.sites[hi].right;
+        // Merged tensor T[l][p][r], site lo + j is bit k - 1 - j of p
+        let mut merged = CMatrix { rows: left * 2, cols: self.sites[lo].right, data: self.sites[lo].data.clone() };
+        for site in &self.sites[lo + 1..=hi] {
+            let next = CMatrix { rows: site.left, cols: 2 * site.right, data: site.data.clone() };
+            let product = merged.matmul(&next);
+            merged = CMatrix { rows: product.rows * 2, cols: site.right, data: product.data };
+        }
+        let dim = 1usize << k;
+        let mut block = merged.data;
+        match op {
+            BlockOp::Matrix { target, control_mask, matrix } => {
+                let bit = 1usize << target;
+                for l in 0..left {
+                    for p in (0..dim).filter(|p| p & bit == 0 && p & control_mask == *control_mask) {
+                        for r in 0..right {
+                            let (i0, i1) = ((l * dim + p) * right + r, (l * dim + (p | bit)) * right + r);
+                            let (x, y) = (block[i0], block[i1]);
+                            block[i0] = matrix[0][0] * x + matrix[0][1] * y;
+                            block[i1] = matrix[1][0] * x + matrix[1][1] * y;
+                        }
+                    }
+                }
+            }
+            BlockOp::Permutation(map) => {
+                let mut permuted = vec![ZERO; block.len()];
+                for l in 0..left {
+                    for (p, &to) in map.iter().enumerate() {
+                        let (src, dst) = ((l * dim + p) * right, (l * dim + to) * right);
+                        permuted[dst..dst + right].copy_from_slice(&block[src..src + right]);
+                    }
+                }
+                block = permuted;
+            }
+        }
+
+        // Split off one site at a time from the left
+        let mut current_left = left;
+        for j in 0..k - 1 {
+            let rows = current_left * 2;
+            let m = CMatrix { rows, cols: block.len() / rows, data: block };
+            let decomposition = svd(&m);
+            let (kept, scale) = self.truncate(&decomposition.s, self.max_bond);
+            self.sites[lo + j] = Site { left: current_left, right: kept, data: columns(&decomposition.u, kept) };
+            block = Vec::with_capacity(kept * m.cols);
+            for i in 0..kept {
+                let sigma = decomposition.s[i] * scale;
+                block.extend(decomposition.vh.data[i * m.cols..(i + 1) * m.cols].iter().map(|x| x * sigma));
+            }
+            current_left = kept;
+        }
+        self.sites[hi] = Site { left: current_left, right, data: block };
+        self.center = hi;
+    }
+
+    // Contract the chain with its conjugate from the left, projecting the sites in
+    // `fixed` onto their given value
+    fn contract(&self, fixed: &[(usize, usize)]) -> f64 {
+        let mut env = vec![Complex64::new(1.0, 0.0)];
+        for (i, site) in self.sites.iter().enumerate() {
+            let s = fixed.iter().find(|(f, _)| *f == i).map(|&(_, s)| s);
+            env = transfer(&env, site, s);
+        }
+        env[0].re
+    }
+
+    // Right environments of every site: envs[i] contracts sites i.. (envs[n] = [1])
+    fn right_environments(&self) -> Vec<Vec<Complex64>> {
+        let n = self.sites.len();
+        let mut envs = vec![vec![Complex64::new(1.0, 0.0)]; n + 1];
+        for i in (0..n).rev() {
+            envs[i] = transfer_right(&envs[i + 1], &self.sites[i]);
+        }
+        envs
+    }
+}
+
+// The first k columns of `u`, row-major
+fn columns(u: &CMatrix, k: usize) -> Vec<Complex64> {
+    (0..u.rows).flat_map(|i| u.data[i * u.cols..i * u.cols + k].iter().copied()).collect()
+}
+
+// L'[b'][k'] = Σ_s Σ_(b,k) L[b][k] conj(A[b][s][b']) A[k][s][k'], over s or only the given one
+fn transfer(env: &[Complex64], site: &Site, only: Option<usize>) -> Vec<Complex64> {
+    let (dl, dr) = (site.left, site.right);
+    let mut out = vec![ZERO; dr * dr];
+    for s in only.map_or(0..2, |s| s..s + 1) {
+        let mut tmp = vec![ZERO; dl * dr];
+        for b in 0..dl {
+            for k in 0..dl {
+                let e = env[b * dl + k];
+                if e == ZERO {
+                    continue;
+                }
+                for kp in 0..dr {
+                    tmp[b * dr + kp] += e * site.at(k, s, kp);
+                }
+            }
+        }
+        for b in 0..dl {
+            for bp in 0..dr {
+                let a = site.at(b, s, bp).conj();
+                if a == ZERO {
+                    continue;
+                }
+                for kp in 0..dr {
+                    out[bp * dr + kp] += a * tmp[b * dr + kp];
+                }
+            }
+        }
+    }
+    out
+}
+
+// R[k][b] = Σ_s Σ_(k',b') A[k][s][k'] R'[k'][b'] conj(A[b][s][b'])
+fn transfer_right(env: &[Complex64], site: &Site) -> Vec<Complex64> {
+    let (dl, dr) = (site.left, site.right);
+    let mut out = vec![ZERO; dl * dl];
+    for s in 0..2 {
+        let mut tmp = vec![ZERO; dl * dr];
+        for k in 0..dl {
+            for kp in 0..dr {
+                let a = site.at(k, s, kp);
+                if a == ZERO {
+                    continue;
+                }
+                for bp in 0..dr {
+                    tmp[k * dr + bp] += a * env[kp * dr + bp];
+                }
+            }
+        }
+        for k in 0..dl {
+            for b in 0..dl {
+                out[k * dl + b] += (0..dr).map(|bp| tmp[k * dr + bp] * site.at(b, s, bp).conj()).sum::<Complex64>();
+            }
+        }
+    }
+    out
+}
+
+// Σ_(b,k) L[b][k] R[k][b]
+fn close(left: &[Complex64], right: &[Complex64], dim: usize) -> f64 {
+    (0..dim).flat_map(|b| (0..dim).map(move |k| (b, k))).map(|(b, k)| left[b * dim + k] * right[k * dim + b]).sum::<Complex64>().re
+}
+
+impl Simulator for Mps {
+    fn num_qubits(&self) -> usize {
+        self.site_of.len()
+    }
+
+    fn apply(&mut self, gate: &Gate) {
+        let qubits: Vec<usize> = gate.qubits().collect();
+        for &q in &qubits {
+            self.check_qubit(q);
+        }
+        let (lo, hi) = self.gather(&qubits);
+        let bit = |q: usize| hi - self.site_of[q];
+        let control_mask = gate.controls.iter().fold(0usize, |mask, &c| mask | 1 << bit(c));
+        let matrix = gate.kind.matrix();
+        self.apply_block(lo, hi, &BlockOp::Matrix { target: bit(gate.target), control_mask, matrix: &matrix });
+    }
+
+    fn apply_permutation(&mut self, controls: &[usize], register: &[usize], f: &dyn Fn(u64) -> u64) {
+        let qubits: Vec<usize> = controls.iter().chain(register).copied().collect();
+        for &q in &qubits {
+            self.check_qubit(q);
+        }
+        let (lo, hi) = self.gather(&qubits);
+        let bit = |q: usize| hi - self.site_of[q];
+        let control_mask = controls.iter().fold(0usize, |mask, &c| mask | 1 << bit(c));
+        let register_bits: Vec<usize> = register.iter().map(|&q| bit(q)).collect();
+        let register_mask = register_bits.iter().fold(0usize, |mask, &b| mask | 1 << b);
+        let map = (0..1usize << (hi - lo + 1))
+            .map(|p| {
+                if p & control_mask != control_mask {
+                    return p;
+                }
+                let x = register_bits.iter().enumerate().fold(0u64, |x, (k, &b)| x | (((p >> b) & 1) as u64) << k);
+                let y = f(x);
+                register_bits.iter().enumerate().fold(p & !register_mask, |p, (k, &b)| p | (((y >> k) & 1) as usize) << b)
+            })
+            .collect();
+        self.apply_block(lo, hi, &BlockOp::Permutation(map));
+    }
+
+    // One trajectory, as on a state vector; local to the qubit's site once it is the center
+    fn apply_kraus(&mut self, qubit: usize, kraus: &[Matrix2], rng: &mut dyn RngCore) {
+        self.check_qubit(qubit);
+        let site = self.site_of[qubit];
+        self.move_center(site);
+        let tensor = &self.sites[site];
+        let (left, right) = (tensor.left, tensor.right);
+        let applied = |k: &Matrix2| -> Vec<Complex64> {
+            let mut out = tensor.data.clone();
+            for l in 0..left {
+                for r in 0..right {
+                    let (x, y) = (tensor.at(l, 0, r), tensor.at(l, 1, r));
+                    out[(l * 2) * right + r] = k[0][0] * x + k[0][1] * y;
+                    out[(l * 2 + 1) * right + r] = k[1][0] * x + k[1][1] * y;
+                }
+            }
+            out
+        };
+        let candidates: Vec<Vec<Complex64>> = kraus.iter().map(applied).collect();
+        let weights: Vec<f64> = candidates.iter().map(|c| c.iter().map(|x| x.norm_sqr()).sum()).collect();
+        let mut x = rng.r#gen::<f64>() * weights.iter().sum::<f64>();
+        let chosen = weights
+            .iter()
+            .position(|&w| {
+                x -= w;
+                x < 0.0
+            })
+            .unwrap_or_else(|| weights.iter().rposition(|&w| w > 0.0).unwrap_or(0));
+        let scale = 1.0 / weights[chosen].sqrt();
+        self.sites[site].data = candidates[chosen].iter().map(|x| x * scale).collect();
+    }
+
+    fn probability_one(&self, qubit: usize) -> f64 {
+        self.check_qubit(qubit);
+        self.contract(&[(self.site_of[qubit], 1)]) / self.contract(&[])
+    }
+
+    fn marginal_probabilities(&self, qubits: &[usize]) -> Vec<f64> {
+        let n = self.sites.len();
+        let mut wanted = vec![None; n];
+        for (k, &q) in qubits.iter().enumerate() {
+            self.check_qubit(q);
+            wanted[self.site_of[q]] = Some(k);
+        }
+        let mut marginal = vec![0.0; 1 << qubits.len()];
+        // Depth first over the outcomes, sharing the contraction of common prefixes
+        let mut stack = vec![(0usize, vec![Complex64::new(1.0, 0.0)], 0usize)];
+        while let Some((site, env, value)) = stack.pop() {
+            if site == n {
+                marginal[value] += env[0].re;
+                continue;
+            }
+            match wanted[site] {
+                Some(k) => {
+                    for s in 0..2 {
+                        let next = transfer(&env, &self.sites[site], Some(s));
+                        if next.iter().any(|x| x.norm_sqr() > 0.0) {
+                            stack.push((site + 1, next, value | s << k));
+                        }
+                    }
+                }
+                None => stack.push((site + 1, transfer(&env, &self.sites[site], None), value)),
+            }
+        }
+        let total: f64 = marginal.iter().sum();
+        marginal.iter().map(|p| p / total).collect()
+    }
+
+    fn measure(&mut self, qubit: usize, rng: &mut dyn RngCore) -> bool {
+        self.check_qubit(qubit);
+        let site = self.site_of[qubit];
+        self.move_center(site);
+        // With the center here, the site alone holds the probabilities
+        let tensor = &self.sites[site];
+        let weight = |s: usize| -> f64 {
+            (0..tensor.left).flat_map(|l| (0..tensor.right).map(move |r| (l, r))).map(|(l, r)| tensor.at(l, s, r).norm_sqr()).sum()
+        };
+        let (w0, w1) = (weight(0), weight(1));
+        let outcome = rng.r#gen::<f64>() * (w0 + w1) < w1;
+        let scale = 1.0 / if outcome { w1 } else { w0 }.sqrt();
+        let keep = outcome as usize;
+        let right = tensor.right;
+        for (i, x) in self.sites[site].data.iter_mut().enumerate() {
+            *x = if (i / right) % 2 == keep { *x * scale } else { ZERO };
+        }
+        outcome
+    }
+
+    // Qubit by qubit from the left, each conditioned on the ones drawn before
+    fn sample_qubits(&self, qubits: &[usize], shots: usize, rng: &mut dyn RngCore) -> Vec<u64> {
+        let mut wanted = vec![None; self.sites.len()];
+        for (k, &q) in qubits.iter().enumerate() {
+            self.check_qubit(q);
+            wanted[self.site_of[q]] = Some(k);
+        }
+        let rights = self.right_environments();
+        (0..shots)
+            .map(|_| {
+                let mut env = vec![Complex64::new(1.0, 0.0)];
+                let mut value = 0u64;
+                for (i, site) in self.sites.iter().enumerate() {
+                    let Some(k) = wanted[i] else {
+                        env = transfer(&env, site, None);
+                        continue;
+                    };
+                    let branches = [transfer(&env, site, Some(0)), transfer(&env, site, Some(1))];
+                    let p = branches.each_ref().map(|b| close(b, &rights[i + 1], site.right).max(0.0));
+                    let one = rng.r#gen::<f64>() * (p[0] + p[1]) < p[1];
+                    let chosen = one as usize;
+                    // Renormalize so long chains do not underflow
+                    env = branches[chosen].iter().map(|x| x / p[chosen]).collect();
+                    value |= (chosen as u64) << k;
+                }
+                value
+            })
+            .collect()
+    }
+
+    fn truncation_error(&self) -> f64 {
+        self.truncation_error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantum::testing::random_circuit;
+    use crate::quantum::StateVector;
+    use crate::seeded_rng;
+
+    // |<mps|ψ>|^2
+    fn overlap(mps: &Mps, state: &StateVector) -> f64 {
+        state.amplitudes().iter().enumerate().map(|(i, a)| mps.amplitude(i as u64).conj() * a).sum::<Complex64>().norm_sqr()
+    }
+
+    #[test]
+    fn matches_the_state_vector_at_full_bond() {
+        // 2^3 is the largest bond 6 qubits can need
+        for seed in 0..10 {
+            let mut rng = seeded_rng(seed);
+            let circuit = random_circuit(6, 80, &mut rng);
+            let mut exact = StateVector::new(6).unwrap();
+            let mut mps = Mps::new(6, 8).unwrap();
+            circuit.run(&mut exact, &mut rng);
+            circuit.run(&mut mps, &mut rng);
+
+            for (i, a) in exact.amplitudes().iter().enumerate() {
+                assert!((mps.amplitude(i as u64) - a).norm() < 1e-9, "seed {}: amplitude {} is {}, expected {}", seed, i, mps.amplitude(i as u64), a);
+            }
+            for (p, q) in mps.marginal_probabilities(&[4, 1, 3]).iter().zip(exact.marginal_probabilities(&[4, 1, 3])) {
+                assert!((p - q).abs() < 1e-9);
+            }
+            assert!(mps.truncation_error() < 1e-12, "seed {}: truncation error {} at full bond", seed, mps.truncation_error());
+        }
+    }
+
+    #[test]
+    fn truncating_a_ghz_state_drops_half_its_weight() {
+        let mut mps = Mps::new(4, 1).unwrap();
+        mps.apply(&Gate::h(0));
+        for q in 1..4 {
+            mps.apply(&Gate::cnot(q - 1, q));
+        }
+        assert!((mps.truncation_error() - 0.5).abs() < 1e-12, "truncation error {}", mps.truncation_error());
+        assert_eq!(mps.largest_bond(), 1);
+    }
+
+    #[test]
+    fn truncation_is_reported() {
+        for seed in 0..5 {
+            let mut rng = seeded_rng(seed);
+            let circuit = random_circuit(6, 80, &mut rng);
+            let mut exact = StateVector::new(6).unwrap();
+            let mut mps = Mps::new(6, 2).unwrap();
+            circuit.run(&mut exact, &mut rng);
+            circuit.run(&mut mps, &mut rng);
+            let error = mps.truncation_error();
+            assert!(error > 1e-6 && error < 1.0, "seed {}: truncation error {}", seed, error);
+            assert!(mps.largest_bond() <= 2);
+            assert!(overlap(&mps, &exact) < 1.0 - 1e-6, "seed {}: the capped state is exact", seed);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use super::{Gate, GateKind, Matrix2, Simulator};
+use num_complex::Complex64;
+use rand::{Rng, RngCore};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+// Gate name the noise of `ModMul` oracle instructions is looked up under
+pub const ORACLE_NOISE: &str = "modmul";
+
+// A single-qubit error channel, by its rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    // With probability p one of X, Y, Z (p/3 each)
+    Depolarizing(f64),
+    // |1> decays to |0> with probability γ (energy relaxation, T1)
+    AmplitudeDamping(f64),
+    // Coherences shrink by sqrt(1 - λ), populations stay (dephasing, T2)
+    PhaseDamping(f64),
+}
+
+impl Channel {
+    pub const NAMES: [&'static str; 3] = ["depolarizing", "amplitude-damping", "phase-damping"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Depolarizing(_) => "depolarizing",
+            Channel::AmplitudeDamping(_) => "amplitude-damping",
+            Channel::PhaseDamping(_) => "phase-damping",
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        match *self {
+            Channel::Depolarizing(p) | Channel::AmplitudeDamping(p) | Channel::PhaseDamping(p) => p,
+        }
+    }
+
+    // The same channel with its rate multiplied by `factor` (capped at 1)
+    pub fn scaled(&self, factor: f64) -> Channel {
+        let rate = (self.rate() * factor).clamp(0.0, 1.0);
+        match self {
+            Channel::Depolarizing(_) => Channel::Depolarizing(rate),
+            Channel::AmplitudeDamping(_) => Channel::AmplitudeDamping(rate),
+            Channel::PhaseDamping(_) => Channel::PhaseDamping(rate),
+        }
+    }
+
+    // Kraus operators K_i, Σ K_i† K_i = 1
+    pub fn kraus(&self) -> Vec<Matrix2> {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let zero = c(0.0);
+        match *self {
+            Channel::Depolarizing(p) => {
+                let pauli = (p / 3.0).sqrt();
+                let mut kraus = vec![[[c((1.0 - p).sqrt()), zero], [zero, c((1.0 - p).sqrt())]]];
+                for kind in [GateKind::X, GateKind::Y, GateKind::Z] {
+                    kraus.push(kind.matrix().map(|row| row.map(|e| e * pauli)));
+                }
+                kraus
+            }
+            Channel::AmplitudeDamping(gamma) => {
+                vec![[[c(1.0), zero], [zero, c((1.0 - gamma).sqrt())]], [[zero, c(gamma.sqrt())], [zero, zero]]]
+            }
+            Channel::PhaseDamping(lambda) => {
+                vec![[[c(1.0), zero], [zero, c((1.0 - lambda).sqrt())]], [[zero, zero], [zero, c(lambda.sqrt())]]]
+            }
+        }
+    }
+
+    fn from_name(name: &str, rate: f64) -> Option<Channel> {
+        match name {
+            "depolarizing" => Some(Channel::Depolarizing(rate)),
+            "amplitude-damping" => Some(Channel::AmplitudeDamping(rate)),
+            "phase-damping" => Some(Channel::PhaseDamping(rate)),
+            _ => None,
+        }
+    }
+}
+
+// Which errors follow which operations. After a gate, each of its channels acts
+// on every qubit the gate touched (controls included); after a measurement the
+// recorded bit flips with probability `readout`.
+//
+// Written and parsed as a comma-separated list of `[GATE:]CHANNEL=RATE` and
+// `readout=RATE`, e.g. "depolarizing=0.001,cx:depolarizing=0.01,readout=0.02".
+// Channels without a gate apply to every gate that has no entry of its own.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NoiseModel {
+    // Channels after gates not listed in `per_gate`
+    pub default: Vec<Channel>,
+    // Channels by gate name (`Gate::name`: "h", "cx", "cp", "ccx", ... or `ORACLE_NOISE`)
+    pub per_gate: BTreeMap<String, Vec<Channel>>,
+    // Probability that a measurement result is recorded flipped
+    pub readout: f64,
+}
+
+impl NoiseModel {
+    // No noise at all, what `Circuit::run` uses
+    pub fn ideal() -> Self {
+        NoiseModel::default()
+    }
+
+    // Depolarizing noise of rate p after every gate
+    pub fn depolarizing(p: f64) -> Self {
+        NoiseModel { default: vec![Channel::Depolarizing(p)], ..NoiseModel::default() }
+    }
+
+    pub fn is_ideal(&self) -> bool {
+        self.readout == 0.0 && self.default.iter().chain(self.per_gate.values().flatten()).all(|c| c.rate() == 0.0)
+    }
+
+    // Every rate multiplied by `factor`, for sweeping the noise strength
+    pub fn scaled(&self, factor: f64) -> Self {
+        let scale = |channels: &Vec<Channel>| channels.iter().map(|c| c.scaled(factor)).collect();
+        NoiseModel {
+            default: scale(&self.default),
+            per_gate: self.per_gate.iter().map(|(name, channels)| (name.clone(), scale(channels))).collect(),
+            readout: (self.readout * factor).clamp(0.0, 1.0),
+        }
+    }
+
+    // Channels that follow the gate called `name`
+    pub fn channels(&self, name: &str) -> &[Channel] {
+        self.per_gate.get(name).unwrap_or(&self.default)
+    }
+
+    // Apply the errors of the operation `name` that touched `qubits`
+    pub fn after(&self, name: &str, qubits: impl IntoIterator<Item = usize>, sim: &mut dyn Simulator, rng: &mut dyn RngCore) {
+        let channels = self.channels(name);
+        if channels.iter().all(|c| c.rate() == 0.0) {
+            return;
+        }
+        for q in qubits {
+            for channel in channels.iter().filter(|c| c.rate() > 0.0) {
+                sim.apply_kraus(q, &channel.kraus(), rng);
+            }
+        }
+    }
+
+    pub fn after_gate(&self, gate: &Gate, sim: &mut dyn Simulator, rng: &mut dyn RngCore) {
+        // Skip building the name when no gate has any channel
+        if self.default.is_empty() && self.per_gate.is_empty() {
+            return;
+        }
+        self.after(&gate.name(), gate.qubits(), sim, rng);
+    }
+
+    // The measured bit as recorded, flipped with probability `readout`
+    pub fn read(&self, outcome: bool, rng: &mut dyn RngCore) -> bool {
+        if self.readout > 0.0 && rng.r#gen::<f64>() < self.readout { !outcome } else { outcome }
+    }
+}
+
+impl fmt::Display for NoiseModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items: Vec<String> = self.default.iter().map(|c| format!("{}={}", c.name(), c.rate())).collect();
+        for (name, channels) in &self.per_gate {
+            items.extend(channels.iter().map(|c| format!("{}:{}={}", name, c.name(), c.rate())));
+        }
+        if self.readout > 0.0 {
+            items.push(format!("readout={}", self.readout));
+        }
+        if items.is_empty() {
+            return f.write_str("ideal");
+        }
+        f.write_str(&items.join(","))
+    }
+}
+
+impl FromStr for NoiseModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut model = NoiseModel::default();
+        if s.trim() == "ideal" {
+            return Ok(model);
+        }
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, rate) = item.split_once('=').ok_or_else(|| format!("Noise entry {} needs =RATE", item))?;
+            let rate: f64 = rate.trim().parse().map_err(|_| format!("Invalid noise rate in {}", item))?;
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("Noise rate {} is not a probability", rate));
+            }
+            if key == "readout" {
+                model.readout = rate;
+                continue;
+            }
+            let (gate, name) = match key.split_once(':') {
+                Some((gate, name)) => (Some(gate), name),
+                None => (None, key),
+            };
+            let channel = Channel::from_name(name, rate)
+                .ok_or_else(|| format!("Unknown noise channel {} (available: {}, readout)", name, Channel::NAMES.join(", ")))?;
+            match gate {
+                Some(gate) => model.per_gate.entry(gate.to_string()).or_default().push(channel),
+                None => model.default.push(channel),
+            }
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kraus_operators_are_complete() {
+        for rate in [0.0, 1e-3, 0.1, 0.5, 1.0] {
+            for channel in [Channel::Depolarizing(rate), Channel::AmplitudeDamping(rate), Channel::PhaseDamping(rate)] {
+                let mut sum = [[Complex64::new(0.0, 0.0); 2]; 2];
+                for k in channel.kraus() {
+                    for i in 0..2 {
+                        for j in 0..2 {
+                            sum[i][j] += (0..2).map(|l| k[l][i].conj() * k[l][j]).sum::<Complex64>();
+                        }
+                    }
+                }
+                for (i, row) in sum.iter().enumerate() {
+                    for (j, entry) in row.iter().enumerate() {
+                        let identity = if i == j { 1.0 } else { 0.0 };
+                        assert!((entry - identity).norm() < 1e-12, "{:?}: Σ K†K = {:?}", channel, sum);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spec_round_trips() {
+        for spec in ["ideal", "depolarizing=0.001", "depolarizing=0.001,cx:depolarizing=0.01,cx:phase-damping=0.5,readout=0.02", "modmul:amplitude-damping=1"] {
+            let model: NoiseModel = spec.parse().unwrap();
+            assert_eq!(model.to_string(), spec);
+            assert_eq!(model.to_string().parse::<NoiseModel>(), Ok(model));
+        }
+        let model: NoiseModel = " readout=0.1, h:depolarizing=0.2 ,amplitude-damping=0.3".parse().unwrap();
+        assert_eq!(model.default, [Channel::AmplitudeDamping(0.3)]);
+        assert_eq!(model.channels("h"), [Channel::Depolarizing(0.2)]);
+        assert_eq!(model.channels("cx"), [Channel::AmplitudeDamping(0.3)]);
+        assert_eq!(model.to_string().parse::<NoiseModel>(), Ok(model));
+        assert!("".parse::<NoiseModel>().unwrap().is_ideal());
+        assert!("depolarizing=0".parse::<NoiseModel>().unwrap().is_ideal());
+    }
+
+    #[test]
+    fn spec_rejects_bad_entries() {
+        for spec in ["depolarizing=1.5", "readout=-0.1", "depolarizing=NaN", "depolarizing=often", "depolarizing", "bit-flip=0.1", "cx:=0.1"] {
+            assert!(spec.parse::<NoiseModel>().is_err(), "{} parsed", spec);
+        }
+        assert_eq!("depolarizing=2".parse::<NoiseModel>(), Err("Noise rate 2 is not a probability".to_string()));
+    }
+
+    #[test]
+    fn scaling_caps_rates_at_one() {
+        let model: NoiseModel = "depolarizing=0.2,cx:phase-damping=0.5,readout=0.4".parse().unwrap();
+        assert_eq!(model.scaled(0.0).to_string(), "depolarizing=0,cx:phase-damping=0");
+        assert!(model.scaled(0.0).is_ideal());
+        let tripled = model.scaled(3.0);
+        assert_eq!((tripled.default[0].rate(), tripled.channels("cx")[0].rate(), tripled.readout), (0.2 * 3.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,427 @@
+use super::{Gate, GateKind, Matrix2, SimError, Simulator, MAX_QUBITS};
+use num_complex::Complex64;
+use rand::{Rng, RngCore};
+use std::thread;
+
+// Below this many amplitudes a gate is applied on the calling thread
+const PARALLEL_THRESHOLD: usize = 1 << 16;
+// Amplitude pairs handed to a worker thread at a time
+const PIECE: usize = 1 << 12;
+
+// A pure state of n qubits as 2^n complex amplitudes.
+// Qubit q is bit q of the basis index (qubit 0 is the least significant).
+// At 16 bytes per amplitude, 28 qubits take 4 GiB and 30 qubits 16 GiB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateVector {
+    num_qubits: usize,
+    amplitudes: Vec<Complex64>,
+}
+
+impl StateVector {
+    // |0...0> on `num_qubits` qubits
+    pub fn new(num_qubits: usize) -> Result<Self, SimError> {
+        Self::basis(num_qubits, 0)
+    }
+
+    // The computational basis state |index>
+    pub fn basis(num_qubits: usize, index: usize) -> Result<Self, SimError> {
+        if num_qubits > MAX_QUBITS {
+            return Err(SimError::TooManyQubits { requested: num_qubits, max: MAX_QUBITS });
+        }
+        let len = 1usize << num_qubits;
+        let mut amplitudes = Vec::new();
+        amplitudes
+            .try_reserve_exact(len)
+            .map_err(|_| SimError::OutOfMemory { qubits: num_qubits, bytes: Self::memory_bytes(num_qubits) })?;
+        amplitudes.resize(len, Complex64::new(0.0, 0.0));
+        amplitudes[index % len] = Complex64::new(1.0, 0.0);
+        Ok(StateVector { num_qubits, amplitudes })
+    }
+
+    // Any state, given its amplitudes (length must be a power of two)
+    pub fn from_amplitudes(amplitudes: Vec<Complex64>) -> Result<Self, SimError> {
+        if !amplitudes.len().is_power_of_two() {
+            return Err(SimError::InvalidState(format!("{} amplitudes is not a power of two", amplitudes.len())));
+        }
+        let num_qubits = amplitudes.len().trailing_zeros() as usize;
+        Ok(StateVector { num_qubits, amplitudes })
+    }
+
+    // Memory the amplitudes of `num_qubits` qubits take
+    pub fn memory_bytes(num_qubits: usize) -> u128 {
+        (std::mem::size_of::<Complex64>() as u128) << num_qubits
+    }
+
+    pub fn amplitudes(&self) -> &[Complex64] {
+        &self.amplitudes
+    }
+
+    // |amplitude|^2 of every basis state
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.amplitudes.iter().map(|a| a.norm_sqr()).collect()
+    }
+
+    pub fn norm_sqr(&self) -> f64 {
+        self.amplitudes.iter().map(|a| a.norm_sqr()).sum()
+    }
+
+    // Draw a basis state from the measurement distribution, without collapsing
+    pub fn sample(&self, rng: &mut dyn RngCore) -> usize {
+        let mut x: f64 = rng.r#gen::<f64>() * self.norm_sqr();
+        for (i, a) in self.amplitudes.iter().enumerate() {
+            x -= a.norm_sqr();
+            if x < 0.0 {
+                return i;
+            }
+        }
+        // Rounding left a sliver at the end, take the last non-zero state
+        self.amplitudes.iter().rposition(|a| a.norm_sqr() > 0.0).unwrap_or(0)
+    }
+
+    // Measure several qubits, bit k of the result is qubits[k]
+    pub fn measure_qubits(&mut self, qubits: &[usize], rng: &mut dyn RngCore) -> u64 {
+        qubits
+            .iter()
+            .enumerate()
+            .fold(0u64, |acc, (k, &q)| if self.measure(q, rng) { acc | (1 << k) } else { acc })
+    }
+
+    // Any 2x2 matrix (unitary or not) on `target`, where every qubit of `control_mask` is 1
+    pub(crate) fn apply_matrix(&mut self, target: usize, control_mask: usize, m: &Matrix2) {
+        for_each_pair(&mut self.amplitudes, target, |i, a0, a1| {
+            if i & control_mask == control_mask {
+                let (x, y) = (*a0, *a1);
+                *a0 = m[0][0] * x + m[0][1] * y;
+                *a1 = m[1][0] * x + m[1][1] * y;
+            }
+        });
+    }
+
+    pub(crate) fn amplitudes_mut(&mut self) -> &mut [Complex64] {
+        &mut self.amplitudes
+    }
+
+    // ||K ψ||^2 for K acting on `qubit`
+    fn kraus_weight(&self, qubit: usize, k: &Matrix2) -> f64 {
+        let bit = 1 << qubit;
+        (0..self.amplitudes.len())
+            .filter(|i| i & bit == 0)
+            .map(|i| {
+                let (x, y) = (self.amplitudes[i], self.amplitudes[i | bit]);
+                (k[0][0] * x + k[0][1] * y).norm_sqr() + (k[1][0] * x + k[1][1] * y).norm_sqr()
+            })
+            .sum()
+    }
+
+    fn check_qubit(&self, qubit: usize) {
+        assert!(qubit < self.num_qubits, "qubit {} out of range for {} qubits", qubit, self.num_qubits);
+    }
+}
+
+impl Simulator for StateVector {
+    fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    fn apply(&mut self, gate: &Gate) {
+        self.check_qubit(gate.target);
+        let mut control_mask = 0usize;
+        for &c in &gate.controls {
+            self.check_qubit(c);
+            assert!(c != gate.target, "qubit {} is both control and target", c);
+            control_mask |= 1 << c;
+        }
+        let m = gate.kind.matrix();
+        let one = Complex64::new(1.0, 0.0);
+        if gate.kind == GateKind::X {
+            for_each_pair(&mut self.amplitudes, gate.target, |i, a0, a1| {
+                if i & control_mask == control_mask {
+                    std::mem::swap(a0, a1);
+                }
+            });
+        } else if gate.kind.is_diagonal() && m[0][0] == one {
+            // Phase-type gates only touch the |1> half
+            for_each_pair(&mut self.amplitudes, gate.target, |i, _, a1| {
+                if i & control_mask == control_mask {
+                    *a1 *= m[1][1];
+                }
+            });
+        } else {
+            self.apply_matrix(gate.target, control_mask, &m);
+        }
+    }
+
+    fn apply_permutation(&mut self, controls: &[usize], register: &[usize], f: &dyn Fn(u64) -> u64) {
+        let control_mask = controls.iter().fold(0usize, |mask, &c| {
+            self.check_qubit(c);
+            mask | 1 << c
+        });
+        let register_mask = register.iter().fold(0usize, |mask, &q| {
+            self.check_qubit(q);
+            mask | 1 << q
+        });
+        // A register of consecutive qubits is read with one shift
+        let contiguous = register.windows(2).all(|w| w[1] == w[0] + 1);
+        let low = register.first().copied().unwrap_or(0);
+        let value_mask = (1u64 << register.len()) - 1;
+        let image = |i: usize| {
+            if contiguous {
+                (i & !register_mask) | (f((i >> low) as u64 & value_mask) as usize) << low
+            } else {
+                (i & !register_mask) | deposit_bits(f(extract_bits(i, register)), register)
+            }
+        };
+        // In place, one cycle at a time, with a bit per basis state (1/128 of the
+        // amplitudes) marking the ones already moved. A cycle is walked from its first
+        // non-zero amplitude, so the empty ones that fill oracle registers stay put.
+        let zero = Complex64::new(0.0, 0.0);
+        let mut moved = vec![0u64; self.amplitudes.len().div_ceil(64)];
+        for start in 0..self.amplitudes.len() {
+            if start & control_mask != control_mask || moved[start / 64] >> (start % 64) & 1 == 1 || self.amplitudes[start] == zero {
+                continue;
+            }
+            let mut carried = self.amplitudes[start];
+            let mut i = image(start);
+            while i != start {
+                assert!(moved[i / 64] >> (i % 64) & 1 == 0, "apply_permutation needs a bijection");
+                moved[i / 64] |= 1 << (i % 64);
+                std::mem::swap(&mut carried, &mut self.amplitudes[i]);
+                i = image(i);
+            }
+            self.amplitudes[start] = carried;
+        }
+    }
+
+    fn marginal_probabilities(&self, qubits: &[usize]) -> Vec<f64> {
+        let mut marginal = vec![0.0; 1 << qubits.len()];
+        for (i, a) in self.amplitudes.iter().enumerate() {
+            marginal[extract_bits(i, qubits) as usize] += a.norm_sqr();
+        }
+        marginal
+    }
+
+    fn apply_kraus(&mut self, qubit: usize, kraus: &[Matrix2], rng: &mut dyn RngCore) {
+        self.check_qubit(qubit);
+        let weights: Vec<f64> = kraus.iter().map(|k| self.kraus_weight(qubit, k)).collect();
+        let mut x = rng.r#gen::<f64>() * weights.iter().sum::<f64>();
+        let chosen = weights
+            .iter()
+            .position(|&w| {
+                x -= w;
+                x < 0.0
+            })
+            .unwrap_or_else(|| weights.iter().rposition(|&w| w > 0.0).unwrap_or(0));
+        // K_i ψ / ||K_i ψ||, the norm folded into the matrix
+        let scale = 1.0 / weights[chosen].sqrt();
+        self.apply_matrix(qubit, 0, &kraus[chosen].map(|row| row.map(|e| e * scale)));
+    }
+
+    fn probability_one(&self, qubit: usize) -> f64 {
+        self.check_qubit(qubit);
+        let bit = 1 << qubit;
+        let one: f64 = self.amplitudes.iter().enumerate().filter(|(i, _)| i & bit != 0).map(|(_, a)| a.norm_sqr()).sum();
+        one / self.norm_sqr()
+    }
+
+    fn measure(&mut self, qubit: usize, rng: &mut dyn RngCore) -> bool {
+        let p1 = self.probability_one(qubit);
+        let outcome = rng.r#gen::<f64>() < p1;
+        // Collapse: drop the other branch and renormalize
+        let scale = 1.0 / if outcome { p1 } else { 1.0 - p1 }.sqrt();
+        let zero = Complex64::new(0.0, 0.0);
+        for_each_pair(&mut self.amplitudes, qubit, |_, a0, a1| {
+            if outcome {
+                *a0 = zero;
+                *a1 *= scale;
+            } else {
+                *a0 *= scale;
+                *a1 = zero;
+            }
+        });
+        outcome
+    }
+}
+
+// The value held by `qubits` in basis state i, bit k = qubits[k]
+pub(super) fn extract_bits(i: usize, qubits: &[usize]) -> u64 {
+    qubits.iter().enumerate().fold(0, |acc, (k, &q)| acc | (((i >> q) & 1) as u64) << k)
+}
+
+// Inverse of `extract_bits`: spread the bits of `value` onto `qubits`
+fn deposit_bits(value: u64, qubits: &[usize]) -> usize {
+    qubits.iter().enumerate().fold(0, |acc, (k, &q)| acc | (((value >> k) & 1) as usize) << q)
+}
+
+// Call f(i, &mut amp[i], &mut amp[i | 1 << target]) for every i with the target bit clear,
+// spread over all cores when the state is large
+fn for_each_pair<F>(amplitudes: &mut [Complex64], target: usize, f: F)
+where
+    F: Fn(usize, &mut Complex64, &mut Complex64) + Sync,
+{
+    let half = 1usize << target;
+    // Asking for the core count costs a few system calls, small states skip it
+    let threads = if amplitudes.len() < PARALLEL_THRESHOLD { 1 } else { thread::available_parallelism().map_or(1, |n| n.get()) };
+    if threads == 1 {
+        for (block, chunk) in amplitudes.chunks_mut(2 * half).enumerate() {
+            let (lo, hi) = chunk.split_at_mut(half);
+            let base = block * 2 * half;
+            for (j, (a0, a1)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+                f(base + j, a0, a1);
+            }
+        }
+        return;
+    }
+
+    // Cut every (lower half, upper half) block into pieces the threads share out
+    let mut pieces = Vec::with_capacity(amplitudes.len() / 2 / PIECE.min(half) + 1);
+    for (block, chunk) in amplitudes.chunks_mut(2 * half).enumerate() {
+        let (lo, hi) = chunk.split_at_mut(half);
+        let base = block * 2 * half;
+        for (k, (lo, hi)) in lo.chunks_mut(PIECE).zip(hi.chunks_mut(PIECE)).enumerate() {
+            pieces.push((base + k * PIECE, lo, hi));
+        }
+    }
+    let per_thread = pieces.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|scope| {
+        let mut pieces = pieces.into_iter();
+        for _ in 0..threads {
+            let batch: Vec<_> = pieces.by_ref().take(per_thread).collect();
+            scope.spawn(move || {
+                for (start, lo, hi) in batch {
+                    for (j, (a0, a1)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+                        f(start + j, a0, a1);
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    fn c(re: f64, im: f64) -> Complex64 {
+        Complex64::new(re, im)
+    }
+
+    fn assert_amplitudes(state: &StateVector, expected: &[Complex64]) {
+        assert_eq!(state.amplitudes().len(), expected.len());
+        for (i, (a, b)) in state.amplitudes().iter().zip(expected).enumerate() {
+            assert!((a - b).norm() < 1e-12, "amplitude {}: {} != {}", i, a, b);
+        }
+    }
+
+    fn from_reals(values: &[f64]) -> StateVector {
+        StateVector::from_amplitudes(values.iter().map(|&x| c(x, 0.0)).collect()).unwrap()
+    }
+
+    #[test]
+    fn single_qubit_gates() {
+        let mut state = StateVector::new(2).unwrap();
+        state.apply(&Gate::x(1));
+        assert_amplitudes(&state, &[c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0), c(0.0, 0.0)]);
+
+        let mut state = StateVector::new(1).unwrap();
+        state.apply(&Gate::h(0));
+        assert_amplitudes(&state, &[c(FRAC_1_SQRT_2, 0.0), c(FRAC_1_SQRT_2, 0.0)]);
+        state.apply(&Gate::z(0));
+        assert_amplitudes(&state, &[c(FRAC_1_SQRT_2, 0.0), c(-FRAC_1_SQRT_2, 0.0)]);
+        state.apply(&Gate::h(0));
+        assert_amplitudes(&state, &[c(0.0, 0.0), c(1.0, 0.0)]);
+        state.apply(&Gate::s(0));
+        assert_amplitudes(&state, &[c(0.0, 0.0), c(0.0, 1.0)]);
+        state.apply(&Gate::t(0));
+        assert_amplitudes(&state, &[c(0.0, 0.0), c(-FRAC_1_SQRT_2, FRAC_1_SQRT_2)]);
+    }
+
+    #[test]
+    fn cnot_and_cphase() {
+        // Control |0>: nothing happens
+        let mut state = StateVector::new(2).unwrap();
+        state.apply(&Gate::cnot(0, 1));
+        assert_amplitudes(&state, &[c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)]);
+        // Control |1>: the target flips, |01> -> |11>
+        state.apply(&Gate::x(0));
+        state.apply(&Gate::cnot(0, 1));
+        assert_amplitudes(&state, &[c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)]);
+
+        // Only |11> picks up the phase
+        let theta = 0.3;
+        let mut state = StateVector::new(2).unwrap();
+        state.apply(&Gate::h(0));
+        state.apply(&Gate::h(1));
+        state.apply(&Gate::cphase(0, 1, theta));
+        let phase = Complex64::from_polar(0.5, theta);
+        assert_amplitudes(&state, &[c(0.5, 0.0), c(0.5, 0.0), c(0.5, 0.0), phase]);
+    }
+
+    #[test]
+    fn measurement_collapses_and_renormalizes() {
+        for seed in 0..8 {
+            let mut rng = seeded_rng(seed);
+            // Bell state: both qubits give the same outcome
+            let mut state = StateVector::new(2).unwrap();
+            state.apply(&Gate::h(0));
+            state.apply(&Gate::cnot(0, 1));
+            let first = state.measure(0, &mut rng);
+            let expected = if first { [c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)] } else { [c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)] };
+            assert_amplitudes(&state, &expected);
+            assert_eq!(state.measure(1, &mut rng), first);
+
+            // 0.1 |00> + 0.2 |01> + 0.3 |10> + 0.4 |11> (weights), measure qubit 1
+            let mut state = from_reals(&[0.1f64.sqrt(), 0.2f64.sqrt(), 0.3f64.sqrt(), 0.4f64.sqrt()]);
+            let expected = if state.measure(1, &mut rng) {
+                [0.0, 0.0, (0.3f64 / 0.7).sqrt(), (0.4f64 / 0.7).sqrt()]
+            } else {
+                [(0.1f64 / 0.3).sqrt(), (0.2f64 / 0.3).sqrt(), 0.0, 0.0]
+            };
+            assert_amplitudes(&state, &expected.map(|x| c(x, 0.0)));
+            assert!((state.norm_sqr() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn measurement_follows_the_probabilities() {
+        let mut rng = seeded_rng(7);
+        let ones = (0..2000)
+            .filter(|_| {
+                let mut state = from_reals(&[0.8f64.sqrt(), 0.2f64.sqrt()]);
+                state.measure(0, &mut rng)
+            })
+            .count();
+        assert!((300..500).contains(&ones), "{} ones in 2000 for p = 0.2", ones);
+    }
+
+    #[test]
+    fn permutation_on_a_controlled_scattered_register() {
+        // Qubits 0 and 2 hold v = q0 + 2 q2, v -> v + 1 mod 4 where qubit 1 is set:
+        // |2> -> |3>, |3> -> |6>, |6> -> |7>, |7> -> |2>
+        let mut state = from_reals(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        state.apply_permutation(&[1], &[0, 2], &|v| (v + 1) % 4);
+        assert_amplitudes(&state, &[1.0, 2.0, 8.0, 3.0, 5.0, 6.0, 4.0, 7.0].map(|x| c(x, 0.0)));
+
+        // The same cycle, starting on an empty amplitude
+        let mut state = from_reals(&[1.0, 2.0, 0.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        state.apply_permutation(&[1], &[0, 2], &|v| (v + 1) % 4);
+        assert_amplitudes(&state, &[1.0, 2.0, 8.0, 0.0, 5.0, 6.0, 4.0, 7.0].map(|x| c(x, 0.0)));
+    }
+
+    #[test]
+    fn permutation_on_a_contiguous_register() {
+        // Qubits 1 and 2 hold v, v -> 3 v mod 4 swaps 1 and 3: |2>, |3> <-> |6>, |7>
+        let mut state = from_reals(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        state.apply_permutation(&[], &[1, 2], &|v| 3 * v % 4);
+        assert_amplitudes(&state, &[1.0, 2.0, 7.0, 8.0, 5.0, 6.0, 3.0, 4.0].map(|x| c(x, 0.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "bijection")]
+    fn permutation_rejects_a_non_bijection() {
+        let mut state = from_reals(&[1.0, 2.0, 3.0, 4.0]);
+        state.apply_permutation(&[], &[0, 1], &|v| v / 2);
+    }
+}
@@ -0,0 +1,552 @@
+// Logical resource estimates for Shor's algorithm at sizes no simulator reaches,
+// such as RSA-2048: logical qubits, Toffoli and T counts, depth and measurement
+// depth for each way of building the modular exponentiation, from the costs of
+// its parts, next to published figures.
+//
+// Costs are counted in an error-corrected gate set where Clifford gates are free:
+// Toffolis (AND gates), and arbitrary-angle Z rotations, each synthesized from
+// about 3 log2(1/ε) T gates (Ross–Selinger). Controlled phases are phase
+// polynomials, 3 rotations for a cp and 7 for a ccp. Depth counts the layers of
+// non-Clifford operations that have to run one after another, measurement depth
+// the rounds of measurements that have to wait for the previous round's result
+// (one per Toffoli layer, one per T gate of a rotation), which is what bounds the
+// runtime once the circuit is error corrected.
+//
+// Every construction uses the semi-classical QFT: the exponent bits go through
+// one control (or one window of controls) and are measured in turn, each followed
+// by one classically controlled rotation.
+
+use std::fmt;
+use std::str::FromStr;
+
+mod surface_code;
+
+pub use surface_code::{physical_report, FactoryDesign, PhysicalEstimate, PhysicalReport, SurfaceCode, SurfaceCodeError, MAX_DISTANCE};
+
+// Default window sizes of the windowed constructions, as Gidney & Ekerå pick for RSA-2048
+pub const DEFAULT_WINDOW: u64 = 5;
+// Default distance between oblivious carry runways, in bits
+pub const DEFAULT_RUNWAY_SPACING: u64 = 1024;
+// Default probability that the approximations (rotation synthesis, coset
+// deviation) spoil a run, split evenly between them
+pub const DEFAULT_ERROR_BUDGET: f64 = 0.01;
+
+// How the controlled modular multiplications are built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Construction {
+    // Beauregard's 2n + 3 qubit circuit, counted gate by gate as `arithmetic::Beauregard`
+    // builds it: Draper adders in Fourier space, so nearly all of its cost is rotations
+    Beauregard,
+    // Windowed arithmetic (Gidney 2019): a table lookup of k x_w a^e_w mod N, addressed by
+    // a window of exponent bits and a window of multiplier bits, replaces the controlled
+    // additions; each lookup feeds one modular addition of ripple-carry adders
+    Windowed,
+    // The same windows on registers in coset representation (Zalka 2006, Gidney 2019) with
+    // oblivious carry runways, as in Gidney & Ekerå 2019: modular additions become plain
+    // additions on n + padding bits, and the runways cut the carry chain into pieces
+    // that ripple in parallel
+    Coset,
+}
+
+impl Construction {
+    pub const ALL: [Construction; 3] = [Construction::Beauregard, Construction::Windowed, Construction::Coset];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Construction::Beauregard => "beauregard",
+            Construction::Windowed => "windowed",
+            Construction::Coset => "coset",
+        }
+    }
+
+    // Logical costs of factoring a `bits`-bit modulus this way
+    pub fn estimate(&self, bits: u64, assumptions: &Assumptions) -> LogicalEstimate {
+        let parameters = assumptions.parameters(bits);
+        match self {
+            Construction::Beauregard => beauregard(bits, &parameters),
+            Construction::Windowed => windowed(bits, &parameters, false),
+            Construction::Coset => windowed(bits, &parameters, true),
+        }
+    }
+}
+
+impl fmt::Display for Construction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Construction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Construction::ALL.iter().copied().find(|c| c.name() == s).ok_or_else(|| {
+            let names: Vec<_> = Construction::ALL.iter().map(|c| c.name()).collect();
+            format!("Unknown construction {} (available: {})", s, names.join(", "))
+        })
+    }
+}
+
+// The free parameters of the estimates. None picks the default for the bit length n.
+//
+// Written and parsed as a comma-separated list of KEY=VALUE, e.g.
+// "exponent-bits=3072,exp-window=4,aqft-cutoff=exact".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assumptions {
+    // Bits of the exponent (None = 2n, the textbook phase register; Ekerå–Håstad
+    // needs only about 1.5n for RSA moduli)
+    pub exponent_bits: Option<u64>,
+    // Largest qubit distance that keeps its rotation in Beauregard's QFTs
+    // (None = ⌈log2 n⌉, Coppersmith's approximate QFT; u64::MAX = exact)
+    pub aqft_cutoff: Option<u64>,
+    // Exponent bits and multiplier bits per table lookup
+    pub exp_window: u64,
+    pub mul_window: u64,
+    // Bits between oblivious carry runways in coset registers
+    pub runway_spacing: u64,
+    // Extra bits of the coset representation and of each runway
+    // (None = enough to keep their deviation within the error budget)
+    pub coset_padding: Option<u64>,
+    pub error_budget: f64,
+}
+
+impl Default for Assumptions {
+    fn default() -> Self {
+        Assumptions {
+            exponent_bits: None,
+            aqft_cutoff: None,
+            exp_window: DEFAULT_WINDOW,
+            mul_window: DEFAULT_WINDOW,
+            runway_spacing: DEFAULT_RUNWAY_SPACING,
+            coset_padding: None,
+            error_budget: DEFAULT_ERROR_BUDGET,
+        }
+    }
+}
+
+impl Assumptions {
+    // The value of every parameter for a `bits`-bit modulus, defaults decided
+    fn parameters(&self, bits: u64) -> Parameters {
+        let mut parameters = Parameters {
+            exponent_bits: self.exponent_bits.unwrap_or(2 * bits),
+            aqft_cutoff: self.aqft_cutoff.unwrap_or_else(|| ceil_log2(bits).max(1)),
+            exp_window: self.exp_window,
+            mul_window: self.mul_window,
+            runway_spacing: self.runway_spacing,
+            coset_padding: 0,
+            error_budget: self.error_budget,
+        };
+        parameters.coset_padding = self.coset_padding.unwrap_or_else(|| {
+            // Every addition deviates with probability 2^-padding at the top and at each runway
+            let deviations = lookup_additions(bits, &parameters) * segments(bits, &parameters) as f64;
+            (deviations / (self.error_budget / 2.0)).log2().ceil().max(1.0) as u64
+        });
+        parameters
+    }
+
+    // The same assumptions with every default filled in for a `bits`-bit modulus
+    pub fn resolved(&self, bits: u64) -> Assumptions {
+        let parameters = self.parameters(bits);
+        Assumptions {
+            exponent_bits: Some(parameters.exponent_bits),
+            aqft_cutoff: Some(parameters.aqft_cutoff),
+            coset_padding: Some(parameters.coset_padding),
+            ..self.clone()
+        }
+    }
+}
+
+// `Assumptions` for one bit length, nothing left to a default
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Parameters {
+    exponent_bits: u64,
+    aqft_cutoff: u64,
+    exp_window: u64,
+    mul_window: u64,
+    runway_spacing: u64,
+    coset_padding: u64,
+    error_budget: f64,
+}
+
+impl fmt::Display for Assumptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items = Vec::new();
+        if let Some(bits) = self.exponent_bits {
+            items.push(format!("exponent-bits={}", bits));
+        }
+        match self.aqft_cutoff {
+            Some(u64::MAX) => items.push("aqft-cutoff=exact".to_string()),
+            Some(cutoff) => items.push(format!("aqft-cutoff={}", cutoff)),
+            None => {}
+        }
+        items.push(format!("exp-window={}", self.exp_window));
+        items.push(format!("mul-window={}", self.mul_window));
+        items.push(format!("runway-spacing={}", self.runway_spacing));
+        if let Some(padding) = self.coset_padding {
+            items.push(format!("coset-padding={}", padding));
+        }
+        items.push(format!("error-budget={}", self.error_budget));
+        f.write_str(&items.join(","))
+    }
+}
+
+impl FromStr for Assumptions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const KEYS: &str = "exponent-bits, aqft-cutoff, exp-window, mul-window, runway-spacing, coset-padding, error-budget";
+        let mut assumptions = Assumptions::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, value) = item.split_once('=').ok_or_else(|| format!("Assumption {} needs =VALUE", item))?;
+            let (key, value) = (key.trim(), value.trim());
+            let count = |min: u64, max: u64| {
+                value.parse::<u64>().ok().filter(|v| (min..=max).contains(v)).ok_or_else(|| format!("Invalid value in {} (from {} to {})", item, min, max))
+            };
+            match key {
+                "exponent-bits" => assumptions.exponent_bits = Some(count(1, u64::MAX)?),
+                "aqft-cutoff" if value == "exact" => assumptions.aqft_cutoff = Some(u64::MAX),
+                "aqft-cutoff" => assumptions.aqft_cutoff = Some(count(0, u64::MAX)?),
+                // 2^(exp + mul) table entries have to stay countable
+                "exp-window" => assumptions.exp_window = count(1, 30)?,
+                "mul-window" => assumptions.mul_window = count(1, 30)?,
+                "runway-spacing" => assumptions.runway_spacing = count(1, u64::MAX)?,
+                "coset-padding" => assumptions.coset_padding = Some(count(1, 1024)?),
+                "error-budget" => {
+                    let budget: f64 = value.parse().map_err(|_| format!("Invalid error budget in {}", item))?;
+                    if !(budget > 0.0 && budget < 1.0) {
+                        return Err(format!("Error budget {} is not between 0 and 1", budget));
+                    }
+                    assumptions.error_budget = budget;
+                }
+                _ => return Err(format!("Unknown assumption {} (available: {})", key, KEYS)),
+            }
+        }
+        Ok(assumptions)
+    }
+}
+
+// What factoring one modulus costs with one construction
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalEstimate {
+    pub construction: Construction,
+    pub bits: u64,
+    pub logical_qubits: u64,
+    pub toffolis: f64,
+    // Arbitrary-angle Z rotations, and the T gates each one is synthesized from
+    pub rotations: f64,
+    pub t_per_rotation: f64,
+    // Layers of non-Clifford operations, and rounds of dependent measurements
+    pub depth: f64,
+    pub measurement_depth: f64,
+}
+
+impl LogicalEstimate {
+    // T gates of the rotations, the Toffolis not included
+    pub fn rotation_t_count(&self) -> f64 {
+        self.rotations * self.t_per_rotation
+    }
+
+    // Every T gate, with 7 per Toffoli
+    pub fn t_count(&self) -> f64 {
+        7.0 * self.toffolis + self.rotation_t_count()
+    }
+
+    // Toffolis + T/2, the unit magic-state factories are measured in and the one
+    // published estimates compare in
+    pub fn toffoli_equivalent(&self) -> f64 {
+        self.toffolis + self.rotation_t_count() / 2.0
+    }
+}
+
+// Beauregard's circuit, with m = n + 1 bits in the Fourier-space register b:
+//   φADD(a)MOD(N)  3 doubly controlled φADD(a), φADD(-N), φADD(N) controlled by the
+//                  ancilla, and 4 QFTs on b. A φADD has a rotation per bit, except for
+//                  the trailing zeros of its constant: m - 1 on average for a, m for odd N.
+//   CMULT(a)MOD(N) a QFT, n φADD(a 2^i)MOD(N), an inverse QFT
+//   U_a            CMULT(a), n controlled swaps (3 Toffolis each), CMULT(a^-1)^-1
+// The φADDs of one adder share their controls, so their rotations run one after another.
+fn beauregard(bits: u64, parameters: &Parameters) -> LogicalEstimate {
+    let n = bits as f64;
+    let m = n + 1.0;
+    let exponent_bits = parameters.exponent_bits as f64;
+    let cutoff = parameters.aqft_cutoff.min(bits) as f64;
+    // Controlled rotations of a QFT on m qubits: min(j, cutoff) of them on qubit j
+    let qft_rotations = cutoff * (cutoff + 1.0) / 2.0 + (m - 1.0 - cutoff) * cutoff;
+
+    let adder_rotations = 3.0 * 7.0 * (m - 1.0) + m + 3.0 * m + 4.0 * 3.0 * qft_rotations;
+    let adder_layers = 3.0 * (m - 1.0) + 1.0 + m + 4.0 * qft_layers(m);
+    let multiplier_rotations = 2.0 * 3.0 * qft_rotations + n * adder_rotations;
+    let multiplier_layers = 2.0 * qft_layers(m) + n * adder_layers;
+
+    let rotations = exponent_bits * (2.0 * multiplier_rotations + 1.0);
+    let toffolis = exponent_bits * 3.0 * n;
+    let t_per_rotation = synthesis_t_count(rotations, parameters.error_budget / 2.0);
+    let rotation_layers = exponent_bits * (2.0 * multiplier_layers + 1.0);
+    LogicalEstimate {
+        construction: Construction::Beauregard,
+        bits,
+        logical_qubits: 2 * bits + 3,
+        toffolis,
+        rotations,
+        t_per_rotation,
+        depth: rotation_layers + toffolis,
+        measurement_depth: rotation_layers * t_per_rotation + toffolis,
+    }
+}
+
+// Rotation layers of a QFT on m qubits: the rotations onto qubit j wait for the
+// Hadamard on j, which waits for the rotations from j onto the qubits above it
+fn qft_layers(m: f64) -> f64 {
+    2.0 * m - 3.0
+}
+
+// Windowed modular exponentiation. Each window of exponent bits multiplies x by a
+// constant in place, y += x k and then x -= y k^-1 after a swap, and each of those
+// is a table lookup and an addition per window of multiplier bits. A lookup of L
+// entries costs L Toffolis by unary iteration, its measurement-based uncomputation
+// about 2√L. A plain modular addition is four ripple-carry passes (add, compare with
+// N, subtract N under the comparison, compare again to clear it), 2 Toffolis a bit
+// each; in coset representation it is one pass over the padded register, and the
+// runways let the pieces between them ripple at once.
+fn windowed(bits: u64, parameters: &Parameters, coset: bool) -> LogicalEstimate {
+    let n = bits as f64;
+    let additions = lookup_additions(bits, parameters);
+    let entries = 2f64.powi((parameters.exp_window + parameters.mul_window) as i32);
+    let lookup = entries + 2.0 * entries.sqrt();
+
+    let (logical_qubits, adder_toffolis, adder_layers) = if coset {
+        let padding = parameters.coset_padding;
+        let width = bits + segments(bits, parameters) * padding;
+        // x and y padded, the lookup output, the exponent window and the carry
+        let qubits = 2 * width + bits + parameters.exp_window + 1;
+        (qubits, 2.0 * width as f64, 2.0 * (parameters.runway_spacing.min(bits) + padding) as f64)
+    } else {
+        // ... plus the comparison flag
+        (3 * bits + parameters.exp_window + 2, 8.0 * n, 8.0 * n)
+    };
+
+    let rotations = parameters.exponent_bits as f64;
+    let toffolis = additions * (lookup + adder_toffolis);
+    let toffoli_layers = additions * (lookup + adder_layers);
+    let t_per_rotation = synthesis_t_count(rotations, parameters.error_budget / 2.0);
+    LogicalEstimate {
+        construction: if coset { Construction::Coset } else { Construction::Windowed },
+        bits,
+        logical_qubits,
+        toffolis,
+        rotations,
+        t_per_rotation,
+        depth: toffoli_layers + rotations,
+        measurement_depth: toffoli_layers + rotations * t_per_rotation,
+    }
+}
+
+// Lookup-additions of the windowed constructions: two per exponent window and multiplier window
+fn lookup_additions(bits: u64, parameters: &Parameters) -> f64 {
+    2.0 * parameters.exponent_bits.div_ceil(parameters.exp_window) as f64 * bits.div_ceil(parameters.mul_window) as f64
+}
+
+// Pieces the runways cut an n-bit register into, each padded
+fn segments(bits: u64, parameters: &Parameters) -> u64 {
+    bits.div_ceil(parameters.runway_spacing)
+}
+
+// T gates per rotation when `rotations` of them share the synthesis error `budget`
+fn synthesis_t_count(rotations: f64, budget: f64) -> f64 {
+    (3.0 * (rotations / budget).log2()).ceil()
+}
+
+fn ceil_log2(x: u64) -> u64 {
+    (u64::BITS - x.saturating_sub(1).leading_zeros()) as u64
+}
+
+// A published estimate, as formulas in the bit length n
+#[derive(Debug, Clone, Copy)]
+pub struct PublishedFigure {
+    pub source: &'static str,
+    // The construction here it corresponds to, if any
+    pub construction: Option<Construction>,
+    pub logical_qubits: fn(f64) -> f64,
+    pub toffoli_equivalent: fn(f64) -> f64,
+    pub measurement_depth: Option<fn(f64) -> f64>,
+    // The formulas as published: qubits; Toffoli + T/2; measurement depth
+    pub formulas: &'static str,
+}
+
+// Leading terms as published; they assume their own exponent lengths (Gidney &
+// Ekerå 1.5n), so exponent-bits=1.5n compares like with like.
+pub const PUBLISHED: [PublishedFigure; 3] = [
+    PublishedFigure {
+        source: "Beauregard 2003 (as tabulated by Gidney & Ekerå 2019)",
+        construction: Some(Construction::Beauregard),
+        logical_qubits: |n| 2.0 * n + 3.0,
+        toffoli_equivalent: |n| 8.0 * n.powi(4),
+        measurement_depth: Some(|n| 8.0 * n.powi(4)),
+        formulas: "2n + 3; 8n^4; 8n^4",
+    },
+    PublishedFigure {
+        source: "Häner, Roetteler & Svore 2017",
+        construction: None,
+        logical_qubits: |n| 2.0 * n + 2.0,
+        toffoli_equivalent: |n| 64.0 * n.powi(3) * n.log2(),
+        measurement_depth: None,
+        formulas: "2n + 2; 64n^3 lg n Toffolis",
+    },
+    PublishedFigure {
+        source: "Gidney & Ekerå 2019",
+        construction: Some(Construction::Coset),
+        logical_qubits: |n| 3.0 * n + 0.002 * n * n.log2(),
+        toffoli_equivalent: |n| 0.3 * n.powi(3) + 0.0005 * n.powi(3) * n.log2(),
+        measurement_depth: Some(|n| 500.0 * n * n + n * n * n.log2()),
+        formulas: "3n + 0.002n lg n; 0.3n^3 + 0.0005n^3 lg n; 500n^2 + n^2 lg n",
+    },
+];
+
+// Every construction for one bit length, with the published figures to compare against
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceReport {
+    pub bits: u64,
+    // The assumptions with their defaults filled in
+    pub assumptions: Assumptions,
+    pub estimates: Vec<LogicalEstimate>,
+}
+
+pub fn estimate_resources(bits: u64, assumptions: &Assumptions) -> ResourceReport {
+    let estimates = Construction::ALL.iter().map(|c| c.estimate(bits, assumptions)).collect();
+    ResourceReport { bits, assumptions: assumptions.resolved(bits), estimates }
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.bits as f64;
+        writeln!(f, "Logical resources for Shor's algorithm on a {}-bit modulus", self.bits)?;
+        writeln!(f, "Assumptions: {}", self.assumptions)?;
+        writeln!(
+            f,
+            "{:<12}  {:>9}  {:>10}  {:>10}  {:>6}  {:>10}  {:>11}  {:>10}  {:>10}",
+            "construction", "qubits", "Toffolis", "rotations", "T/rot", "T", "Toffoli+T/2", "depth", "meas depth"
+        )?;
+        for e in &self.estimates {
+            writeln!(
+                f,
+                "{:<12}  {:>9}  {:>10.3e}  {:>10.3e}  {:>6}  {:>10.3e}  {:>11.3e}  {:>10.3e}  {:>10.3e}",
+                e.construction.name(),
+                e.logical_qubits,
+                e.toffolis,
+                e.rotations,
+                e.t_per_rotation,
+                e.t_count(),
+                e.toffoli_equivalent(),
+                e.depth,
+                e.measurement_depth,
+            )?;
+        }
+        writeln!(f, "\nPublished figures at n = {} (ratio = this estimate / published):", self.bits)?;
+        for p in &PUBLISHED {
+            let ours = p.construction.and_then(|c| self.estimates.iter().find(|e| e.construction == c));
+            let ratio = |ours: f64, published: f64| format!("{:.2}", ours / published);
+            writeln!(f, "  {} ({})", p.source, p.formulas)?;
+            let depth = p.measurement_depth.map(|d| format!("{:.3e}", d(n))).unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "    {:>9.0} qubits, {:.3e} Toffoli+T/2, measurement depth {}",
+                (p.logical_qubits)(n),
+                (p.toffoli_equivalent)(n),
+                depth
+            )?;
+            if let Some(e) = ours {
+                let depth = p.measurement_depth.map(|d| ratio(e.measurement_depth, d(n))).unwrap_or_else(|| "-".to_string());
+                writeln!(
+                    f,
+                    "    vs {}: qubits {}, Toffoli+T/2 {}, measurement depth {}",
+                    e.construction,
+                    ratio(e.logical_qubits as f64, (p.logical_qubits)(n)),
+                    ratio(e.toffoli_equivalent(), (p.toffoli_equivalent)(n)),
+                    depth
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::Beauregard;
+    use crate::circuit::Qft;
+    use crate::quantum::GateKind;
+
+    fn assumptions(spec: &str) -> Assumptions {
+        spec.parse().unwrap()
+    }
+
+    #[test]
+    fn beauregard_closed_form() {
+        // With the exact QFT (R = n(n+1)/2 controlled rotations on m = n + 1 qubits) an adder
+        // has 21n + 4(n+1) + 12R = 6n^2 + 31n + 4 rotations, a multiplier 6R + n adders
+        for n in [2u64, 3, 5, 8, 13] {
+            for e in [1, 2 * n] {
+                let estimate = Construction::Beauregard.estimate(n, &assumptions(&format!("exponent-bits={},aqft-cutoff=exact", e)));
+                let multiplier = 6 * n * n * n + 34 * n * n + 7 * n;
+                assert_eq!(estimate.logical_qubits, 2 * n + 3);
+                assert_eq!(estimate.toffolis, (e * 3 * n) as f64);
+                assert_eq!(estimate.rotations, (e * (2 * multiplier + 1)) as f64);
+            }
+        }
+        // A cutoff of c keeps c(c+1)/2 + (m-1-c)c rotations per QFT
+        let (n, c) = (8u64, 2);
+        let cut = Construction::Beauregard.estimate(n, &assumptions("exponent-bits=1,aqft-cutoff=2"));
+        let qft = c * (c + 1) / 2 + (n - c) * c;
+        let adder = 21 * n + 4 * (n + 1) + 12 * qft;
+        assert_eq!(cut.rotations, (2 * (6 * qft + n * adder) + 1) as f64);
+    }
+
+    #[test]
+    fn beauregard_matches_the_circuit_it_counts() {
+        for n in [5u64, 7, 11, 13] {
+            let circuit = Beauregard::new(n, 1, Qft::exact());
+            let bits = u64::from(u64::BITS - n.leading_zeros());
+            let gates = circuit.controlled_mod_mul(2, 0).unwrap();
+            let toffolis = gates.iter().filter(|g| g.kind == GateKind::X && g.controls.len() == 2).count();
+            let estimate = Construction::Beauregard.estimate(bits, &assumptions("exponent-bits=1,aqft-cutoff=exact"));
+            assert_eq!(circuit.num_qubits() as u64 + 1, estimate.logical_qubits);
+            assert_eq!(toffolis as f64, estimate.toffolis);
+        }
+    }
+
+    #[test]
+    fn windowed_closed_form() {
+        // n = 8, 16 exponent bits, 2-bit windows: 2 * 8 * 4 = 64 lookup-additions of a
+        // 16-entry table (16 + 2 * 4 Toffolis) and 8n Toffolis of modular addition
+        let estimate = Construction::Windowed.estimate(8, &assumptions("exponent-bits=16,exp-window=2,mul-window=2"));
+        assert_eq!(estimate.logical_qubits, 3 * 8 + 2 + 2);
+        assert_eq!(estimate.toffolis, 64.0 * (24.0 + 64.0));
+        assert_eq!(estimate.rotations, 16.0);
+        // 16 rotations sharing half of 0.01: ⌈3 log2(3200)⌉ T gates each
+        assert_eq!(estimate.t_per_rotation, 35.0);
+        assert_eq!(estimate.depth, 64.0 * 88.0 + 16.0);
+        assert_eq!(estimate.measurement_depth, 64.0 * 88.0 + 16.0 * 35.0);
+        assert_eq!(estimate.toffoli_equivalent(), 64.0 * 88.0 + 16.0 * 35.0 / 2.0);
+    }
+
+    #[test]
+    fn coset_closed_form() {
+        // Runways every 4 bits cut 8 bits into 2 segments, each padded by 3: 14-bit registers
+        let estimate = Construction::Coset.estimate(8, &assumptions("exponent-bits=16,exp-window=2,mul-window=2,runway-spacing=4,coset-padding=3"));
+        assert_eq!(estimate.logical_qubits, 2 * 14 + 8 + 2 + 1);
+        assert_eq!(estimate.toffolis, 64.0 * (24.0 + 28.0));
+        assert_eq!(estimate.depth, 64.0 * (24.0 + 2.0 * (4.0 + 3.0)) + 16.0);
+    }
+
+    #[test]
+    fn defaults_are_resolved_once() {
+        // 2n exponent bits, a ⌈log2 n⌉ cutoff, and padding for 128 deviations in half of 0.01
+        let resolved = assumptions("exp-window=2,mul-window=2,runway-spacing=4").resolved(8);
+        assert_eq!((resolved.exponent_bits, resolved.aqft_cutoff, resolved.coset_padding), (Some(16), Some(3), Some(15)));
+        let report = estimate_resources(8, &assumptions("exp-window=2,mul-window=2,runway-spacing=4"));
+        assert_eq!(report.assumptions, resolved);
+        assert_eq!(report.estimates[2], Construction::Coset.estimate(8, &resolved));
+        assert_eq!(resolved.to_string().parse::<Assumptions>(), Ok(resolved));
+    }
+}
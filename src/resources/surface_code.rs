@@ -0,0 +1,451 @@
+// Physical costs of a logical estimate on a surface-code machine: code distance,
+// physical qubits and wall-clock time.
+//
+// A patch of distance d (one logical qubit, or one tile of routing space) takes
+// 2(d+1)^2 physical qubits, data and measurement qubits with a margin for the
+// lattice surgery between patches, and fails with probability
+// prefactor (p / threshold)^((d+1)/2) per code cycle.
+//
+// Toffolis consume CCZ states and T gates half of one each (a CCZ state turns into
+// two T states by the catalyzed CCZ -> 2T conversion), so the states needed are the
+// Toffoli + T/2 count. Factories make them: rounds of 15-to-1 T distillation (output
+// error 35 ε^3) feeding an 8T -> CCZ stage (28 ε^2). Every block is `factory_tiles`
+// patches big and runs for `factory_cycles` times its distance in code cycles, at the
+// smallest distance whose own logical errors stay below what it distills to.
+//
+// The computation is reaction limited: every layer of dependent measurements waits
+// one reaction time (decoding plus feedback), unless the factories cannot keep up.
+
+use super::{Construction, LogicalEstimate, ResourceReport};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+// Largest code distance the estimates consider
+pub const MAX_DISTANCE: u64 = 201;
+// Most rounds of 15-to-1 distillation before giving up on the error budget
+const MAX_LEVELS: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceCode {
+    // Error rate of every physical gate, measurement and injected state
+    pub physical_error: f64,
+    pub threshold: f64,
+    pub prefactor: f64,
+    // One round of stabilizer measurements
+    pub cycle_time: Duration,
+    // From a measurement to the gate that depends on its result
+    pub reaction_time: Duration,
+    // Routing tiles per logical qubit, the space lattice surgery needs between them
+    pub routing_overhead: f64,
+    // Allowed probability of a run failing, half for the patches, half for the states
+    pub error_budget: f64,
+    // Rounds of 15-to-1 distillation (None = fewest that meet the budget)
+    pub factory_levels: Option<u32>,
+    // Patches per distillation block, and its code cycles per unit of distance
+    pub factory_tiles: f64,
+    pub factory_cycles: f64,
+    // Factories on the machine (None = enough to keep up with the reaction time)
+    pub factories: Option<u64>,
+}
+
+impl Default for SurfaceCode {
+    fn default() -> Self {
+        SurfaceCode {
+            physical_error: 1e-3,
+            threshold: 1e-2,
+            prefactor: 0.1,
+            cycle_time: Duration::from_micros(1),
+            reaction_time: Duration::from_micros(10),
+            routing_overhead: 0.5,
+            error_budget: 0.1,
+            factory_levels: None,
+            factory_tiles: 11.0,
+            factory_cycles: 6.0,
+            factories: None,
+        }
+    }
+}
+
+impl SurfaceCode {
+    // Probability that one patch of distance d fails in one code cycle
+    pub fn logical_error(&self, distance: u64) -> f64 {
+        self.prefactor * (self.physical_error / self.threshold).powf((distance + 1) as f64 / 2.0)
+    }
+
+    // Physical qubits of one patch
+    pub fn patch_qubits(distance: u64) -> f64 {
+        2.0 * ((distance + 1) * (distance + 1)) as f64
+    }
+
+    // Smallest odd distance at which `patch_cycles(d)` patch-cycles fail with probability at most `target`
+    fn distance_for(&self, patch_cycles: impl Fn(u64) -> f64, target: f64) -> Option<u64> {
+        (3..=MAX_DISTANCE).step_by(2).find(|&d| patch_cycles(d) * self.logical_error(d) <= target)
+    }
+
+    // A factory whose CCZ states keep `states` of them within half the error budget
+    pub fn factory(&self, states: f64) -> Result<FactoryDesign, SurfaceCodeError> {
+        let target = self.error_budget / 2.0 / states;
+        let block = |d: u64| self.factory_tiles * self.factory_cycles * d as f64;
+        // Injected T states are as good as the physical gates
+        let mut error = self.physical_error;
+        let mut distances = Vec::new();
+        loop {
+            let ccz = 28.0 * error * error;
+            let done = match self.factory_levels {
+                Some(levels) => distances.len() as u32 >= levels,
+                None => ccz < target,
+            };
+            if done {
+                // What the distillation leaves of the budget is for the stage's own errors
+                let allowed = if ccz < target { target - ccz } else { ccz };
+                let ccz_distance = self.distance_for(block, allowed).ok_or(SurfaceCodeError::DistanceTooLarge)?;
+                return Ok(self.layout(distances, ccz_distance, ccz + block(ccz_distance) * self.logical_error(ccz_distance)));
+            }
+            if distances.len() as u32 >= MAX_LEVELS {
+                return Err(SurfaceCodeError::DistillationTooWeak { levels: MAX_LEVELS });
+            }
+            let distilled = 35.0 * error.powi(3);
+            if distilled >= error {
+                return Err(SurfaceCodeError::DistillationTooWeak { levels: distances.len() as u32 });
+            }
+            let distance = self.distance_for(block, distilled).ok_or(SurfaceCodeError::DistanceTooLarge)?;
+            error = distilled + block(distance) * self.logical_error(distance);
+            distances.push(distance);
+        }
+    }
+
+    // Blocks per level so that each level keeps the next one busy: the CCZ stage eats 8
+    // T states per run, a 15-to-1 block 15 of the level below, and a block of distance d
+    // finishes a run every `factory_cycles` d code cycles
+    fn layout(&self, distances: Vec<u64>, ccz_distance: u64, state_error: f64) -> FactoryDesign {
+        let mut blocks = vec![0; distances.len()];
+        let (mut inputs, mut consumer) = (8.0, ccz_distance as f64);
+        for (level, &d) in distances.iter().enumerate().rev() {
+            blocks[level] = (inputs * d as f64 / consumer).ceil() as u64;
+            (inputs, consumer) = (15.0 * blocks[level] as f64, d as f64);
+        }
+        let qubits = self.factory_tiles
+            * (Self::patch_qubits(ccz_distance) + distances.iter().zip(&blocks).map(|(&d, &b)| b as f64 * Self::patch_qubits(d)).sum::<f64>());
+        let period = self.cycle_time.as_secs_f64() * self.factory_cycles * ccz_distance as f64;
+        FactoryDesign { distances, blocks, ccz_distance, qubits, period, state_error }
+    }
+
+    // The machine that runs `logical`
+    pub fn estimate(&self, logical: &LogicalEstimate) -> Result<PhysicalEstimate, SurfaceCodeError> {
+        if self.physical_error >= self.threshold {
+            return Err(SurfaceCodeError::AboveThreshold { physical_error: self.physical_error, threshold: self.threshold });
+        }
+        let states = logical.toffoli_equivalent();
+        let factory = self.factory(states)?;
+        let reaction_limited = logical.measurement_depth * self.reaction_time.as_secs_f64();
+        // Without dependent measurements there is no pace to keep up with, one factory sets it
+        let factories = self.factories.unwrap_or_else(|| {
+            if reaction_limited > 0.0 { (states * factory.period / reaction_limited).ceil().max(1.0) as u64 } else { 1 }
+        });
+        let runtime = reaction_limited.max(states * factory.period / factories as f64);
+
+        let tiles = (logical.logical_qubits as f64 * (1.0 + self.routing_overhead)).ceil();
+        let cycles = runtime / self.cycle_time.as_secs_f64();
+        let distance = self.distance_for(|_| tiles * cycles, self.error_budget / 2.0).ok_or(SurfaceCodeError::DistanceTooLarge)?;
+        let data_qubits = tiles * Self::patch_qubits(distance);
+        Ok(PhysicalEstimate {
+            construction: logical.construction,
+            bits: logical.bits,
+            distance,
+            data_qubits,
+            physical_qubits: data_qubits + factories as f64 * factory.qubits,
+            failure_probability: (tiles * cycles * self.logical_error(distance) + states * factory.state_error).min(1.0),
+            factory_limited: runtime > reaction_limited,
+            factories,
+            factory,
+            runtime,
+        })
+    }
+}
+
+impl fmt::Display for SurfaceCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let micros = |d: Duration| d.as_nanos() as f64 / 1000.0;
+        let auto = |value: Option<String>| value.unwrap_or_else(|| "auto".to_string());
+        write!(
+            f,
+            "physical-error={},threshold={},prefactor={},cycle-time-us={},reaction-time-us={},routing-overhead={},error-budget={},factory-levels={},factory-tiles={},factory-cycles={},factories={}",
+            self.physical_error,
+            self.threshold,
+            self.prefactor,
+            micros(self.cycle_time),
+            micros(self.reaction_time),
+            self.routing_overhead,
+            self.error_budget,
+            auto(self.factory_levels.map(|l| l.to_string())),
+            self.factory_tiles,
+            self.factory_cycles,
+            auto(self.factories.map(|n| n.to_string())),
+        )
+    }
+}
+
+impl FromStr for SurfaceCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const KEYS: &str = "physical-error, threshold, prefactor, cycle-time-us, reaction-time-us, routing-overhead, error-budget, factory-levels, factory-tiles, factory-cycles, factories";
+        let mut code = SurfaceCode::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, value) = item.split_once('=').ok_or_else(|| format!("Surface code parameter {} needs =VALUE", item))?;
+            let (key, value) = (key.trim(), value.trim());
+            let number = |valid: fn(f64) -> bool| value.parse::<f64>().ok().filter(|&x| valid(x)).ok_or_else(|| format!("Invalid value in {}", item));
+            let probability = || number(|x| x > 0.0 && x < 1.0);
+            let positive = || number(|x| x > 0.0 && x.is_finite());
+            // Durations count whole nanoseconds, a time that rounds to none would be a division by zero
+            let micros = || {
+                let nanos = (positive()? * 1000.0).round() as u64;
+                (nanos > 0).then(|| Duration::from_nanos(nanos)).ok_or_else(|| format!("Invalid value in {} (below a nanosecond)", item))
+            };
+            let count = |min: u64| value.parse::<u64>().ok().filter(|&v| v >= min).ok_or_else(|| format!("Invalid value in {}", item));
+            match key {
+                "physical-error" => code.physical_error = probability()?,
+                "threshold" => code.threshold = probability()?,
+                "prefactor" => code.prefactor = positive()?,
+                "cycle-time-us" => code.cycle_time = micros()?,
+                "reaction-time-us" => code.reaction_time = micros()?,
+                "routing-overhead" => code.routing_overhead = number(|x| x >= 0.0 && x.is_finite())?,
+                "error-budget" => code.error_budget = probability()?,
+                "factory-levels" if value == "auto" => code.factory_levels = None,
+                "factory-levels" => code.factory_levels = Some(count(1).map(|l| l.min(MAX_LEVELS as u64) as u32)?),
+                "factory-tiles" => code.factory_tiles = positive()?,
+                "factory-cycles" => code.factory_cycles = positive()?,
+                "factories" if value == "auto" => code.factories = None,
+                "factories" => code.factories = Some(count(1)?),
+                _ => return Err(format!("Unknown surface code parameter {} (available: {})", key, KEYS)),
+            }
+        }
+        Ok(code)
+    }
+}
+
+// Why a construction has no physical estimate
+#[derive(Debug, Clone, PartialEq)]
+pub enum SurfaceCodeError {
+    // Larger codes only make things worse
+    AboveThreshold { physical_error: f64, threshold: f64 },
+    // Needs a code distance past MAX_DISTANCE
+    DistanceTooLarge,
+    // This many rounds of 15-to-1 distillation do not get the states good enough
+    DistillationTooWeak { levels: u32 },
+}
+
+impl fmt::Display for SurfaceCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurfaceCodeError::AboveThreshold { physical_error, threshold } => {
+                write!(f, "physical error rate {} is not below the threshold {}", physical_error, threshold)
+            }
+            SurfaceCodeError::DistanceTooLarge => write!(f, "needs a code distance above {}", MAX_DISTANCE),
+            SurfaceCodeError::DistillationTooWeak { levels } => write!(f, "{} rounds of distillation do not reach the error budget", levels),
+        }
+    }
+}
+
+impl Error for SurfaceCodeError {}
+
+// The magic-state factory `SurfaceCode::factory` designs
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactoryDesign {
+    // Code distance and block count of each 15-to-1 round, the first round first
+    pub distances: Vec<u64>,
+    pub blocks: Vec<u64>,
+    pub ccz_distance: u64,
+    pub qubits: f64,
+    // Seconds per CCZ state, and the error of each one
+    pub period: f64,
+    pub state_error: f64,
+}
+
+// What running one construction takes on the machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalEstimate {
+    pub construction: Construction,
+    pub bits: u64,
+    pub distance: u64,
+    // Physical qubits of the logical qubits and their routing space
+    pub data_qubits: f64,
+    pub factory: FactoryDesign,
+    pub factories: u64,
+    pub physical_qubits: f64,
+    // Wall-clock seconds, set by the factories rather than the reaction time when `factory_limited`
+    pub runtime: f64,
+    pub factory_limited: bool,
+    // Chance that an error spoils the run (the expected number of errors, capped at 1), within
+    // the error budget unless `factory_levels` or `factories` were forced too low
+    pub failure_probability: f64,
+}
+
+impl PhysicalEstimate {
+    // "RSA-2048 needs X physical qubits for Y hours"
+    pub fn summary(&self) -> String {
+        format!("RSA-{} needs {} physical qubits for {} ({} construction)", self.bits, in_words(self.physical_qubits), duration(self.runtime), self.construction)
+    }
+}
+
+// Physical estimates for every construction of a logical report
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalReport {
+    pub bits: u64,
+    pub code: SurfaceCode,
+    pub estimates: Vec<(Construction, Result<PhysicalEstimate, SurfaceCodeError>)>,
+}
+
+pub fn physical_report(logical: &ResourceReport, code: &SurfaceCode) -> PhysicalReport {
+    let estimates = logical.estimates.iter().map(|e| (e.construction, code.estimate(e))).collect();
+    PhysicalReport { bits: logical.bits, code: code.clone(), estimates }
+}
+
+impl fmt::Display for PhysicalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Surface code: {}", self.code)?;
+        writeln!(
+            f,
+            "{:<12}  {:>8}  {:>11}  {:>22}  {:>15}  {:>12}  {:>9}",
+            "construction", "distance", "data qubits", "factories", "physical qubits", "runtime", "failure"
+        )?;
+        for (construction, estimate) in &self.estimates {
+            match estimate {
+                Ok(e) => {
+                    let factories = format!("{} x {:.0} (d {})", e.factories, e.factory.qubits, e.factory.ccz_distance);
+                    let runtime = if e.factory_limited { format!("{}*", duration(e.runtime)) } else { duration(e.runtime) };
+                    writeln!(
+                        f,
+                        "{:<12}  {:>8}  {:>11.3e}  {:>22}  {:>15.3e}  {:>12}  {:>9.2e}",
+                        construction.name(),
+                        e.distance,
+                        e.data_qubits,
+                        factories,
+                        e.physical_qubits,
+                        runtime,
+                        e.failure_probability
+                    )?;
+                }
+                Err(err) => writeln!(f, "{:<12}  {}", construction.name(), err)?,
+            }
+        }
+        if self.estimates.iter().any(|(_, e)| e.as_ref().is_ok_and(|e| e.factory_limited)) {
+            writeln!(f, "* limited by the factories, not the reaction time")?;
+        }
+        for e in self.estimates.iter().filter_map(|(_, e)| e.as_ref().ok()) {
+            writeln!(f, "{}", e.summary())?;
+        }
+        Ok(())
+    }
+}
+
+// Physical qubits in words past a million
+fn in_words(x: f64) -> String {
+    match x {
+        x if x >= 1e9 => format!("{:.1} billion", x / 1e9),
+        x if x >= 1e6 => format!("{:.1} million", x / 1e6),
+        x => format!("{:.0}", x),
+    }
+}
+
+// Seconds in the largest unit that keeps the number readable
+fn duration(seconds: f64) -> String {
+    const UNITS: [(&str, f64); 5] = [("years", 365.25 * 86400.0), ("days", 86400.0), ("hours", 3600.0), ("minutes", 60.0), ("seconds", 1.0)];
+    let (unit, size) = UNITS.iter().copied().find(|&(_, size)| seconds >= 2.0 * size).unwrap_or(("seconds", 1.0));
+    format!("{:.1} {}", seconds / size, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::Assumptions;
+
+    fn logical(construction: Construction, bits: u64) -> LogicalEstimate {
+        construction.estimate(bits, &Assumptions::default())
+    }
+
+    #[test]
+    fn rsa_2048_matches_the_published_estimate() {
+        // Gidney & Ekerå 2019: 20 million noisy qubits for 8 hours at p = 1e-3, a 1 µs
+        // cycle and 10 µs reactions, with code distance 27 for the data
+        let estimate = SurfaceCode::default().estimate(&logical(Construction::Coset, 2048)).unwrap();
+        assert!((15e6..25e6).contains(&estimate.physical_qubits), "{}", estimate.summary());
+        assert!((4.0 * 3600.0..12.0 * 3600.0).contains(&estimate.runtime), "{}", estimate.summary());
+        assert!((25..=31).contains(&estimate.distance));
+        assert!(estimate.failure_probability <= SurfaceCode::default().error_budget);
+        assert!(!estimate.factory_limited);
+    }
+
+    #[test]
+    fn distance_is_the_smallest_that_meets_the_budget() {
+        let code = SurfaceCode::default();
+        for bits in [64, 512, 2048] {
+            let logical = logical(Construction::Coset, bits);
+            let estimate = code.estimate(&logical).unwrap();
+            assert_eq!(estimate.distance % 2, 1);
+            let tiles = (logical.logical_qubits as f64 * (1.0 + code.routing_overhead)).ceil();
+            let patch_cycles = tiles * estimate.runtime / code.cycle_time.as_secs_f64();
+            assert!(patch_cycles * code.logical_error(estimate.distance) <= code.error_budget / 2.0);
+            assert!(patch_cycles * code.logical_error(estimate.distance - 2) > code.error_budget / 2.0);
+            assert_eq!(estimate.data_qubits, tiles * SurfaceCode::patch_qubits(estimate.distance));
+        }
+        // Each step of 2 in distance is one more factor p / threshold
+        assert!((code.logical_error(5) / code.logical_error(3) - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn factory_takes_the_fewest_levels_that_meet_the_budget() {
+        let code = SurfaceCode::default();
+        // 28 p^2 = 2.8e-5 per CCZ state is enough for a thousand states, not for a billion
+        assert!(code.factory(1e3).unwrap().distances.is_empty());
+        let factory = code.factory(1e9).unwrap();
+        assert_eq!(factory.distances.len(), 1);
+        assert!(factory.state_error * 1e9 <= code.error_budget / 2.0);
+        // Enough 15-to-1 blocks to feed the 8 inputs of the CCZ stage
+        assert_eq!(factory.blocks[0], (8.0 * factory.distances[0] as f64 / factory.ccz_distance as f64).ceil() as u64);
+
+        let noisier = SurfaceCode { physical_error: 5e-3, ..SurfaceCode::default() };
+        assert!(noisier.factory(1e9).unwrap().distances.len() > 1);
+        let forced = SurfaceCode { factory_levels: Some(3), ..SurfaceCode::default() };
+        assert_eq!(forced.factory(1e3).unwrap().distances.len(), 3);
+        // 35 p^3 is no better than p at p = 0.2
+        let hopeless = SurfaceCode { physical_error: 0.2, threshold: 0.5, ..SurfaceCode::default() };
+        assert_eq!(hopeless.factory(1e9), Err(SurfaceCodeError::DistillationTooWeak { levels: 0 }));
+    }
+
+    #[test]
+    fn refuses_codes_above_threshold() {
+        let code = SurfaceCode { physical_error: 0.02, ..SurfaceCode::default() };
+        assert_eq!(code.estimate(&logical(Construction::Coset, 2048)), Err(SurfaceCodeError::AboveThreshold { physical_error: 0.02, threshold: 0.01 }));
+    }
+
+    #[test]
+    fn no_measurement_depth_takes_one_factory() {
+        let mut logical = logical(Construction::Windowed, 64);
+        logical.measurement_depth = 0.0;
+        let code = SurfaceCode::default();
+        let estimate = code.estimate(&logical).unwrap();
+        assert_eq!(estimate.factories, 1);
+        assert!(estimate.factory_limited);
+        assert_eq!(estimate.runtime, logical.toffoli_equivalent() * estimate.factory.period);
+        // Forced factories split the states between them
+        let estimate = SurfaceCode { factories: Some(4), ..code }.estimate(&logical).unwrap();
+        assert_eq!(estimate.runtime, logical.toffoli_equivalent() * estimate.factory.period / 4.0);
+    }
+
+    #[test]
+    fn spec_round_trips() {
+        let default = SurfaceCode::default();
+        assert_eq!(default.to_string().parse::<SurfaceCode>(), Ok(default.clone()));
+        let code: SurfaceCode = "physical-error=1e-4,cycle-time-us=0.5,reaction-time-us=2.25,factory-levels=2,factories=12".parse().unwrap();
+        assert_eq!((code.cycle_time, code.reaction_time), (Duration::from_nanos(500), Duration::from_nanos(2250)));
+        assert_eq!((code.factory_levels, code.factories), (Some(2), Some(12)));
+        assert_eq!(code.to_string().parse::<SurfaceCode>(), Ok(code));
+        assert_eq!("factory-levels=9".parse::<SurfaceCode>().unwrap().factory_levels, Some(MAX_LEVELS));
+        for spec in ["physical-error=1", "threshold=0", "cycle-time-us=0.0001", "reaction-time-us=-1", "factories=0", "factory-levels=0", "prefactor", "distance=9"] {
+            assert!(spec.parse::<SurfaceCode>().is_err(), "{} parsed", spec);
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use crate::budget::Budget;
+use crate::circuit::{order_finding_circuit, semiclassical_order_finding_circuit, Oracle, Qft};
+use crate::config::Config;
+use crate::error::FactorError;
+use crate::events::SilentObserver;
+use crate::math::gcd;
+use crate::period::{check_size, find_period_classical, QuantumOrderFinding, SemiClassicalOrderFinding};
+use crate::postprocess::{analyze_histogram, analyze_measurement, MeasurementFailure, DEFAULT_MAX_MULTIPLE};
+use crate::quantum::{NoiseModel, SimulatorKind};
+use crate::shor::factor_from_period;
+use crate::{resolve_seed, seeded_rng};
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+use std::f64::consts::PI;
+use std::fmt;
+
+// Largest phase register whose whole ideal distribution is listed (2^16 outcomes)
+pub const THEORY_MAX_BITS: usize = 16;
+// Outcomes never seen are listed when the ideal circuit gives them at least this probability
+pub const THEORY_FLOOR: f64 = 1e-6;
+// Outcomes the console report shows, the export has all of them
+const ROWS_SHOWN: usize = 32;
+
+// One outcome of the phase register
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRow {
+    pub y: u64,
+    pub shots: usize,
+    // shots / total shots
+    pub probability: f64,
+    // Probability of y for the ideal circuit
+    pub theory: f64,
+    // What post-processing y on its own gives
+    pub result: Result<BigUint, MeasurementFailure>,
+}
+
+// A histogram of the order-finding circuit for one base, read the way `shors_algorithm`
+// reads single measurements and set against the ideal circuit
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleReport {
+    pub n: BigUint,
+    pub a: BigUint,
+    pub semiclassical: bool,
+    pub oracle: Oracle,
+    pub simulator: SimulatorKind,
+    pub noise: NoiseModel,
+    pub seed: u64,
+    pub shots: usize,
+    // Bits of the phase register, Q = 2^t
+    pub t: usize,
+    // The order of a, found classically
+    pub order: BigUint,
+    // Whether a and its order split n (r even and a^(r/2) ≢ -1)
+    pub splits: bool,
+    // The outcomes seen, most shots first, then (when t <= THEORY_MAX_BITS) the ones
+    // never seen that the ideal circuit gives with probability THEORY_FLOOR or more
+    pub rows: Vec<SampleRow>,
+    // Shots whose outcome alone gives the order
+    pub correct_shots: usize,
+    // Chance that one run of the ideal circuit gives the order (None when t > THEORY_MAX_BITS)
+    pub theory_success: Option<f64>,
+    // Half the L1 distance between the histogram and the ideal distribution
+    pub total_variation: f64,
+    // Runs `shors_algorithm` makes per base before moving on to the next one
+    pub runs_per_base: usize,
+}
+
+impl SampleReport {
+    pub fn success_rate(&self) -> f64 {
+        self.correct_shots as f64 / self.shots as f64
+    }
+
+    // Runs of the circuit until one gives the order, on average (None when no shot did)
+    pub fn expected_runs(&self) -> Option<f64> {
+        (self.correct_shots > 0).then(|| 1.0 / self.success_rate())
+    }
+
+    // Iterations of `shors_algorithm` (bases tried) until this base splits n, when every
+    // base behaved like this one: each gets `runs_per_base` runs to give the order
+    pub fn expected_iterations(&self) -> Option<f64> {
+        if !self.splits || self.correct_shots == 0 {
+            return None;
+        }
+        Some(1.0 / (1.0 - (1.0 - self.success_rate()).powi(self.runs_per_base as i32)))
+    }
+
+    fn circuit_name(&self) -> &'static str {
+        if self.semiclassical { "semiclassical" } else { "order-finding" }
+    }
+
+    // One line per outcome: y,bits,shots,probability,theory,period (empty when y gives none)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("y,bits,shots,probability,theory,period\n");
+        for row in &self.rows {
+            let period = row.result.as_ref().map(|r| r.to_string()).unwrap_or_default();
+            csv += &format!("{},{:0t$b},{},{},{},{}\n", row.y, row.y, row.shots, row.probability, row.theory, period, t = self.t);
+        }
+        csv
+    }
+
+    // The whole report as one JSON object, the outcomes as an array of the CSV's columns
+    pub fn to_json(&self) -> String {
+        let number = |x: Option<f64>| x.map(|x| x.to_string()).unwrap_or_else(|| "null".to_string());
+        let mut json = String::from("{\n");
+        json += &format!("  \"n\": \"{}\",\n  \"a\": \"{}\",\n", self.n, self.a);
+        json += &format!("  \"circuit\": \"{}\",\n  \"oracle\": \"{}\",\n", self.circuit_name(), self.oracle);
+        json += &format!("  \"simulator\": \"{}\",\n  \"noise\": \"{}\",\n", self.simulator, self.noise);
+        json += &format!("  \"seed\": {},\n  \"shots\": {},\n  \"t\": {},\n", self.seed, self.shots, self.t);
+        json += &format!("  \"order\": \"{}\",\n  \"splits\": {},\n", self.order, self.splits);
+        json += &format!("  \"correct_shots\": {},\n  \"success_rate\": {},\n", self.correct_shots, self.success_rate());
+        json += &format!("  \"theory_success\": {},\n  \"total_variation\": {},\n", number(self.theory_success), self.total_variation);
+        json += &format!("  \"expected_runs\": {},\n  \"expected_iterations\": {},\n", number(self.expected_runs()), number(self.expected_iterations()));
+        json += "  \"outcomes\": [";
+        for (i, row) in self.rows.iter().enumerate() {
+            let period = row.result.as_ref().map(|r| format!("\"{}\"", r)).unwrap_or_else(|_| "null".to_string());
+            json += if i == 0 { "\n" } else { ",\n" };
+            json += &format!(
+                "    {{\"y\": {}, \"bits\": \"{:0t$b}\", \"shots\": {}, \"probability\": {}, \"theory\": {}, \"period\": {}}}",
+                row.y, row.y, row.shots, row.probability, row.theory, period, t = self.t
+            );
+        }
+        json += "\n  ]\n}\n";
+        json
+    }
+}
+
+// Probability that the ideal order-finding circuit with a t-bit phase register measures y
+// when the order is r. The work register leaves the phase register in a uniform
+// superposition of the x ≡ x0 (mod r), m of them, for a random x0; the inverse QFT turns
+// that into |Σ_j e^(2πi j r y / Q)|^2 / Q m = sin^2(π m r y / Q) / sin^2(π r y / Q) / Q m.
+// Of the r residues, Q mod r occur ⌈Q / r⌉ times and the rest ⌊Q / r⌋ times.
+pub fn ideal_probability(y: u64, t: usize, r: u64) -> f64 {
+    let q = 1u128 << t;
+    let r = r as u128;
+    let turn = r * y as u128 % q;
+    let peak = |m: u128| {
+        if turn == 0 {
+            return (m * m) as f64;
+        }
+        // m r y mod Q, in floating point only past 2^128
+        let wound = m.checked_mul(turn).map(|x| (x % q) as f64).unwrap_or_else(|| (m as f64 * turn as f64) % q as f64);
+        ((PI * wound / q as f64).sin() / (PI * turn as f64 / q as f64).sin()).powi(2)
+    };
+    let (count, extra) = (q / r, q % r);
+    (extra as f64 * peak(count + 1) + (r - extra) as f64 * peak(count)) / (q as f64 * q as f64)
+}
+
+// Run the order-finding circuit for a mod n (the semi-classical one when `semiclassical`)
+// `shots` times on `config.simulator` with `config.noise`, and analyse the histogram
+pub fn sample_order_finding(n: &BigUint, a: &BigUint, semiclassical: bool, oracle: Oracle, config: &Config, shots: usize) -> Result<SampleReport, FactorError> {
+    if shots == 0 {
+        return Err(FactorError::Backend("sampling needs at least one shot".to_string()));
+    }
+    if gcd(a, n) != BigUint::one() {
+        return Err(FactorError::Backend(format!("{} shares a factor with {}, there is no order to find", a, n)));
+    }
+    let (Some(a_small), Some(n_small)) = (a.to_u64(), n.to_u64()) else {
+        return Err(FactorError::Backend(format!("{} is too large to simulate", n)));
+    };
+    let qft = Qft::exact();
+    let (of, max_qubits, runs_per_base) = if semiclassical {
+        let finder = SemiClassicalOrderFinding::default();
+        (semiclassical_order_finding_circuit(a_small, n_small, &qft, oracle), finder.max_qubits, finder.measurements)
+    } else {
+        let finder = QuantumOrderFinding::default();
+        (order_finding_circuit(a_small, n_small, &qft, oracle), finder.max_qubits, finder.measurements)
+    };
+    let of = of.map_err(|e| FactorError::Backend(e.to_string()))?;
+    check_size(n, of.circuit.num_qubits, config.simulator, max_qubits)?;
+
+    let seed = resolve_seed(config);
+    let histogram = of.circuit.histogram(config.simulator, &config.noise, shots, &mut seeded_rng(seed)).map_err(|e| FactorError::Backend(e.to_string()))?;
+    let t = of.phase_bits();
+    let limit = n * n;
+    let order = find_period_classical(a, n, &limit, &Budget::unlimited())?
+        .ok_or_else(|| FactorError::Backend(format!("no order of {} mod {} below {}", a, n, limit)))?;
+    let r = order.to_u64().ok_or_else(|| FactorError::Backend(format!("the order of {} mod {} is too large to simulate", a, n)))?;
+
+    let analysis = analyze_histogram(&histogram, t, a, n, DEFAULT_MAX_MULTIPLE);
+    let mut rows: Vec<SampleRow> = analysis
+        .outcomes
+        .into_iter()
+        .map(|(outcome, count)| {
+            let y = outcome.y.to_u64().unwrap_or(0);
+            SampleRow { y, shots: count, probability: count as f64 / shots as f64, theory: ideal_probability(y, t, r), result: outcome.result }
+        })
+        .collect();
+    let correct_shots = rows.iter().filter(|row| row.result.as_ref() == Ok(&order)).map(|row| row.shots).sum();
+    // Every outcome never seen is a gap of its whole ideal probability
+    let seen: f64 = rows.iter().map(|row| row.theory).sum();
+    let total_variation = (rows.iter().map(|row| (row.probability - row.theory).abs()).sum::<f64>() + (1.0 - seen).max(0.0)) / 2.0;
+
+    let theory_success = (t <= THEORY_MAX_BITS).then(|| {
+        let mut success = 0.0;
+        for y in 0..1u64 << t {
+            let theory = ideal_probability(y, t, r);
+            if theory < f64::EPSILON * f64::EPSILON {
+                continue;
+            }
+            let result = analyze_measurement(&BigUint::from(y), t, a, n, DEFAULT_MAX_MULTIPLE).result;
+            if result.as_ref() == Ok(&order) {
+                success += theory;
+            }
+            if theory >= THEORY_FLOOR && !histogram.contains_key(&y) {
+                rows.push(SampleRow { y, shots: 0, probability: 0.0, theory, result });
+            }
+        }
+        success
+    });
+
+    Ok(SampleReport {
+        n: n.clone(),
+        a: a.clone(),
+        semiclassical,
+        oracle,
+        simulator: config.simulator,
+        noise: config.noise.clone(),
+        seed,
+        shots,
+        t,
+        splits: factor_from_period(a, &order, n, &mut SilentObserver).is_some(),
+        order,
+        rows,
+        correct_shots,
+        theory_success,
+        total_variation,
+        runs_per_base,
+    })
+}
+
+impl fmt::Display for SampleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} shots of the {} circuit for a = {}, N = {} (t = {}, {} oracle, {} simulator, noise {}), seed {}",
+            self.shots,
+            self.circuit_name(),
+            self.a,
+            self.n,
+            self.t,
+            self.oracle,
+            self.simulator,
+            self.noise,
+            self.seed
+        )?;
+        writeln!(f, "True order r = {}", self.order)?;
+        writeln!(f, "{:>10}  {:>w$}  {:>8}  {:>9}  {:>9}  post-processing", "y", "bits", "shots", "empirical", "ideal", w = self.t.max(4))?;
+        let seen: Vec<&SampleRow> = self.rows.iter().filter(|row| row.shots > 0).collect();
+        for row in seen.iter().take(ROWS_SHOWN) {
+            let result = match &row.result {
+                Ok(r) => format!("r = {}", r),
+                Err(why) => why.to_string(),
+            };
+            writeln!(
+                f,
+                "{:>10}  {:>0w$b}  {:>8}  {:>8.2}%  {:>8.2}%  {}",
+                row.y,
+                row.y,
+                row.shots,
+                100.0 * row.probability,
+                100.0 * row.theory,
+                result,
+                w = self.t.max(4)
+            )?;
+        }
+        if seen.len() > ROWS_SHOWN {
+            writeln!(f, "  ... {} more outcomes seen (all of them are in the export)", seen.len() - ROWS_SHOWN)?;
+        }
+        let ideal = self.theory_success.map(|p| format!(", ideal circuit {:.1}%", 100.0 * p)).unwrap_or_default();
+        writeln!(f, "Shots giving r = {} on their own: {}/{} ({:.1}%{})", self.order, self.correct_shots, self.shots, 100.0 * self.success_rate(), ideal)?;
+        writeln!(f, "Total variation distance from the ideal distribution: {:.4}", self.total_variation)?;
+        match self.expected_runs() {
+            Some(runs) => {
+                let ideal = self.theory_success.filter(|&p| p > 0.0).map(|p| format!(" (ideal {:.2})", 1.0 / p)).unwrap_or_default();
+                writeln!(f, "Expected runs of the circuit until one gives r: {:.2}{}", runs, ideal)?;
+            }
+            None => writeln!(f, "No shot gave r on its own")?,
+        }
+        match self.expected_iterations() {
+            Some(iterations) => writeln!(
+                f,
+                "Expected shors_algorithm iterations with bases like this one: {:.2} (up to {} runs per base)",
+                iterations, self.runs_per_base
+            ),
+            None if !self.splits => writeln!(
+                f,
+                "r = {} does not split {} (odd, or a^(r/2) ≡ -1), so shors_algorithm moves on to another base",
+                self.order, self.n
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    // Just enough JSON to read the export back
+    #[derive(Debug, Clone, PartialEq)]
+    enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        Str(String),
+        Array(Vec<Json>),
+        Object(BTreeMap<String, Json>),
+    }
+
+    fn parse_json(text: &str) -> Result<Json, String> {
+        let (value, rest) = json_value(text.trim_start())?;
+        if rest.trim().is_empty() { Ok(value) } else { Err(format!("trailing text {:?}", rest)) }
+    }
+
+    fn json_value(s: &str) -> Result<(Json, &str), String> {
+        let s = s.trim_start();
+        if let Some(rest) = s.strip_prefix("null") {
+            return Ok((Json::Null, rest));
+        }
+        if let Some(rest) = s.strip_prefix("true") {
+            return Ok((Json::Bool(true), rest));
+        }
+        if let Some(rest) = s.strip_prefix("false") {
+            return Ok((Json::Bool(false), rest));
+        }
+        if let Some(rest) = s.strip_prefix('"') {
+            let end = rest.find('"').ok_or("unterminated string")?;
+            return Ok((Json::Str(rest[..end].to_string()), &rest[end + 1..]));
+        }
+        if let Some(mut rest) = s.strip_prefix('[') {
+            let mut items = Vec::new();
+            if let Some(after) = rest.trim_start().strip_prefix(']') {
+                return Ok((Json::Array(items), after));
+            }
+            loop {
+                let (item, after) = json_value(rest)?;
+                items.push(item);
+                let after = after.trim_start();
+                if let Some(after) = after.strip_prefix(',') {
+                    rest = after;
+                } else {
+                    return Ok((Json::Array(items), after.strip_prefix(']').ok_or("expected ]")?));
+                }
+            }
+        }
+        if let Some(mut rest) = s.strip_prefix('{') {
+            let mut fields = BTreeMap::new();
+            loop {
+                let (Json::Str(key), after) = json_value(rest)? else { return Err("expected a key".to_string()) };
+                let (value, after) = json_value(after.trim_start().strip_prefix(':').ok_or("expected :")?)?;
+                fields.insert(key, value);
+                let after = after.trim_start();
+                if let Some(after) = after.strip_prefix(',') {
+                    rest = after;
+                } else {
+                    return Ok((Json::Object(fields), after.strip_prefix('}').ok_or("expected }")?));
+                }
+            }
+        }
+        let len = s.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))).unwrap_or(s.len());
+        let number = s[..len].parse().map_err(|_| format!("unexpected {:?}", &s[..len.max(1).min(s.len())]))?;
+        Ok((Json::Number(number), &s[len..]))
+    }
+
+    fn sample(shots: usize, seed: u64) -> Result<SampleReport, FactorError> {
+        let config = Config { seed: Some(seed), simulator: SimulatorKind::StateVector, ..Config::default() };
+        sample_order_finding(&BigUint::from(15u32), &BigUint::from(7u32), false, Oracle::Permutation, &config, shots)
+    }
+
+    #[test]
+    fn ideal_probabilities_sum_to_one() {
+        for t in [1, 3, 6, 9] {
+            for r in [1, 2, 3, 4, 6, 7, 10, 12, 100, 600] {
+                let total: f64 = (0..1u64 << t).map(|y| ideal_probability(y, t, r)).sum();
+                assert!((total - 1.0).abs() < 1e-9, "t = {}, r = {}: {}", t, r, total);
+            }
+        }
+        // r divides Q: r equal peaks at multiples of Q / r
+        assert!((ideal_probability(64, 8, 4) - 0.25).abs() < 1e-12);
+        assert!(ideal_probability(65, 8, 4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn order_of_7_mod_15() {
+        let report = sample(400, 25).unwrap();
+        assert_eq!((report.t, report.order.clone(), report.splits), (8, BigUint::from(4u32), true));
+        // A quarter of the shots each on y = 0, 64, 128, 192. All but y = 0 give 4 on
+        // their own, y = 128 through its convergent 2 and the small multiples of it.
+        assert_eq!(report.theory_success, Some(0.75));
+        let correct: usize = report.rows.iter().filter(|row| row.y != 0).map(|row| row.shots).sum();
+        assert_eq!(report.correct_shots, correct);
+        assert!((260..340).contains(&report.correct_shots), "{}", report.correct_shots);
+        assert_eq!(report.rows.iter().map(|row| row.shots).sum::<usize>(), 400);
+        assert!(report.rows.iter().all(|row| row.y % 64 == 0));
+        // The same seed, the same shots
+        assert_eq!(sample(400, 25).unwrap(), report);
+    }
+
+    #[test]
+    fn refuses_zero_shots_and_shared_factors() {
+        assert!(matches!(sample(0, 1), Err(FactorError::Backend(_))));
+        let config = Config { seed: Some(1), ..Config::default() };
+        assert!(matches!(sample_order_finding(&BigUint::from(15u32), &BigUint::from(6u32), false, Oracle::Permutation, &config, 10), Err(FactorError::Backend(_))));
+    }
+
+    #[test]
+    fn csv_has_a_row_per_outcome() {
+        let report = sample(100, 3).unwrap();
+        let csv = report.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("y,bits,shots,probability,theory,period"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), report.rows.len());
+        for (fields, row) in rows.iter().zip(&report.rows) {
+            assert_eq!(fields.len(), 6);
+            assert_eq!(fields[0].parse::<u64>(), Ok(row.y));
+            assert_eq!(fields[1].len(), 8);
+            assert_eq!(u64::from_str_radix(fields[1], 2), Ok(row.y));
+            assert_eq!(fields[2].parse::<usize>(), Ok(row.shots));
+            assert_eq!(fields[3].parse::<f64>(), Ok(row.shots as f64 / 100.0));
+            assert_eq!(fields[4].parse::<f64>(), Ok(row.theory));
+            assert_eq!(fields[5], row.result.as_ref().map(|r| r.to_string()).unwrap_or_default());
+        }
+    }
+
+    #[test]
+    fn json_parses() {
+        let report = sample(100, 3).unwrap();
+        let Json::Object(fields) = parse_json(&report.to_json()).unwrap() else { panic!("not an object") };
+        assert_eq!(fields["n"], Json::Str("15".to_string()));
+        assert_eq!(fields["circuit"], Json::Str("order-finding".to_string()));
+        assert_eq!(fields["shots"], Json::Number(100.0));
+        assert_eq!(fields["order"], Json::Str("4".to_string()));
+        assert_eq!(fields["splits"], Json::Bool(true));
+        assert_eq!(fields["success_rate"], Json::Number(report.success_rate()));
+        let Json::Array(outcomes) = &fields["outcomes"] else { panic!("no outcomes") };
+        assert_eq!(outcomes.len(), report.rows.len());
+        assert!(outcomes.iter().all(|o| matches!(o, Json::Object(o) if o.len() == 6)));
+        assert!(parse_json("{\"a\": 1,}").is_err());
+    }
+}
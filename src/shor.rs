@@ -0,0 +1,297 @@
+use crate::budget::{Budget, Progress};
+use crate::config::Config;
+use crate::error::FactorError;
+use crate::events::{Event, Observer};
+use crate::period::{PeriodContext, PeriodFinder};
+use crate::math::{gcd, modpow, perfect_power};
+use crate::primality::is_prime;
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{CheckedSub, One};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+// How a split of N was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    // N is even, no need for Shor at all
+    Even,
+    // The random base 'a' already shared a factor with N
+    Gcd { a: BigUint },
+    // The real thing: period r of a^x mod N gave the factor
+    Period { a: BigUint, r: BigUint },
+    // N = base^exponent, p is the base
+    PerfectPower { exponent: u32 },
+}
+
+// A non-trivial split n = p * q
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Factorization {
+    pub n: BigUint,
+    pub p: BigUint,
+    pub q: BigUint,
+    pub method: Method,
+    // Number of values of 'a' tried (0 when N was even)
+    pub attempts: u64,
+    // Seed of the generator that picked the bases, when `factor` created it
+    pub seed: Option<u64>,
+}
+
+impl Factorization {
+    fn new(n: &BigUint, p: BigUint, method: Method, attempts: u64) -> Self {
+        let q = n / &p;
+        Factorization { n: n.clone(), p, q, method, attempts, seed: None }
+    }
+}
+
+// Shor's algorithm implementation, with period finding done by `finder`
+// The bases 'a' come from `rng`, every step is reported to `observer`
+pub fn shors_algorithm<R: Rng + ?Sized>(
+    n: &BigUint,
+    config: &Config,
+    finder: &dyn PeriodFinder,
+    rng: &mut R,
+    observer: &mut dyn Observer,
+) -> Result<Factorization, FactorError> {
+    split(n, config, &Budget::start(config), finder, rng, observer)
+}
+
+// `shors_algorithm` under a budget that may already be running
+pub(crate) fn split<R: Rng + ?Sized>(
+    n: &BigUint,
+    config: &Config,
+    budget: &Budget,
+    finder: &dyn PeriodFinder,
+    rng: &mut R,
+    observer: &mut dyn Observer,
+) -> Result<Factorization, FactorError> {
+    if n <= &BigUint::one() {
+        return Err(FactorError::TooSmall(n.clone()));
+    }
+    if n.is_even() {
+        if n == &BigUint::from(2u32) {
+            return Err(FactorError::Prime(n.clone()));
+        }
+        return Ok(Factorization::new(n, BigUint::from(2u32), Method::Even, 0));
+    }
+    // A prime n would make the loop below run forever, every period is useless
+    if is_prime(n) {
+        return Err(FactorError::Prime(n.clone()));
+    }
+
+    // Prime powers defeat the reduction, every base gives only trivial factors
+    if let Some((base, exponent)) = perfect_power(n) {
+        observer.on_event(&Event::PerfectPower { base: base.clone(), exponent });
+        return Ok(Factorization::new(n, base, Method::PerfectPower { exponent }, 0));
+    }
+
+    let one = BigUint::one();
+    let two = BigUint::from(2u32);
+    let limit = config.period_limit.clone().unwrap_or_else(|| n * n); // A reasonable upper bound heuristic, though not guaranteed
+    let mut attempts = 0u64;
+    // Measurement randomness for the finder, split off once so the bases stay the same
+    let mut finder_rng = ChaCha20Rng::from_seed(rng.r#gen());
+    // Attempts lost to the period limit, reported instead of a plain
+    // exhausted budget when they account for every attempt
+    let mut limit_failures = 0u64;
+    let mut last_limit_error = None;
+    // Useless periods, kept for the report if we give up
+    let mut periods = Vec::new();
+    let progress = |attempts: u64, periods: Vec<(BigUint, BigUint)>| Progress {
+        attempts,
+        elapsed: budget.elapsed(),
+        periods,
+        factors: Vec::new(),
+        remaining: vec![(n.clone(), 1)],
+        seed: None,
+    };
+
+    loop {
+        if config.max_attempts.is_some_and(|max| attempts >= max) {
+            return Err(match last_limit_error {
+                Some(err) if limit_failures == attempts => err,
+                _ => FactorError::AttemptsExhausted(progress(attempts, periods)),
+            });
+        }
+        if let Err(reason) = budget.check() {
+            return Err(FactorError::Interrupted(reason, progress(attempts, periods)));
+        }
+        attempts += 1;
+
+        // 1. Pick a random number 'a' such that 1 < a < n
+        let a = rng.gen_biguint_range(&two, n);
+        observer.on_event(&Event::TryingBase { attempt: attempts, a: a.clone() });
+
+        // 2. Compute gcd(a, n)
+        let common_divisor = gcd(&a, n);
+        if common_divisor > one {
+            observer.on_event(&Event::GcdFactor { a: a.clone(), factor: common_divisor.clone() });
+            return Ok(Factorization::new(n, common_divisor, Method::Gcd { a }, attempts));
+        }
+
+        // 3. Find the period 'r' of a^x mod n
+        // *** This is where the Quantum Fourier Transform would be used on a quantum computer ***
+        observer.on_event(&Event::FindingPeriod { a: a.clone(), backend: finder.name() });
+        let mut ctx = PeriodContext { limit: &limit, budget, rng: &mut finder_rng, observer: &mut *observer };
+        let r = match finder.find_period(&a, n, &mut ctx) {
+            Ok(Some(r)) => r,
+            Err(FactorError::Interrupted(reason, _)) => {
+                return Err(FactorError::Interrupted(reason, progress(attempts, periods)));
+            }
+            Ok(None) => {
+                observer.on_event(&Event::PeriodNotFound { a, backend: finder.name(), error: None });
+                continue;
+            }
+            Err(err @ FactorError::PeriodLimitExceeded { .. }) => {
+                observer.on_event(&Event::PeriodNotFound { a, backend: finder.name(), error: Some(err.clone()) });
+                limit_failures += 1;
+                last_limit_error = Some(err);
+                continue; // Try a different 'a'
+            }
+            // Anything else means the finder cannot work on this n
+            Err(err) => return Err(err),
+        };
+        observer.on_event(&Event::PeriodFound { a: a.clone(), r: r.clone() });
+
+        match factor_from_period(&a, &r, n, observer) {
+            Some(factor) => return Ok(Factorization::new(n, factor, Method::Period { a, r }, attempts)),
+            None => periods.push((a, r)),
+        }
+    }
+}
+
+// Steps 4 to 6 for a period r of a mod n: a non-trivial factor from gcd(a^(r/2) ± 1, n),
+// None when r is odd, a^(r/2) ≡ -1 (mod n) or both gcds are trivial
+pub fn factor_from_period(a: &BigUint, r: &BigUint, n: &BigUint, observer: &mut dyn Observer) -> Option<BigUint> {
+    let one = BigUint::one();
+    let two = BigUint::from(2u32);
+
+    // 4. Check if 'r' is even
+    if r.is_odd() {
+        observer.on_event(&Event::OddPeriod { a: a.clone(), r: r.clone() });
+        return None;
+    }
+
+    // 5. Check if a^(r/2) % n == n - 1 (or a^(r/2) == -1 mod n)
+    let r_half = r / &two;
+    let term = modpow(a, &r_half, n);
+
+    let n_minus_1 = n - &one;
+    if term == n_minus_1 {
+        observer.on_event(&Event::MinusOne { a: a.clone(), r: r.clone() });
+        return None;
+    }
+
+    // 6. Compute factors
+    let factor1 = gcd(&(term.clone() + &one), n);
+    let factor2 = gcd(&(term.checked_sub(&one).unwrap_or_else(|| n.clone() + term.clone() - &one)), n); // Handles potential underflow if term is 0 or 1
+
+    let nontrivial = |f: &BigUint| *f != one && f != n;
+    if nontrivial(&factor1) {
+        observer.on_event(&Event::FactorFound { a: a.clone(), factor: factor1.clone() });
+    }
+    if nontrivial(&factor2) {
+        observer.on_event(&Event::FactorFound { a: a.clone(), factor: factor2.clone() });
+        return Some(factor2);
+    }
+    if nontrivial(&factor1) {
+        return Some(factor1);
+    }
+
+    // If factors are trivial (1 or n), try another 'a'
+    observer.on_event(&Event::TrivialFactors { a: a.clone(), r: r.clone() });
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SilentObserver;
+    use crate::period::{BabyStepGiantStep, Classical};
+    use crate::{factor, seeded_rng};
+
+    fn split_of(n: u64, seed: u64) -> Result<Factorization, FactorError> {
+        factor(&BigUint::from(n), &Config { seed: Some(seed), ..Config::default() })
+    }
+
+    #[test]
+    fn splits_small_semiprimes() {
+        for (n, expected) in [(15u64, [3u64, 5]), (21, [3, 7]), (35, [5, 7]), (143, [11, 13])] {
+            for seed in 0..20 {
+                let found = split_of(n, seed).unwrap();
+                let mut pq = [found.p.clone(), found.q.clone()];
+                pq.sort();
+                assert_eq!(pq, expected.map(BigUint::from), "{} with seed {}", n, seed);
+                assert!(matches!(found.method, Method::Gcd { .. } | Method::Period { .. }));
+                assert!(found.attempts >= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn even_numbers_split_off_a_two() {
+        for n in [4u64, 6, 2 * 15, 8 * 21, 1 << 40, (1 << 20) * 35] {
+            let found = split_of(n, 0).unwrap();
+            assert_eq!((found.p, found.q, found.method, found.attempts), (BigUint::from(2u32), BigUint::from(n / 2), Method::Even, 0));
+        }
+    }
+
+    #[test]
+    fn perfect_powers_skip_the_bases() {
+        let found = split_of(243, 0).unwrap();
+        assert_eq!((found.p, found.q, found.method), (BigUint::from(3u32), BigUint::from(81u32), Method::PerfectPower { exponent: 5 }));
+        assert_eq!(split_of(15 * 15, 0).unwrap().method, Method::PerfectPower { exponent: 2 });
+    }
+
+    #[test]
+    fn refuses_primes_and_tiny_numbers() {
+        for n in [0u64, 1] {
+            assert_eq!(split_of(n, 0), Err(FactorError::TooSmall(BigUint::from(n))));
+        }
+        for n in [2u64, 3, 5, 97, 7919, (1 << 61) - 1] {
+            assert_eq!(split_of(n, 0), Err(FactorError::Prime(BigUint::from(n))));
+        }
+    }
+
+    #[test]
+    fn runs_with_any_finder() {
+        let config = Config::default();
+        for (finder, n) in [(&Classical as &dyn PeriodFinder, 101u64 * 113), (&BabyStepGiantStep::default(), 1_000_003 * 1_000_033)] {
+            let found = shors_algorithm(&BigUint::from(n), &config, finder, &mut seeded_rng(4), &mut SilentObserver).unwrap();
+            assert_eq!(&found.p * &found.q, found.n);
+            assert!(found.p > BigUint::one() && found.q > BigUint::one());
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        // With one attempt and no luck, the period 2 of 14 mod 15 is reported back
+        let config = Config { max_attempts: Some(1), ..Config::default() };
+        let n = BigUint::from(15u32);
+        for seed in 0..50 {
+            match shors_algorithm(&n, &config, &Classical, &mut seeded_rng(seed), &mut SilentObserver) {
+                Ok(found) => assert_eq!(found.attempts, 1),
+                Err(FactorError::AttemptsExhausted(progress)) => {
+                    assert_eq!(progress.attempts, 1);
+                    assert_eq!(progress.periods, vec![(BigUint::from(14u32), BigUint::from(2u32))]);
+                    return;
+                }
+                Err(err) => panic!("unexpected {:?}", err),
+            }
+        }
+        panic!("no seed picked a = 14");
+    }
+
+    #[test]
+    fn factor_from_period_cases() {
+        let n = BigUint::from(15u32);
+        let from = |a: u32, r: u32| factor_from_period(&BigUint::from(a), &BigUint::from(r), &n, &mut SilentObserver);
+        // 7^2 = 4: gcd(5, 15) and gcd(3, 15), the second one wins
+        assert_eq!(from(7, 4), Some(BigUint::from(3u32)));
+        // 14 ≡ -1
+        assert_eq!(from(14, 2), None);
+        assert_eq!(from(4, 3), None);
+        // 4^2 = 1, so gcd(0, 15) = 15 and gcd(2, 15) = 1
+        assert_eq!(from(4, 4), None);
+    }
+}